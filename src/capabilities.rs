@@ -0,0 +1,97 @@
+//! # Router Capabilities
+//! The roles and advanced profile features a router announced in its `Welcome`.
+//!
+//! ## Examples
+//! ```
+//! use wamp_client::capabilities::RouterCapabilities;
+//! use wamp_core::messages::Welcome;
+//! use wamp_core::serde_json::json;
+//!
+//! let welcome = Welcome {
+//!     session: 1,
+//!     details: json!({
+//!         "roles": {
+//!             "broker": { "features": { "publisher_exclusion": true, "pattern_based_subscription": true } },
+//!             "dealer": { "features": { "call_canceling": true, "progressive_call_results": false } }
+//!         }
+//!     }),
+//! };
+//!
+//! let capabilities = RouterCapabilities::from(&welcome);
+//! let broker = capabilities.broker.unwrap();
+//! let dealer = capabilities.dealer.unwrap();
+//!
+//! assert!(broker.publisher_exclusion);
+//! assert!(broker.pattern_based_subscription);
+//! assert!(!broker.event_retention);
+//! assert!(dealer.call_canceling);
+//! assert!(!dealer.progressive_call_results);
+//! ```
+
+use serde::{Deserialize, Serialize};
+use wamp_core::messages::Welcome;
+use wamp_core::serde_json::{self, Value};
+
+/// Features announced by a router acting as a broker.
+#[derive(Debug, Clone, PartialEq, Eq, Default, Serialize, Deserialize)]
+#[serde(default)]
+pub struct BrokerFeatures {
+    pub pattern_based_subscription: bool,
+    pub publisher_exclusion: bool,
+    pub publisher_identification: bool,
+    pub subscriber_blackwhite_listing: bool,
+    pub publication_trustlevels: bool,
+    pub event_history: bool,
+    pub event_retention: bool,
+    pub session_meta_api: bool,
+    pub subscription_meta_api: bool,
+    pub payload_passthru_mode: bool,
+}
+
+/// Features announced by a router acting as a dealer.
+#[derive(Debug, Clone, PartialEq, Eq, Default, Serialize, Deserialize)]
+#[serde(default)]
+pub struct DealerFeatures {
+    pub progressive_call_results: bool,
+    pub call_canceling: bool,
+    pub call_timeout: bool,
+    pub caller_identification: bool,
+    pub call_trustlevels: bool,
+    pub pattern_based_registration: bool,
+    pub shared_registration: bool,
+    pub session_meta_api: bool,
+    pub registration_meta_api: bool,
+    pub payload_passthru_mode: bool,
+}
+
+/// Roles a router announced, `None` for a role it does not play.
+#[derive(Debug, Clone, PartialEq, Eq, Default, Serialize, Deserialize)]
+pub struct RouterCapabilities {
+    pub broker: Option<BrokerFeatures>,
+    pub dealer: Option<DealerFeatures>,
+}
+
+impl RouterCapabilities {
+    /// Parse the `roles` of `Welcome.details`. Missing or malformed feature maps are read as
+    /// no features announced.
+    pub fn from_details(details: &Value) -> Self {
+        let roles = &details["roles"];
+        Self {
+            broker: role_features(&roles["broker"]),
+            dealer: role_features(&roles["dealer"]),
+        }
+    }
+}
+
+impl<'a> From<&'a Welcome> for RouterCapabilities {
+    fn from(welcome: &'a Welcome) -> Self {
+        Self::from_details(&welcome.details)
+    }
+}
+
+fn role_features<T: Default + for<'de> Deserialize<'de>>(role: &Value) -> Option<T> {
+    if role.is_null() {
+        return None;
+    }
+    Some(serde_json::from_value(role["features"].clone()).unwrap_or_default())
+}
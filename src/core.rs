@@ -1,6 +1,108 @@
-use std::sync::{Mutex, Arc};
-use wamp_core::tungstenite::WebSocket;
+use std::io::{self, ErrorKind};
+use std::sync::{Mutex, Arc, PoisonError};
+use std::time::{Duration, Instant};
+use wamp_core::tungstenite::{self, Message, WebSocket};
 use wamp_core::tungstenite::stream::MaybeTlsStream;
 use std::net::TcpStream;
+#[cfg(feature = "native-tls")]
+use wamp_core::http::Response;
+#[cfg(feature = "native-tls")]
+use wamp_core::tungstenite::{
+    client::IntoClientRequest, client_tls_with_config, error::UrlError,
+    handshake::HandshakeError, protocol::WebSocketConfig, Connector,
+};
 
-pub(crate) type Socket = Arc<Mutex<WebSocket<MaybeTlsStream<TcpStream>>>>;
\ No newline at end of file
+pub(crate) type Socket = Arc<Mutex<WebSocket<MaybeTlsStream<TcpStream>>>>;
+
+/// Longest a blocking read waits on the socket at once, before looking up from it.
+pub(crate) const IDLE_WAIT: Duration = Duration::from_secs(60);
+
+/// How long a read waits for a frame while holding the socket lock, before letting writers in.
+const LOCKED_READ: Duration = Duration::from_millis(10);
+
+/// The TCP stream under a WebSocket, `None` for TLS backends it cannot be reached through.
+pub(crate) fn tcp_stream(socket: &WebSocket<MaybeTlsStream<TcpStream>>) -> Option<&TcpStream> {
+    match socket.get_ref() {
+        MaybeTlsStream::Plain(stream) => Some(stream),
+        #[cfg(feature = "native-tls")]
+        MaybeTlsStream::NativeTls(stream) => Some(stream.get_ref()),
+        _ => None,
+    }
+}
+
+/// Whether an IO error is a read timeout, or a non blocking read finding nothing.
+pub(crate) fn is_timeout(error: &io::Error) -> bool {
+    matches!(error.kind(), ErrorKind::WouldBlock | ErrorKind::TimedOut)
+}
+
+/// # Read Timeout
+/// Read the next message from a shared socket, returning `None` if none arrives within
+/// `timeout`.
+///
+/// The socket lock is only held for short reads, which also return frames tungstenite has
+/// already buffered. In between, the wait for new bytes happens on a clone of the TCP stream
+/// without the lock, so other threads can write pings and requests while the connection is
+/// idle. TLS backends the TCP stream cannot be reached through block with the lock held.
+pub(crate) fn read_timeout(socket: &Socket, timeout: Duration) -> tungstenite::Result<Option<Message>> {
+    let deadline = Instant::now() + timeout;
+    loop {
+        let stream = {
+            let socket = &mut *socket.lock().unwrap_or_else(PoisonError::into_inner);
+            let stream = match tcp_stream(socket) {
+                Some(stream) => stream.try_clone()?,
+                None => return socket.read().map(Some),
+            };
+            stream.set_read_timeout(Some(LOCKED_READ))?;
+            let message = socket.read();
+            stream.set_read_timeout(None)?;
+            match message {
+                Ok(message) => return Ok(Some(message)),
+                // A frame cut short by the timeout stays buffered in tungstenite.
+                Err(tungstenite::Error::Io(error)) if is_timeout(&error) => stream,
+                Err(error) => return Err(error),
+            }
+        };
+
+        let remaining = deadline.saturating_duration_since(Instant::now());
+        if remaining == Duration::from_secs(0) {
+            return Ok(None);
+        }
+        stream.set_read_timeout(Some(remaining))?;
+        let ready = stream.peek(&mut [0; 1]);
+        stream.set_read_timeout(None)?;
+        match ready {
+            Ok(_) => {}
+            Err(error) if is_timeout(&error) => return Ok(None),
+            Err(error) => return Err(error.into()),
+        }
+    }
+}
+
+/// Connect like `tungstenite::connect_with_config`, wrapping the stream with `connector`
+/// instead of the default TLS settings.
+#[cfg(feature = "native-tls")]
+pub(crate) fn connect_with_connector<R: IntoClientRequest>(
+    request: R,
+    config: Option<WebSocketConfig>,
+    connector: Connector,
+) -> tungstenite::Result<(WebSocket<MaybeTlsStream<TcpStream>>, Response<Option<Vec<u8>>>)> {
+    let request = request.into_client_request()?;
+    let host = request
+        .uri()
+        .host()
+        .ok_or(tungstenite::Error::Url(UrlError::NoHostName))?
+        .to_string();
+    let port = request
+        .uri()
+        .port_u16()
+        .unwrap_or(match request.uri().scheme_str() {
+            Some("wss") => 443,
+            _ => 80,
+        });
+    let stream = TcpStream::connect((host.as_str(), port))?;
+    client_tls_with_config(request, stream, config, Some(connector)).map_err(|error| match error {
+        HandshakeError::Failure(error) => error,
+        // Only a non blocking stream interrupts the handshake.
+        HandshakeError::Interrupted(_) => tungstenite::Error::Io(io::ErrorKind::WouldBlock.into()),
+    })
+}
\ No newline at end of file
@@ -1,16 +1,208 @@
+use std::convert::Infallible;
+use std::fmt;
+use std::io;
 use std::time::SystemTimeError;
 
 use wamp_core::WampError;
+use wamp_core::messages::{Abort, Messages};
+use wamp_core::serde_json;
+use wamp_core::tungstenite;
 
+use crate::messages::{AbortExt, PayloadField};
+use crate::sync::client::ConnectionState;
 
-
-
+/// # Error
+/// Everything that can go wrong in the client. Wrapped errors are available through
+/// `std::error::Error::source`.
+///
+/// ## Examples
+/// ```
+/// use std::collections::HashSet;
+/// use wamp_client::error::Error;
+/// use wamp_client::messages::{wamp_error, PayloadField};
+/// use wamp_client::sync::client::ConnectionState;
+/// use wamp_core::messages::{Abort, WampErrorEvent};
+/// use wamp_core::serde_json::{self, json};
+///
+/// let errors = vec![
+///     Error::TimeOutError("no reply"),
+///     Error::NoSubscription,
+///     Error::SerdeJsonError(serde_json::from_str::<u8>("{").unwrap_err()),
+///     Error::Abort(Abort { details: json!({}), reason: "wamp.error.no_such_realm".to_string() }),
+///     Error::DuplicateRequestId(1),
+///     Error::ConnectionClosed,
+///     Error::NotEstablished(ConnectionState::Connecting),
+///     Error::InvalidHello("no roles"),
+///     Error::HelloNotSent,
+///     Error::BudgetExceeded(64),
+///     Error::PayloadError(PayloadField::Kwargs, serde_json::from_str::<u8>("{").unwrap_err()),
+///     Error::Error("something else"),
+/// ];
+///
+/// let messages: HashSet<String> = errors.iter().map(|error| error.to_string()).collect();
+/// assert!(messages.iter().all(|message| !message.is_empty()));
+/// assert_eq!(messages.len(), errors.len());
+///
+/// let error: Box<dyn std::error::Error> = Box::new(Error::ConnectionClosed);
+/// assert!(error.source().is_none());
+///
+/// let error: Box<dyn std::error::Error> =
+///     Box::new(Error::from(wamp_error(WampErrorEvent::Call, 7, "wamp.error.canceled")));
+/// assert!(error.source().is_some());
+/// assert_eq!(format!("{:?}", Error::HelloNotSent), "HelloNotSent");
+/// ```
+#[derive(Debug)]
 pub enum Error {
     WampCoreError(wamp_core::Error),
     TimeOutError(&'static str),
     SystemTimeError(SystemTimeError),
     NoSubscription,
-    WampMessageError(WampError)
+    WampMessageError(WampError),
+    TungsteniteError(tungstenite::Error),
+    SerdeJsonError(serde_json::Error),
+    IoError(io::Error),
+    MsgPackEncodeError(rmp_serde::encode::Error),
+    MsgPackDecodeError(rmp_serde::decode::Error),
+    InvalidFrameReceived(Messages),
+    Abort(Abort),
+    DuplicateRequestId(u64),
+    /// The WebSocket connection is closed, reading or sending again needs a new connection.
+    ///
+    /// ```
+    /// use wamp_client::error::Error;
+    /// use wamp_core::tungstenite;
+    ///
+    /// assert!(matches!(Error::from(tungstenite::Error::ConnectionClosed), Error::ConnectionClosed));
+    /// assert!(matches!(Error::from(tungstenite::Error::AlreadyClosed), Error::ConnectionClosed));
+    /// ```
+    ///
+    /// Sending after the client closed the session:
+    /// ```
+    /// use wamp_client::error::Error;
+    /// use wamp_client::sync::client::Client;
+    /// use wamp_client::sync::WampRequest;
+    /// use wamp_client::test_util::{establish, MockRouter};
+    /// use wamp_core::publish;
+    ///
+    /// let (uri, router) = MockRouter::spawn(move |router| {
+    ///     router.welcome();
+    ///     router.socket().read().unwrap();
+    ///     router.send(r#"[6,{},"wamp.close.goodbye_and_out"]"#);
+    /// });
+    ///
+    /// let (mut client, _) = Client::connect(WampRequest { uri, protocol: "wamp.2.json" }).unwrap();
+    /// establish(&mut client);
+    /// client.close("wamp.close.system_shutdown").unwrap();
+    ///
+    /// assert!(matches!(client.send(publish!("topic")), Err(Error::ConnectionClosed)));
+    /// router.join().unwrap();
+    /// ```
+    ///
+    /// Reading after the router closed the connection returns it instead of panicking:
+    /// ```
+    /// use wamp_client::error::Error;
+    /// use wamp_client::hello::HelloBuilder;
+    /// use wamp_client::sync::client::{Client, ConnectionState};
+    /// use wamp_client::sync::WampRequest;
+    /// use wamp_client::test_util::MockRouter;
+    ///
+    /// let (uri, router) = MockRouter::spawn(move |router| {
+    ///     router.welcome();
+    ///     router.socket().close(None).unwrap();
+    /// });
+    ///
+    /// let (mut client, _) = Client::connect(WampRequest { uri, protocol: "wamp.2.json" }).unwrap();
+    /// client.send(HelloBuilder::new("realm1").build().unwrap()).unwrap();
+    /// let error = loop {
+    ///     match client.read() {
+    ///         Ok(message) => {
+    ///             client.read_contexts(message).unwrap();
+    ///         }
+    ///         Err(error) => break error,
+    ///     }
+    /// };
+    ///
+    /// assert!(matches!(error, Error::ConnectionClosed));
+    /// assert_eq!(client.state(), ConnectionState::Closed);
+    /// assert!(matches!(client.read(), Err(Error::ConnectionClosed)));
+    /// router.join().unwrap();
+    /// ```
+    ConnectionClosed,
+    NotEstablished(ConnectionState),
+    InvalidHello(&'static str),
+    HelloNotSent,
+    BudgetExceeded(usize),
+    /// The args or kwargs of a message did not deserialize into the requested type.
+    PayloadError(PayloadField, serde_json::Error),
+    Error(&'static str)
+}
+
+impl fmt::Display for Error {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Error::WampCoreError(error) => write!(f, "wamp core error: {:?}", error),
+            Error::TimeOutError(message) => write!(f, "timed out: {}", message),
+            Error::SystemTimeError(error) => write!(f, "system time error: {}", error),
+            Error::NoSubscription => write!(f, "no subscription has been established"),
+            Error::WampMessageError(error) => write!(
+                f,
+                "router replied with error {} to request {}",
+                error.error, error.request_id
+            ),
+            Error::TungsteniteError(error) => write!(f, "websocket error: {}", error),
+            Error::SerdeJsonError(error) => write!(f, "json error: {}", error),
+            Error::IoError(error) => write!(f, "io error: {}", error),
+            Error::MsgPackEncodeError(error) => write!(f, "msgpack encode error: {}", error),
+            Error::MsgPackDecodeError(error) => write!(f, "msgpack decode error: {}", error),
+            Error::InvalidFrameReceived(message) => {
+                write!(f, "received a frame a client should never receive: {:?}", message)
+            }
+            Error::Abort(abort) => match abort.message() {
+                Some(message) => write!(f, "session aborted with {}: {}", abort.reason(), message),
+                None => write!(f, "session aborted with {}", abort.reason()),
+            },
+            Error::DuplicateRequestId(request_id) => {
+                write!(f, "request id {} is already waiting for a reply", request_id)
+            }
+            Error::ConnectionClosed => write!(f, "the websocket connection is closed"),
+            Error::NotEstablished(state) => {
+                write!(f, "the session is not established, the client is {:?}", state)
+            }
+            Error::InvalidHello(message) => write!(f, "invalid hello: {}", message),
+            Error::BudgetExceeded(budget) => {
+                write!(f, "queued messages would exceed the byte budget of {}", budget)
+            }
+            Error::PayloadError(PayloadField::Args, error) => {
+                write!(f, "could not deserialize the args: {}", error)
+            }
+            Error::PayloadError(PayloadField::Kwargs, error) => {
+                write!(f, "could not deserialize the kwargs: {}", error)
+            }
+            Error::HelloNotSent => write!(
+                f,
+                "no Hello was sent, so the router will never reply; send one with Client::send \
+                 before reading"
+            ),
+            Error::Error(message) => write!(f, "{}", message),
+        }
+    }
+}
+
+impl std::error::Error for Error {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            Error::WampCoreError(error) => Some(error),
+            Error::WampMessageError(error) => Some(error),
+            Error::SystemTimeError(error) => Some(error),
+            Error::TungsteniteError(error) => Some(error),
+            Error::SerdeJsonError(error) => Some(error),
+            Error::PayloadError(_, error) => Some(error),
+            Error::IoError(error) => Some(error),
+            Error::MsgPackEncodeError(error) => Some(error),
+            Error::MsgPackDecodeError(error) => Some(error),
+            _ => None,
+        }
+    }
 }
 
 impl From<wamp_core::Error> for Error {
@@ -29,4 +221,46 @@ impl From<SystemTimeError> for Error {
     fn from(value: SystemTimeError) -> Self {
         Error::SystemTimeError(value)
     }
-}
\ No newline at end of file
+}
+
+impl From<tungstenite::Error> for Error {
+    fn from(value: tungstenite::Error) -> Self {
+        match value {
+            // Using a closed socket is recoverable by reconnecting, so it gets its own variant.
+            tungstenite::Error::ConnectionClosed | tungstenite::Error::AlreadyClosed => {
+                Error::ConnectionClosed
+            }
+            value => Error::TungsteniteError(value),
+        }
+    }
+}
+
+impl From<serde_json::Error> for Error {
+    fn from(value: serde_json::Error) -> Self {
+        Error::SerdeJsonError(value)
+    }
+}
+
+impl From<io::Error> for Error {
+    fn from(value: io::Error) -> Self {
+        Error::IoError(value)
+    }
+}
+
+impl From<rmp_serde::encode::Error> for Error {
+    fn from(value: rmp_serde::encode::Error) -> Self {
+        Error::MsgPackEncodeError(value)
+    }
+}
+
+impl From<rmp_serde::decode::Error> for Error {
+    fn from(value: rmp_serde::decode::Error) -> Self {
+        Error::MsgPackDecodeError(value)
+    }
+}
+
+impl From<Infallible> for Error {
+    fn from(value: Infallible) -> Self {
+        match value {}
+    }
+}
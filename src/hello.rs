@@ -0,0 +1,215 @@
+//! # Hello
+//! Build the `Hello` that opens a session, with the roles and role features the client actually
+//! uses advertised to the router.
+//!
+//! ## Examples
+//! ```
+//! use wamp_client::hello::{Feature, HelloBuilder};
+//!
+//! let hello = HelloBuilder::new("realm1")
+//!     .feature(Feature::CallCanceling)
+//!     .build()
+//!     .unwrap();
+//!
+//! assert_eq!(hello.realm, "realm1");
+//! assert_eq!(hello.details["agent"], concat!("wamp-client/", env!("CARGO_PKG_VERSION")));
+//! // Call canceling involves both ends of a call, and neither pub/sub role.
+//! assert_eq!(hello.details["roles"]["caller"]["features"]["call_canceling"], true);
+//! assert_eq!(hello.details["roles"]["callee"]["features"]["call_canceling"], true);
+//! assert!(hello.details["roles"]["publisher"]["features"].get("call_canceling").is_none());
+//!
+//! // A session needs at least one role.
+//! assert!(HelloBuilder::new("realm1").roles(vec![]).build().is_err());
+//!
+//! let hello = HelloBuilder::new("realm1").agent("sensor-gateway/2.1").build().unwrap();
+//! assert_eq!(hello.details["agent"], "sensor-gateway/2.1");
+//! ```
+
+use std::convert::TryFrom;
+use wamp_core::messages::Hello;
+use wamp_core::serde_json::{json, Map, Value};
+
+use crate::error::Error;
+use crate::sync::auth::AuthConfig;
+
+/// The `agent` advertised unless `HelloBuilder::agent` overrides it.
+pub const DEFAULT_AGENT: &str = concat!(env!("CARGO_PKG_NAME"), "/", env!("CARGO_PKG_VERSION"));
+
+/// A WAMP client role, advertised in the `roles` of `Hello.details`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Role {
+    Caller,
+    Callee,
+    Publisher,
+    Subscriber,
+}
+
+impl Role {
+    /// Every client role, in the order they are advertised.
+    pub const ALL: [Role; 4] = [Role::Caller, Role::Callee, Role::Publisher, Role::Subscriber];
+
+    /// Name of the role in the `roles` map.
+    pub fn name(&self) -> &'static str {
+        match self {
+            Role::Caller => "caller",
+            Role::Callee => "callee",
+            Role::Publisher => "publisher",
+            Role::Subscriber => "subscriber",
+        }
+    }
+}
+
+/// An advanced profile feature, advertised under every role that takes part in it.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Feature {
+    ProgressiveCallResults,
+    CallCanceling,
+    CallTimeout,
+    CallerIdentification,
+    PatternBasedRegistration,
+    SharedRegistration,
+    PublisherExclusion,
+    PublisherIdentification,
+    PatternBasedSubscription,
+    SubscriberBlackwhiteListing,
+    EventRetention,
+}
+
+impl Feature {
+    /// Name of the feature in a role's `features` map.
+    pub fn name(&self) -> &'static str {
+        match self {
+            Feature::ProgressiveCallResults => "progressive_call_results",
+            Feature::CallCanceling => "call_canceling",
+            Feature::CallTimeout => "call_timeout",
+            Feature::CallerIdentification => "caller_identification",
+            Feature::PatternBasedRegistration => "pattern_based_registration",
+            Feature::SharedRegistration => "shared_registration",
+            Feature::PublisherExclusion => "publisher_exclusion",
+            Feature::PublisherIdentification => "publisher_identification",
+            Feature::PatternBasedSubscription => "pattern_based_subscription",
+            Feature::SubscriberBlackwhiteListing => "subscriber_blackwhite_listing",
+            Feature::EventRetention => "event_retention",
+        }
+    }
+
+    /// The client roles that advertise the feature.
+    pub fn roles(&self) -> &'static [Role] {
+        match self {
+            Feature::ProgressiveCallResults
+            | Feature::CallCanceling
+            | Feature::CallTimeout
+            | Feature::CallerIdentification => &[Role::Caller, Role::Callee],
+            Feature::PatternBasedRegistration | Feature::SharedRegistration => &[Role::Callee],
+            Feature::PublisherExclusion
+            | Feature::PublisherIdentification
+            | Feature::SubscriberBlackwhiteListing => &[Role::Publisher],
+            Feature::PatternBasedSubscription | Feature::EventRetention => &[Role::Subscriber],
+        }
+    }
+}
+
+/// # Hello Builder
+/// Accumulates the realm, roles and features of a `Hello`, then builds it with `build`.
+///
+/// All four client roles are advertised unless `roles` narrows them down. Each enabled feature
+/// is added to the `features` map of the roles it applies to, and `detail` overrides anything
+/// derived, for routers that need something the builder does not model.
+#[derive(Debug, Clone)]
+pub struct HelloBuilder {
+    realm: String,
+    agent: String,
+    roles: Vec<Role>,
+    features: Vec<Feature>,
+    details: Map<String, Value>,
+}
+
+impl HelloBuilder {
+    pub fn new<R: ToString>(realm: R) -> Self {
+        Self {
+            realm: realm.to_string(),
+            agent: DEFAULT_AGENT.to_string(),
+            roles: Role::ALL.to_vec(),
+            features: vec![],
+            details: Map::new(),
+        }
+    }
+
+    /// The client implementation and version routers log for the session, `DEFAULT_AGENT`
+    /// unless set.
+    pub fn agent<A: ToString>(mut self, agent: A) -> Self {
+        self.agent = agent.to_string();
+        self
+    }
+
+    /// Advertise only the given roles.
+    pub fn roles(mut self, roles: Vec<Role>) -> Self {
+        self.roles = roles;
+        self
+    }
+
+    /// Advertise a feature under every advertised role that takes part in it.
+    pub fn feature(mut self, feature: Feature) -> Self {
+        if !self.features.contains(&feature) {
+            self.features.push(feature);
+        }
+        self
+    }
+
+    /// Override a top level entry of `Hello.details`, replacing what the builder derived.
+    pub fn detail<K: ToString>(mut self, key: K, value: Value) -> Self {
+        self.details.insert(key.to_string(), value);
+        self
+    }
+
+    /// Advertise the auth methods and `authid` of `auth`.
+    pub fn auth(self, auth: &AuthConfig) -> Self {
+        let builder = self.detail("authmethods", json!(auth.methods()));
+        match auth.authid() {
+            Some(authid) => builder.detail("authid", json!(authid)),
+            None => builder,
+        }
+    }
+
+    /// The `Hello.details` the builder derives, with overrides applied.
+    pub fn details(&self) -> Value {
+        let mut roles = Map::new();
+        for role in &self.roles {
+            let mut features = Map::new();
+            for feature in &self.features {
+                if feature.roles().contains(role) {
+                    features.insert(feature.name().to_string(), json!(true));
+                }
+            }
+            roles.insert(role.name().to_string(), json!({ "features": features }));
+        }
+
+        let mut details = Map::new();
+        details.insert("agent".to_string(), json!(self.agent));
+        details.insert("roles".to_string(), Value::Object(roles));
+        for (key, value) in &self.details {
+            details.insert(key.clone(), value.clone());
+        }
+        Value::Object(details)
+    }
+
+    /// Build the `Hello`, returning `Error::InvalidHello` if no role is advertised, since the
+    /// router would abort the session.
+    pub fn build(&self) -> Result<Hello, Error> {
+        if self.roles.is_empty() {
+            return Err(Error::InvalidHello("a Hello must advertise at least one role"));
+        }
+        Ok(Hello {
+            realm: self.realm.clone(),
+            details: self.details(),
+        })
+    }
+}
+
+impl TryFrom<HelloBuilder> for Hello {
+    type Error = Error;
+
+    fn try_from(builder: HelloBuilder) -> Result<Self, Error> {
+        builder.build()
+    }
+}
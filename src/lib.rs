@@ -1,5 +1,25 @@
 extern crate wamp_core;
+extern crate serde;
+extern crate rand;
+extern crate rmp_serde;
+extern crate hmac;
+extern crate sha2;
+extern crate pbkdf2;
+extern crate base64;
+#[cfg(feature = "async")]
+extern crate tokio_tungstenite;
+#[cfg(feature = "async")]
+extern crate futures_util;
+pub mod capabilities;
 pub mod core;
 pub mod sync;
 pub mod threads;
-pub mod error;
\ No newline at end of file
+#[cfg(feature = "async")]
+pub mod tokio;
+pub mod error;
+pub mod hello;
+pub mod messages;
+pub mod options;
+pub mod reconnect;
+#[cfg(feature = "test-util")]
+pub mod test_util;
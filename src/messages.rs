@@ -0,0 +1,548 @@
+use serde::de::{self, DeserializeOwned, DeserializeSeed, IgnoredAny, SeqAccess, Visitor};
+use serde::{Deserializer, Serialize};
+use std::convert::TryInto;
+use std::fmt;
+use std::marker::PhantomData;
+use wamp_core::http::Response;
+use wamp_core::messages::*;
+use wamp_core::publish;
+use wamp_core::serde_json::{self, json, to_string, to_value, Value};
+use wamp_core::tungstenite::Message;
+
+use crate::error::Error;
+use crate::options::{CancelMode, PublishOptions};
+use crate::sync::negotiated_protocol;
+
+/// # Abort Accessors
+/// Typed access to why the router refused or aborted a session.
+///
+/// ## Examples
+/// ```
+/// use wamp_client::messages::AbortExt;
+/// use wamp_core::messages::Abort;
+/// use wamp_core::serde_json::json;
+///
+/// let abort = Abort {
+///     details: json!({ "message": "The realm does not exist." }),
+///     reason: "wamp.error.no_such_realm".to_string(),
+/// };
+///
+/// assert_eq!(abort.reason(), "wamp.error.no_such_realm");
+/// assert_eq!(abort.message(), Some("The realm does not exist."));
+/// ```
+pub trait AbortExt {
+    /// The reason URI, such as `wamp.error.no_such_realm`.
+    fn reason(&self) -> &str;
+    /// The human readable `message` from the abort details, if the router sent one.
+    fn message(&self) -> Option<&str>;
+}
+
+impl AbortExt for Abort {
+    fn reason(&self) -> &str {
+        &self.reason
+    }
+
+    fn message(&self) -> Option<&str> {
+        self.details.get("message").and_then(Value::as_str)
+    }
+}
+
+/// # Interrupt Accessors
+/// Typed access to how the caller wants an interrupted invocation ended.
+///
+/// With `CancelMode::Kill` the dealer waits for the callee, which should stop the invocation and
+/// answer it with an `Error` such as `wamp.error.canceled`. With `CancelMode::KillNoWait` the
+/// dealer has already answered the caller, so the callee should stop without replying, as any
+/// reply is discarded.
+///
+/// ## Examples
+/// ```
+/// use wamp_client::messages::InterruptExt;
+/// use wamp_client::options::CancelMode;
+/// use wamp_core::messages::Interrupt;
+/// use wamp_core::serde_json::json;
+///
+/// let interrupt = Interrupt { request_id: 1, options: json!({ "mode": "kill" }) };
+/// assert_eq!(interrupt.mode(), Some(CancelMode::Kill));
+///
+/// let interrupt = Interrupt { request_id: 1, options: json!({}) };
+/// assert_eq!(interrupt.mode(), None);
+/// ```
+pub trait InterruptExt {
+    /// The `mode` option, `None` if the dealer sent none or one the client does not know.
+    fn mode(&self) -> Option<CancelMode>;
+}
+
+impl InterruptExt for Interrupt {
+    fn mode(&self) -> Option<CancelMode> {
+        self.options
+            .get("mode")
+            .and_then(|mode| serde_json::from_value(mode.clone()).ok())
+    }
+}
+
+/// # Payload Accessors
+/// Deserialize the positional `args` or keyword `kwargs` of a message into a type of your own,
+/// instead of picking them out of the raw `Value`s. A payload that does not fit the type returns
+/// `Error::PayloadError`.
+///
+/// ## Examples
+/// ```
+/// use serde::Deserialize;
+/// use wamp_client::error::Error;
+/// use wamp_client::messages::{PayloadExt, PayloadField};
+/// use wamp_core::messages::Messages;
+/// use wamp_core::serde_json::from_str;
+///
+/// #[derive(Deserialize)]
+/// struct Reading {
+///     sensor: String,
+///     celsius: f64,
+/// }
+///
+/// let event = match from_str(r#"[36,1,2,{},["kitchen",21.5],{"sensor":"kitchen","celsius":21.5}]"#) {
+///     Ok(Messages::Event(event)) => event,
+///     _ => unreachable!(),
+/// };
+///
+/// let (sensor, celsius): (String, f64) = event.args_as().unwrap();
+/// assert_eq!((sensor.as_str(), celsius), ("kitchen", 21.5));
+///
+/// let reading: Reading = event.kwargs_as().unwrap();
+/// assert_eq!((reading.sensor.as_str(), reading.celsius), ("kitchen", 21.5));
+///
+/// let result = match from_str(r#"[50,1,{},[],{"celsius":"hot"}]"#) {
+///     Ok(Messages::Result(result)) => result,
+///     _ => unreachable!(),
+/// };
+/// assert!(matches!(result.kwargs_as::<Reading>(), Err(Error::PayloadError(PayloadField::Kwargs, _))));
+/// ```
+pub trait PayloadExt {
+    fn args_as<T: DeserializeOwned>(&self) -> Result<T, Error>;
+    fn kwargs_as<T: DeserializeOwned>(&self) -> Result<T, Error>;
+}
+
+macro_rules! impl_payload_ext {
+    ($($message: ty),*) => {
+        $(
+            impl PayloadExt for $message {
+                fn args_as<T: DeserializeOwned>(&self) -> Result<T, Error> {
+                    serde_json::from_value(self.args.clone())
+                        .map_err(|error| Error::PayloadError(PayloadField::Args, error))
+                }
+
+                fn kwargs_as<T: DeserializeOwned>(&self) -> Result<T, Error> {
+                    serde_json::from_value(self.kwargs.clone())
+                        .map_err(|error| Error::PayloadError(PayloadField::Kwargs, error))
+                }
+            }
+        )*
+    };
+}
+
+impl_payload_ext!(Event, Invocation, WampResult, WampError);
+
+/// # Event Accessors
+/// Typed access to the details of an `Event`.
+///
+/// An event delivered for a prefix or wildcard subscription carries the concrete topic it was
+/// published to in its `topic` detail, since the subscription only names the pattern.
+///
+/// ## Examples
+/// ```
+/// use wamp_client::messages::EventExt;
+/// use wamp_core::messages::Messages;
+/// use wamp_core::serde_json::from_str;
+///
+/// let event = match from_str(r#"[36,1,2,{"topic":"com.myapp.sensor.kitchen"},[21.5]]"#) {
+///     Ok(Messages::Event(event)) => event,
+///     _ => unreachable!(),
+/// };
+/// assert_eq!(event.topic(), Some("com.myapp.sensor.kitchen"));
+/// ```
+pub trait EventExt {
+    /// The concrete topic of an event matched by a pattern, `None` for exact subscriptions.
+    fn topic(&self) -> Option<&str>;
+}
+
+impl EventExt for Event {
+    fn topic(&self) -> Option<&str> {
+        self.details.get("topic").and_then(Value::as_str)
+    }
+}
+
+/// # Serializer
+/// The serialization a WAMP session uses on the wire, negotiated with the WebSocket subprotocol.
+///
+/// Frames are built as JSON text by `wamp_core`, so `encode` transcodes them to the session's
+/// serialization before sending, and `decode` transcodes received frames back to JSON text.
+///
+/// ## Examples
+/// Every message type round trips through MessagePack:
+/// ```
+/// use wamp_client::messages::Serializer;
+/// use wamp_core::messages::Messages;
+/// use wamp_core::serde_json::{from_str, Value};
+/// use wamp_core::tungstenite::Message;
+///
+/// let frames = [
+///     r#"[1,"realm1",{"roles":{"caller":{}}}]"#,
+///     r#"[2,1,{"roles":{"dealer":{}}}]"#,
+///     r#"[3,{"message":"no"},"wamp.error.no_such_realm"]"#,
+///     r#"[4,"wampcra",{"challenge":"abc"}]"#,
+///     r#"[5,"signature",{}]"#,
+///     r#"[6,{},"wamp.close.close_realm"]"#,
+///     r#"[8,48,7,{},"wamp.error.runtime_error",[1.5],{"reason":"failed"}]"#,
+///     r#"[16,2,{"acknowledge":true},"topic",[1],{"a":null}]"#,
+///     r#"[17,2,3]"#,
+///     r#"[32,4,{"match":"prefix"},"topic"]"#,
+///     r#"[33,4,5]"#,
+///     r#"[34,6,5]"#,
+///     r#"[35,6]"#,
+///     r#"[36,5,7,{},["hello"],{"n":-1}]"#,
+///     r#"[48,8,{},"procedure",[1,2],{}]"#,
+///     r#"[49,8,{"mode":"kill"}]"#,
+///     r#"[50,8,{},[3]]"#,
+///     r#"[64,9,{},"procedure"]"#,
+///     r#"[65,9,10]"#,
+///     r#"[66,11,10]"#,
+///     r#"[67,11]"#,
+///     r#"[68,12,10,{},[1],{}]"#,
+///     r#"[69,12,{}]"#,
+///     r#"[70,12,{},[2]]"#,
+/// ];
+///
+/// for frame in frames.iter() {
+///     let sent = Serializer::MsgPack.encode(Message::Text(frame.to_string())).unwrap();
+///     assert!(sent.is_binary());
+///
+///     let received = Serializer::MsgPack.decode(sent).unwrap().unwrap();
+///     assert_eq!(from_str::<Value>(&received).unwrap(), from_str::<Value>(frame).unwrap());
+///     from_str::<Messages>(&received).unwrap();
+/// }
+/// ```
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Serializer {
+    /// `wamp.2.json`, frames are sent as WebSocket text messages.
+    Json,
+    /// `wamp.2.msgpack`, frames are sent as WebSocket binary messages.
+    MsgPack,
+}
+
+impl Serializer {
+    /// The WebSocket subprotocol that negotiates the serialization.
+    pub fn protocol(&self) -> &'static str {
+        match self {
+            Serializer::Json => "wamp.2.json",
+            Serializer::MsgPack => "wamp.2.msgpack",
+        }
+    }
+
+    /// The serialization negotiated by a WebSocket subprotocol, if it is one the client speaks.
+    pub fn from_protocol(protocol: &str) -> Option<Self> {
+        match protocol.trim() {
+            "wamp.2.json" => Some(Serializer::Json),
+            "wamp.2.msgpack" => Some(Serializer::MsgPack),
+            _ => None,
+        }
+    }
+
+    /// The serialization picked by the `Sec-WebSocket-Protocol` of a handshake response, falling
+    /// back to JSON when the router did not pick one the client speaks.
+    pub fn from_response<T>(response: &Response<T>) -> Self {
+        negotiated_protocol(response)
+            .and_then(Serializer::from_protocol)
+            .unwrap_or(Serializer::Json)
+    }
+
+    /// Transcode a JSON text frame into a frame of this serialization. Other messages, such as
+    /// pings, are returned as they are.
+    pub fn encode(&self, message: Message) -> Result<Message, Error> {
+        match (self, message) {
+            (Serializer::MsgPack, Message::Text(text)) => {
+                let frame: Value = serde_json::from_str(&text)?;
+                Ok(Message::Binary(rmp_serde::to_vec(&frame)?))
+            }
+            (_, message) => Ok(message),
+        }
+    }
+
+    /// Transcode a received data frame into JSON text, `None` for control frames.
+    pub fn decode(&self, message: Message) -> Result<Option<String>, Error> {
+        match message {
+            Message::Text(text) => Ok(Some(text)),
+            Message::Binary(bytes) => {
+                let frame: Value = rmp_serde::from_slice(&bytes)?;
+                Ok(Some(to_string(&frame)?))
+            }
+            _ => Ok(None),
+        }
+    }
+}
+
+/// # Serialized Size
+/// Size in bytes a message takes on the wire, to reject or chunk oversized payloads before
+/// sending them.
+///
+/// ## Examples
+/// ```
+/// use std::convert::TryInto;
+/// use wamp_client::messages::{SerializedSize, Serializer};
+/// use wamp_core::call;
+/// use wamp_core::messages::{Call, Messages};
+/// use wamp_core::serde_json::json;
+/// use wamp_core::tungstenite::Message;
+///
+/// let mut call = call!("com.example.upload");
+/// call.args = json!(["a payload of a known size"]);
+///
+/// let size = Messages::from(call.clone()).serialized_size(Serializer::Json).unwrap();
+/// let sent: Message = call.try_into().unwrap();
+/// assert_eq!(size, sent.len());
+/// ```
+pub trait SerializedSize {
+    fn serialized_size(&self, serializer: Serializer) -> Result<usize, Error>;
+}
+
+impl<T> SerializedSize for T
+where
+    T: Clone + TryInto<Message>,
+    Error: From<<T as TryInto<Message>>::Error>,
+{
+    fn serialized_size(&self, serializer: Serializer) -> Result<usize, Error> {
+        let message: Message = self.clone().try_into()?;
+        Ok(serializer.encode(message)?.len())
+    }
+}
+
+/// # Value Frame
+/// Serialize a WAMP frame built as a raw `Value` into a WebSocket text message, without going
+/// through the typed message structs.
+///
+/// ## Examples
+/// ```
+/// use wamp_client::messages::value_frame;
+/// use wamp_core::serde_json::json;
+/// use wamp_core::tungstenite::Message;
+///
+/// let call = json!([48, 7, {}, "com.example.add", [1, 2], { "round": true }]);
+///
+/// assert_eq!(
+///     value_frame(&call).unwrap(),
+///     Message::Text(r#"[48,7,{},"com.example.add",[1,2],{"round":true}]"#.to_string())
+/// );
+/// ```
+pub fn value_frame(value: &Value) -> Result<Message, Error> {
+    Ok(Message::Text(to_string(value)?))
+}
+
+/// # Wamp Error
+/// Build an `Error` frame answering request `request_id` of the `event` kind, with empty details
+/// and payload.
+///
+/// ## Examples
+/// ```
+/// use wamp_client::messages::wamp_error;
+/// use wamp_core::messages::WampErrorEvent;
+///
+/// let error = wamp_error(WampErrorEvent::Invocation, 7, "wamp.error.invalid_argument");
+/// assert_eq!(error.request_id, 7);
+/// assert_eq!(error.error, "wamp.error.invalid_argument");
+/// ```
+pub fn wamp_error<E: ToString>(event: WampErrorEvent, request_id: u64, error: E) -> WampError {
+    WampError {
+        event,
+        request_id,
+        details: json!({}),
+        error: error.to_string(),
+        args: json!([]),
+        kwargs: json!({}),
+    }
+}
+
+/// Which payload list of a message a serialized value is placed in.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum PayloadField {
+    /// Positional `args`. A value that does not serialize to an array becomes its only element.
+    Args,
+    /// Keyword `kwargs`. The value must serialize to an object.
+    Kwargs,
+}
+
+/// # Publish Value
+/// Build a `Publish` on `topic` with `value` serialized into its args or kwargs.
+///
+/// ## Examples
+/// ```
+/// use serde::Serialize;
+/// use wamp_client::messages::{publish_value, PayloadField};
+/// use wamp_client::options::PublishOptions;
+/// use wamp_core::serde_json::json;
+///
+/// #[derive(Serialize)]
+/// struct Reading {
+///     sensor: String,
+///     celsius: f64,
+/// }
+///
+/// let reading = Reading { sensor: "kitchen".to_string(), celsius: 21.5 };
+///
+/// let publish = publish_value("sensors.reading", &reading, PublishOptions::default(), PayloadField::Args).unwrap();
+/// assert_eq!(publish.args, json!([{ "sensor": "kitchen", "celsius": 21.5 }]));
+///
+/// let publish = publish_value("sensors.reading", &reading, PublishOptions::default(), PayloadField::Kwargs).unwrap();
+/// assert_eq!(publish.kwargs, json!({ "sensor": "kitchen", "celsius": 21.5 }));
+/// ```
+pub fn publish_value<T: ToString, V: Serialize>(
+    topic: T,
+    value: &V,
+    options: PublishOptions,
+    field: PayloadField,
+) -> Result<Publish, Error> {
+    let mut publish = publish!(topic.to_string());
+    publish.options = options.to_value();
+    let value = to_value(value)?;
+    match field {
+        PayloadField::Args if value.is_array() => publish.args = value,
+        PayloadField::Args => publish.args = json!([value]),
+        PayloadField::Kwargs if value.is_object() => publish.kwargs = value,
+        PayloadField::Kwargs => {
+            return Err(Error::Error("kwargs payloads must serialize to a JSON object"))
+        }
+    }
+    Ok(publish)
+}
+
+/// The request id of a reply frame, as it is matched against pending requests.
+pub(crate) fn reply_request_id(message: &Messages) -> Option<u64> {
+    match message {
+        Messages::Error(error) => Some(error.request_id),
+        Messages::Published(published) => Some(published.request_id),
+        Messages::Subscribed(subscribed) => Some(subscribed.request_id),
+        Messages::Unsubscribed(unsubscribed) => Some(unsubscribed.request_id),
+        Messages::Result(result) => Some(result.request_id),
+        Messages::Registered(registered) => Some(registered.request_id),
+        Messages::Unregistered(unregistered) => Some(unregistered.request_id),
+        Messages::Interrupt(interrupt) => Some(interrupt.request_id),
+        _ => None,
+    }
+}
+
+/// Overwrite the request id of a reply frame, leaving other frames untouched.
+pub(crate) fn set_reply_request_id(message: &mut Messages, request_id: u64) {
+    match message {
+        Messages::Error(error) => error.request_id = request_id,
+        Messages::Published(published) => published.request_id = request_id,
+        Messages::Subscribed(subscribed) => subscribed.request_id = request_id,
+        Messages::Unsubscribed(unsubscribed) => unsubscribed.request_id = request_id,
+        Messages::Result(result) => result.request_id = request_id,
+        Messages::Registered(registered) => registered.request_id = request_id,
+        Messages::Unregistered(unregistered) => unregistered.request_id = request_id,
+        Messages::Interrupt(interrupt) => interrupt.request_id = request_id,
+        _ => {}
+    }
+}
+
+/// # Visit Result Args
+/// Deserialize the args of a raw `Result` frame one element at a time, handing each one to
+/// `visit`, without building a `Value` of the whole args list. Returns the request id of the
+/// result.
+///
+/// Read the raw frame text with `Client::read_frame`.
+///
+/// ## Examples
+/// ```
+/// use wamp_client::messages::visit_result_args;
+///
+/// let items: Vec<String> = (0..100_000).map(|i| i.to_string()).collect();
+/// let frame = format!("[50,7,{{}},[{}],{{}}]", items.join(","));
+///
+/// let mut count = 0;
+/// let mut sum = 0u64;
+/// let request_id = visit_result_args(&frame, |item: u64| {
+///     count += 1;
+///     sum += item;
+/// })
+/// .unwrap();
+///
+/// assert_eq!(request_id, 7);
+/// assert_eq!(count, 100_000);
+/// assert_eq!(sum, (0..100_000u64).sum::<u64>());
+/// ```
+pub fn visit_result_args<T, F>(frame: &str, mut visit: F) -> Result<u64, Error>
+where
+    T: DeserializeOwned,
+    F: FnMut(T),
+{
+    let mut deserializer = serde_json::Deserializer::from_str(frame);
+    let request_id = deserializer.deserialize_seq(ResultFrame {
+        visit: &mut visit,
+        item: PhantomData,
+    })?;
+    deserializer.end()?;
+    Ok(request_id)
+}
+
+/// Message code of `Result` frames.
+const RESULT: u64 = 50;
+
+struct ResultFrame<'v, T, F: 'v> {
+    visit: &'v mut F,
+    item: PhantomData<T>,
+}
+
+impl<'de, 'v, T: DeserializeOwned, F: FnMut(T)> Visitor<'de> for ResultFrame<'v, T, F> {
+    type Value = u64;
+
+    fn expecting(&self, formatter: &mut fmt::Formatter) -> fmt::Result {
+        formatter.write_str("a WAMP Result frame")
+    }
+
+    fn visit_seq<A: SeqAccess<'de>>(self, mut seq: A) -> Result<u64, A::Error> {
+        let code: u64 = seq
+            .next_element()?
+            .ok_or_else(|| de::Error::invalid_length(0, &"a WAMP Result frame"))?;
+        if code != RESULT {
+            return Err(de::Error::custom(format!("expected a Result frame, got code {}", code)));
+        }
+        let request_id: u64 = seq
+            .next_element()?
+            .ok_or_else(|| de::Error::invalid_length(1, &"a WAMP Result frame"))?;
+        seq.next_element::<IgnoredAny>()?
+            .ok_or_else(|| de::Error::invalid_length(2, &"a WAMP Result frame"))?;
+        seq.next_element_seed(ResultArgs {
+            visit: self.visit,
+            item: PhantomData,
+        })?;
+        while let Some(IgnoredAny) = seq.next_element()? {}
+        Ok(request_id)
+    }
+}
+
+struct ResultArgs<'v, T, F: 'v> {
+    visit: &'v mut F,
+    item: PhantomData<T>,
+}
+
+impl<'de, 'v, T: DeserializeOwned, F: FnMut(T)> DeserializeSeed<'de> for ResultArgs<'v, T, F> {
+    type Value = ();
+
+    fn deserialize<D: de::Deserializer<'de>>(self, deserializer: D) -> Result<(), D::Error> {
+        deserializer.deserialize_seq(self)
+    }
+}
+
+impl<'de, 'v, T: DeserializeOwned, F: FnMut(T)> Visitor<'de> for ResultArgs<'v, T, F> {
+    type Value = ();
+
+    fn expecting(&self, formatter: &mut fmt::Formatter) -> fmt::Result {
+        formatter.write_str("the args list of a WAMP Result frame")
+    }
+
+    fn visit_seq<A: SeqAccess<'de>>(self, mut seq: A) -> Result<(), A::Error> {
+        while let Some(item) = seq.next_element::<T>()? {
+            (self.visit)(item);
+        }
+        Ok(())
+    }
+}
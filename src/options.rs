@@ -0,0 +1,235 @@
+//! # Options
+//! Typed options maps for the messages that take one, so they can be built, cloned and tweaked
+//! instead of hand writing `Value`s.
+//!
+//! ## Examples
+//! The wire representation of the options maps round trips:
+//! ```
+//! use wamp_client::options::{InvokePolicy, MatchPolicy, PublishOptions, RegisterOptions, SubscribeOptions};
+//! use wamp_core::serde_json::json;
+//!
+//! let subscribe = SubscribeOptions { match_policy: Some(MatchPolicy::Prefix), ..Default::default() };
+//! assert_eq!(subscribe.to_value(), json!({ "match": "prefix" }));
+//! assert_eq!(SubscribeOptions::from_value(subscribe.to_value()).unwrap(), subscribe);
+//!
+//! let register = RegisterOptions { invoke: Some(InvokePolicy::RoundRobin), ..Default::default() };
+//! assert_eq!(register.to_value(), json!({ "invoke": "roundrobin" }));
+//!
+//! let base = PublishOptions { acknowledge: Some(true), ..Default::default() };
+//! let tweaked = PublishOptions { exclude_me: Some(false), ..base.clone() };
+//! assert_eq!(tweaked.to_value(), json!({ "acknowledge": true, "exclude_me": false }));
+//! assert_eq!(PublishOptions::from_value(tweaked.to_value()).unwrap(), tweaked);
+//! ```
+//!
+//! Options the structs do not model go in `extra`:
+//! ```
+//! use wamp_client::options::CallOptions;
+//! use wamp_core::call;
+//! use wamp_core::serde_json::json;
+//!
+//! let mut options = CallOptions { timeout: Some(1000), ..Default::default() };
+//! options.extra.insert("x_custom".to_string(), json!("value"));
+//!
+//! let mut call = call!("procedure");
+//! call.options = options.to_value();
+//! assert_eq!(call.options, json!({ "timeout": 1000, "x_custom": "value" }));
+//! ```
+
+use serde::{Deserialize, Serialize};
+use wamp_core::serde_json::{self, Map, Value};
+
+use crate::error::Error;
+
+/// How a subscription or registration URI is matched by the router.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum MatchPolicy {
+    Exact,
+    Prefix,
+    Wildcard,
+}
+
+/// How the router picks a callee when a procedure has a shared registration.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum InvokePolicy {
+    Single,
+    RoundRobin,
+    Random,
+    First,
+    Last,
+}
+
+/// How the dealer cancels a call, sent as the `mode` option of `Cancel`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum CancelMode {
+    /// Stop waiting for the result, without interrupting the callee.
+    Skip,
+    /// Interrupt the callee, and wait for it to answer before replying with an error.
+    Kill,
+    /// Interrupt the callee, and reply with an error right away.
+    KillNoWait,
+}
+
+/// Options map of a `Subscribe` message.
+#[derive(Debug, Clone, PartialEq, Default, Serialize, Deserialize)]
+pub struct SubscribeOptions {
+    #[serde(rename = "match", skip_serializing_if = "Option::is_none")]
+    pub match_policy: Option<MatchPolicy>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub get_retained: Option<bool>,
+    /// Client side limit on the events per second delivered to the event callback, excess events
+    /// are dropped. Never sent to the router.
+    #[serde(skip)]
+    pub rate_limit: Option<u32>,
+    /// Options not modelled above, such as vendor `x_` options, merged into the map as is.
+    #[serde(flatten)]
+    pub extra: Map<String, Value>,
+}
+
+/// Options map of a `Call` message.
+#[derive(Debug, Clone, PartialEq, Default, Serialize, Deserialize)]
+pub struct CallOptions {
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub timeout: Option<u64>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub receive_progress: Option<bool>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub disclose_me: Option<bool>,
+    /// Options not modelled above, such as vendor `x_` options, merged into the map as is.
+    #[serde(flatten)]
+    pub extra: Map<String, Value>,
+}
+
+/// Options map of a `Publish` message.
+#[derive(Debug, Clone, PartialEq, Default, Serialize, Deserialize)]
+pub struct PublishOptions {
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub acknowledge: Option<bool>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub exclude: Option<Vec<u64>>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub exclude_authid: Option<Vec<String>>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub exclude_authrole: Option<Vec<String>>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub eligible: Option<Vec<u64>>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub eligible_authid: Option<Vec<String>>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub eligible_authrole: Option<Vec<String>>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub exclude_me: Option<bool>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub disclose_me: Option<bool>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub retain: Option<bool>,
+    /// Options not modelled above, such as vendor `x_` options, merged into the map as is.
+    #[serde(flatten)]
+    pub extra: Map<String, Value>,
+}
+
+/// Options map of a `Register` message.
+#[derive(Debug, Clone, PartialEq, Default, Serialize, Deserialize)]
+pub struct RegisterOptions {
+    #[serde(rename = "match", skip_serializing_if = "Option::is_none")]
+    pub match_policy: Option<MatchPolicy>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub invoke: Option<InvokePolicy>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub disclose_caller: Option<bool>,
+    /// Options not modelled above, such as vendor `x_` options, merged into the map as is.
+    #[serde(flatten)]
+    pub extra: Map<String, Value>,
+}
+
+macro_rules! options_conversions {
+    ($($options: ident),*) => {
+        $(
+            impl $options {
+                /// Serialize into the options map sent on the wire.
+                pub fn to_value(&self) -> Value {
+                    serde_json::to_value(self).expect("options always serialize to a map")
+                }
+
+                /// Parse an options map received from or destined for the wire.
+                pub fn from_value(value: Value) -> Result<Self, Error> {
+                    Ok(serde_json::from_value(value)?)
+                }
+            }
+
+            impl From<$options> for Value {
+                fn from(options: $options) -> Self {
+                    options.to_value()
+                }
+            }
+        )*
+    };
+}
+
+options_conversions!(SubscribeOptions, CallOptions, PublishOptions, RegisterOptions);
+
+impl RegisterOptions {
+    /// # Register Options Shared
+    /// Options for a shared registration, which other callees may register as well, with
+    /// `invoke` picking the callee for each call.
+    ///
+    /// ## Examples
+    /// ```
+    /// use wamp_client::options::{InvokePolicy, MatchPolicy, RegisterOptions};
+    /// use wamp_core::serde_json::json;
+    ///
+    /// let options = RegisterOptions {
+    ///     match_policy: Some(MatchPolicy::Prefix),
+    ///     ..RegisterOptions::shared(InvokePolicy::Random)
+    /// };
+    /// assert_eq!(options.to_value(), json!({ "match": "prefix", "invoke": "random" }));
+    /// ```
+    pub fn shared(invoke: InvokePolicy) -> Self {
+        Self {
+            invoke: Some(invoke),
+            ..Default::default()
+        }
+    }
+}
+
+impl SubscribeOptions {
+    /// # Subscribe Options Prefix
+    /// Options subscribing to every topic that starts with the subscribed URI, so
+    /// `com.myapp.sensor` receives events published to `com.myapp.sensor.kitchen` and
+    /// `com.myapp.sensor.hall.door` alike.
+    ///
+    /// ## Examples
+    /// ```
+    /// use wamp_client::options::SubscribeOptions;
+    /// use wamp_core::serde_json::json;
+    ///
+    /// assert_eq!(SubscribeOptions::prefix().to_value(), json!({ "match": "prefix" }));
+    /// ```
+    pub fn prefix() -> Self {
+        Self {
+            match_policy: Some(MatchPolicy::Prefix),
+            ..Default::default()
+        }
+    }
+
+    /// # Subscribe Options Wildcard
+    /// Options subscribing to every topic matching the subscribed URI, whose empty components
+    /// match any single component, so `com.myapp..update` receives events published to
+    /// `com.myapp.user.update` but not to `com.myapp.user.profile.update`.
+    ///
+    /// ## Examples
+    /// ```
+    /// use wamp_client::options::SubscribeOptions;
+    /// use wamp_core::serde_json::json;
+    ///
+    /// assert_eq!(SubscribeOptions::wildcard().to_value(), json!({ "match": "wildcard" }));
+    /// ```
+    pub fn wildcard() -> Self {
+        Self {
+            match_policy: Some(MatchPolicy::Wildcard),
+            ..Default::default()
+        }
+    }
+}
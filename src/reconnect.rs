@@ -0,0 +1,153 @@
+//! # Reconnect
+//! Backoff between reconnect attempts, with jitter so that clients dropped by the same router
+//! restart do not all reconnect in lockstep.
+//!
+//! ## Examples
+//! ```
+//! use std::time::Duration;
+//! use wamp_client::reconnect::{Jitter, ReconnectPolicy};
+//!
+//! let policy = ReconnectPolicy {
+//!     initial_delay: Duration::from_millis(100),
+//!     max_delay: Duration::from_secs(5),
+//!     jitter: Jitter::Full,
+//!     ..Default::default()
+//! };
+//!
+//! let delays: Vec<Duration> = (0..1000).map(|_| policy.delay(3)).collect();
+//!
+//! // Full jitter picks anywhere up to the exponential delay of the attempt, 100ms * 2^3.
+//! assert!(delays.iter().all(|delay| *delay <= Duration::from_millis(800)));
+//! assert!(delays.iter().any(|delay| *delay != delays[0]));
+//!
+//! let mut backoff = ReconnectPolicy { jitter: Jitter::Decorrelated, ..policy }.backoff();
+//! for _ in 0..1000 {
+//!     let delay = backoff.next().unwrap();
+//!     assert!(delay >= Duration::from_millis(100) && delay <= Duration::from_secs(5));
+//! }
+//! ```
+
+use rand::Rng;
+use std::time::Duration;
+
+/// How a reconnect delay is randomized.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Jitter {
+    /// Plain exponential backoff, every client waits exactly the same.
+    None,
+    /// Anywhere between zero and the exponential delay of the attempt.
+    Full,
+    /// Anywhere between the initial delay and three times the previous delay, capped at the
+    /// max delay.
+    Decorrelated,
+}
+
+/// # Reconnect Policy
+/// Exponential backoff between reconnect attempts.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct ReconnectPolicy {
+    /// Delay before the first reconnect attempt.
+    pub initial_delay: Duration,
+    /// Delays never grow past this.
+    pub max_delay: Duration,
+    /// Factor the delay grows by with every attempt.
+    pub multiplier: u32,
+    /// Give up after this many attempts, `None` to retry forever.
+    pub max_attempts: Option<u32>,
+    pub jitter: Jitter,
+}
+
+impl Default for ReconnectPolicy {
+    fn default() -> Self {
+        Self {
+            initial_delay: Duration::from_millis(500),
+            max_delay: Duration::from_secs(30),
+            multiplier: 2,
+            max_attempts: None,
+            jitter: Jitter::Full,
+        }
+    }
+}
+
+impl ReconnectPolicy {
+    /// Exponential delay of `attempt`, counting from `0`, before jitter.
+    pub fn base_delay(&self, attempt: u32) -> Duration {
+        let factor = self.multiplier.checked_pow(attempt).unwrap_or(u32::max_value());
+        self.initial_delay
+            .checked_mul(factor)
+            .map_or(self.max_delay, |delay| delay.min(self.max_delay))
+    }
+
+    /// Delay before `attempt`, counting from `0`, with `Jitter::None` or `Jitter::Full` applied.
+    ///
+    /// Decorrelated jitter depends on the previous delay, so it is computed as the base delay
+    /// here, use `backoff` for it.
+    pub fn delay(&self, attempt: u32) -> Duration {
+        let base = self.base_delay(attempt);
+        match self.jitter {
+            Jitter::Full => random_between(Duration::from_secs(0), base),
+            Jitter::None | Jitter::Decorrelated => base,
+        }
+    }
+
+    /// Iterator over the delays of successive attempts, ending after `max_attempts`.
+    pub fn backoff(&self) -> Backoff {
+        Backoff {
+            policy: *self,
+            attempt: 0,
+            previous: self.initial_delay,
+        }
+    }
+}
+
+/// Delays of successive reconnect attempts, created with `ReconnectPolicy::backoff`.
+#[derive(Debug, Clone)]
+pub struct Backoff {
+    policy: ReconnectPolicy,
+    attempt: u32,
+    previous: Duration,
+}
+
+impl Backoff {
+    /// Number of delays handed out so far.
+    pub fn attempt(&self) -> u32 {
+        self.attempt
+    }
+
+    /// Start over from the initial delay, after a successful reconnect.
+    pub fn reset(&mut self) {
+        self.attempt = 0;
+        self.previous = self.policy.initial_delay;
+    }
+}
+
+impl Iterator for Backoff {
+    type Item = Duration;
+
+    fn next(&mut self) -> Option<Duration> {
+        if let Some(max_attempts) = self.policy.max_attempts {
+            if self.attempt >= max_attempts {
+                return None;
+            }
+        }
+
+        let delay = match self.policy.jitter {
+            Jitter::Decorrelated => {
+                let upper = self.previous.checked_mul(3).unwrap_or(self.policy.max_delay);
+                random_between(self.policy.initial_delay, upper).min(self.policy.max_delay)
+            }
+            _ => self.policy.delay(self.attempt),
+        };
+        self.attempt += 1;
+        self.previous = delay;
+        Some(delay)
+    }
+}
+
+fn random_between(low: Duration, high: Duration) -> Duration {
+    if high <= low {
+        return low;
+    }
+    let nanos = rand::thread_rng().gen_range(low.as_nanos() as u64..=high.as_nanos() as u64);
+    Duration::from_nanos(nanos)
+}
@@ -0,0 +1,336 @@
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+use wamp_core::http::Response;
+use wamp_core::messages::{Challenge, Goodbye, Welcome};
+use wamp_core::serde_json::Value;
+use wamp_core::tungstenite::client::connect_with_config;
+use wamp_core::tungstenite::protocol::WebSocketConfig;
+#[cfg(feature = "native-tls")]
+use wamp_core::tungstenite::Connector;
+
+use crate::error::Error;
+use crate::messages::Serializer;
+
+use super::{
+    client::{Client, DEFAULT_TIMEOUT},
+    context::{CallBack, Context},
+    request::with_headers,
+    Protocols, WampRequest,
+};
+
+/// # Client Builder
+/// Accumulates the options for a sync `Client` in one place, then connects with `connect`, or
+/// with `build` to the `uri` and subprotocols set on the builder.
+///
+/// Callbacks set on the builder are attached before the client is handed back, so none of the
+/// session's frames can be missed.
+///
+/// ## Examples
+/// ```
+/// use std::cell::RefCell;
+/// use std::rc::Rc;
+/// use wamp_client::hello::HelloBuilder;
+/// use wamp_client::sync::builder::ClientBuilder;
+/// use wamp_client::sync::client::LoopExit;
+/// use wamp_client::test_util::MockRouter;
+///
+/// let (uri, router) = MockRouter::spawn(move |router| {
+///     router.recv();
+///     router.send(r#"[2,42,{"roles":{"broker":{}}}]"#);
+///     router.send(r#"[6,{},"wamp.close.system_shutdown"]"#);
+/// });
+///
+/// let seen = Rc::new(RefCell::new(vec![]));
+/// let (seen2, seen3) = (seen.clone(), seen.clone());
+/// let (mut client, _response) = ClientBuilder::new()
+///     .uri(uri)
+///     .protocol("wamp.2.msgpack")
+///     .protocol("wamp.2.json")
+///     .on_welcome(Box::new(move |ctx, welcome| {
+///         seen2.borrow_mut().push(format!("joined session {}", welcome.session));
+///         ctx
+///     }))
+///     .on_goodbye(Box::new(move |ctx, goodbye| {
+///         seen3.borrow_mut().push(format!("router left with {}", goodbye.reason));
+///         ctx
+///     }))
+///     .build()
+///     .unwrap();
+///
+/// client.send(HelloBuilder::new("realm1").build().unwrap()).unwrap();
+/// assert!(matches!(client.event_loop().unwrap(), LoopExit::Goodbye(_)));
+/// assert_eq!(*seen.borrow(), vec![
+///     "joined session 42",
+///     "router left with wamp.close.system_shutdown",
+/// ]);
+/// drop(client);
+/// router.join().unwrap();
+/// ```
+///
+/// The options take effect on the connected client, here against a router picking MessagePack:
+/// ```
+/// use std::net::TcpListener;
+/// use std::thread;
+/// use std::time::Duration;
+/// use wamp_client::messages::Serializer;
+/// use wamp_client::sync::builder::ClientBuilder;
+/// use wamp_core::tungstenite::accept_hdr;
+/// use wamp_core::tungstenite::handshake::server::{Request, Response};
+///
+/// let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+/// let uri = format!("ws://{}/ws", listener.local_addr().unwrap());
+/// thread::spawn(move || {
+///     let (stream, _) = listener.accept().unwrap();
+///     let mut socket = accept_hdr(stream, |request: &Request, mut response: Response| {
+///         assert_eq!(request.headers()["Sec-WebSocket-Protocol"], "wamp.2.msgpack, wamp.2.json");
+///         response.headers_mut().insert("Sec-WebSocket-Protocol", "wamp.2.msgpack".parse().unwrap());
+///         Ok(response)
+///     })
+///     .unwrap();
+///     while socket.read().is_ok() {}
+/// });
+///
+/// let (client, _) = ClientBuilder::new()
+///     .uri(uri)
+///     .protocol("wamp.2.msgpack")
+///     .protocol("wamp.2.json")
+///     .timeout(Duration::from_secs(2))
+///     .capacity(32)
+///     .build()
+///     .unwrap();
+///
+/// assert_eq!(client.timeout(), Duration::from_secs(2));
+/// assert_eq!(client.serializer(), Serializer::MsgPack);
+/// ```
+pub struct ClientBuilder {
+    capacity: Option<usize>,
+    timeout: Duration,
+    websocket_config: Option<WebSocketConfig>,
+    uri: Option<String>,
+    protocols: Vec<String>,
+    headers: Vec<(String, String)>,
+    on_welcome: Option<CallBack<Welcome>>,
+    on_challenge: Option<CallBack<Challenge>>,
+    on_goodbye: Option<CallBack<Goodbye>>,
+    on_extension: Option<CallBack<Value>>,
+    #[cfg(feature = "native-tls")]
+    tls_connector: Option<Connector>,
+}
+
+/// # WebSocket Config Summary
+/// The WebSocket settings in effect on a live connection, returned by `Client::connection_config`.
+#[derive(Debug, Clone, PartialEq)]
+pub struct WebSocketConfigSummary {
+    /// Largest message the socket accepts, `None` when unlimited.
+    pub max_message_size: Option<usize>,
+    /// Largest single frame the socket accepts, `None` when unlimited.
+    pub max_frame_size: Option<usize>,
+    /// Bytes buffered before a write is flushed to the stream.
+    pub write_buffer_size: usize,
+    /// Whether per-message compression is in effect. Tungstenite does not negotiate
+    /// `permessage-deflate`, so this is always `false` for now.
+    pub compression: bool,
+}
+
+impl From<&WebSocketConfig> for WebSocketConfigSummary {
+    fn from(config: &WebSocketConfig) -> Self {
+        Self {
+            max_message_size: config.max_message_size,
+            max_frame_size: config.max_frame_size,
+            write_buffer_size: config.write_buffer_size,
+            compression: false,
+        }
+    }
+}
+
+impl Default for ClientBuilder {
+    fn default() -> Self {
+        Self {
+            capacity: None,
+            timeout: DEFAULT_TIMEOUT,
+            websocket_config: None,
+            uri: None,
+            protocols: vec![],
+            headers: vec![],
+            on_welcome: None,
+            on_challenge: None,
+            on_goodbye: None,
+            on_extension: None,
+            #[cfg(feature = "native-tls")]
+            tls_connector: None,
+        }
+    }
+}
+
+impl ClientBuilder {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Initial capacity of each of the client context's callback vecs.
+    pub fn capacity(mut self, capacity: usize) -> Self {
+        self.capacity = Some(capacity);
+        self
+    }
+
+    /// Time the blocking helpers wait for a reply from the router.
+    pub fn timeout(mut self, timeout: Duration) -> Self {
+        self.timeout = timeout;
+        self
+    }
+
+    /// Configuration for the underlying tungstenite socket, such as max message and frame sizes.
+    pub fn websocket_config(mut self, websocket_config: WebSocketConfig) -> Self {
+        self.websocket_config = Some(websocket_config);
+        self
+    }
+
+    /// # Client Builder Tls Connector
+    /// Wrap `wss://` connections with `connector` instead of the default TLS settings, such as
+    /// one trusting the private CA of the router. See `Client::connect_with_tls_config`.
+    #[cfg(feature = "native-tls")]
+    pub fn tls_connector(mut self, connector: Connector) -> Self {
+        self.tls_connector = Some(connector);
+        self
+    }
+
+    /// The router URI `build` connects to.
+    pub fn uri<U: ToString>(mut self, uri: U) -> Self {
+        self.uri = Some(uri.to_string());
+        self
+    }
+
+    /// Offer a subprotocol, such as `wamp.2.json`, in order of preference. `build` offers
+    /// `wamp.2.json` when none is given.
+    pub fn protocol<P: ToString>(mut self, protocol: P) -> Self {
+        self.protocols.push(protocol.to_string());
+        self
+    }
+
+    /// # Client Builder Header
+    /// Add `name: value` to the WebSocket handshake, for routers or proxies that want an
+    /// `Authorization` header or cookies. A header the handshake already has is replaced, except
+    /// for the WebSocket headers, such as `Upgrade` or `Sec-WebSocket-Protocol`, which are always
+    /// the generated ones.
+    ///
+    /// ## Examples
+    /// ```
+    /// use std::net::TcpListener;
+    /// use std::thread;
+    /// use wamp_client::sync::{builder::ClientBuilder, WampRequest};
+    /// use wamp_core::tungstenite::accept_hdr;
+    /// use wamp_core::tungstenite::handshake::server::{Request, Response};
+    ///
+    /// let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+    /// let uri = format!("ws://{}", listener.local_addr().unwrap());
+    ///
+    /// let router = thread::spawn(move || {
+    ///     let (stream, _) = listener.accept().unwrap();
+    ///     let mut headers = None;
+    ///     let _socket = accept_hdr(stream, |request: &Request, mut response: Response| {
+    ///         headers = Some(request.headers().clone());
+    ///         response
+    ///             .headers_mut()
+    ///             .insert("Sec-WebSocket-Protocol", "wamp.2.json".parse().unwrap());
+    ///         Ok(response)
+    ///     })
+    ///     .unwrap();
+    ///     headers.unwrap()
+    /// });
+    ///
+    /// let (_client, _) = ClientBuilder::new()
+    ///     .header("Authorization", "Bearer t0ps3cr3t")
+    ///     .header("Upgrade", "h2c")
+    ///     .connect(WampRequest { uri, protocol: "wamp.2.json" })
+    ///     .unwrap();
+    ///
+    /// let headers = router.join().unwrap();
+    /// assert_eq!(headers["Authorization"], "Bearer t0ps3cr3t");
+    /// assert_eq!(headers["Upgrade"], "websocket");
+    /// ```
+    pub fn header<N: ToString, V: ToString>(mut self, name: N, value: V) -> Self {
+        self.headers.push((name.to_string(), value.to_string()));
+        self
+    }
+
+    /// See `Client::on_welcome`.
+    pub fn on_welcome(mut self, on_welcome: CallBack<Welcome>) -> Self {
+        self.on_welcome = Some(on_welcome);
+        self
+    }
+
+    /// See `Client::on_challenge`.
+    pub fn on_challenge(mut self, on_challenge: CallBack<Challenge>) -> Self {
+        self.on_challenge = Some(on_challenge);
+        self
+    }
+
+    /// See `Client::on_goodbye`.
+    pub fn on_goodbye(mut self, on_goodbye: CallBack<Goodbye>) -> Self {
+        self.on_goodbye = Some(on_goodbye);
+        self
+    }
+
+    /// See `Client::on_extension`.
+    pub fn on_extension(mut self, on_extension: CallBack<Value>) -> Self {
+        self.on_extension = Some(on_extension);
+        self
+    }
+
+    /// Connect to the builder's `uri`, offering its subprotocols. Returns `Error::Error` if no
+    /// `uri` was set.
+    pub fn build(mut self) -> Result<(Client, Response<Option<Vec<u8>>>), Error> {
+        let uri = self
+            .uri
+            .take()
+            .ok_or(Error::Error("ClientBuilder::build needs a uri"))?;
+        let protocols = if self.protocols.is_empty() {
+            Protocols::new(vec![Serializer::Json.protocol()])
+        } else {
+            Protocols::new(self.protocols.clone())
+        };
+        self.connect(WampRequest {
+            uri,
+            protocol: protocols,
+        })
+    }
+
+    pub fn connect<U: ToString, P: ToString>(
+        self,
+        request: WampRequest<U, P>,
+    ) -> Result<(Client, Response<Option<Vec<u8>>>), Error> {
+        let endpoint = request.uri.to_string();
+        let protocol = request.protocol.to_string();
+        let request = with_headers(request, &self.headers)?;
+        #[cfg(feature = "native-tls")]
+        let (socket, response) = match self.tls_connector {
+            Some(connector) => {
+                crate::core::connect_with_connector(request, self.websocket_config, connector)?
+            }
+            None => connect_with_config(request, self.websocket_config, 3)?,
+        };
+        #[cfg(not(feature = "native-tls"))]
+        let (socket, response) = connect_with_config(request, self.websocket_config, 3)?;
+        let socket = Arc::new(Mutex::new(socket));
+        let context = match self.capacity {
+            Some(capacity) => Context::new_with_capacity(Some(socket.clone()), capacity),
+            None => Context::new(Some(socket.clone())),
+        };
+        let mut client = Client::from_socket(socket, context, endpoint, protocol);
+        client.set_timeout(self.timeout);
+        client.set_serializer(Serializer::from_response(&response));
+        if let Some(on_welcome) = self.on_welcome {
+            client.on_welcome(on_welcome);
+        }
+        if let Some(on_challenge) = self.on_challenge {
+            client.on_challenge(on_challenge);
+        }
+        if let Some(on_goodbye) = self.on_goodbye {
+            client.on_goodbye(on_goodbye);
+        }
+        if let Some(on_extension) = self.on_extension {
+            client.on_extension(on_extension);
+        }
+        Ok((client, response))
+    }
+}
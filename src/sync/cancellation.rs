@@ -0,0 +1,66 @@
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Arc;
+
+/// # Cancellation Token
+/// Cooperative cancellation flag for long running callee handlers, polled with `is_cancelled`.
+///
+/// The client holds a session token, flipped by `Client::shutdown`. Each invocation of a
+/// procedure registered with `Client::register_cancellable` gets a child token, which is also
+/// flipped by an `Interrupt` for that invocation. Tokens are `Send`, so a clone can be cancelled
+/// from another thread, such as a signal handler.
+///
+/// ## Examples
+/// ```
+/// use std::thread;
+/// use wamp_client::sync::cancellation::CancellationToken;
+///
+/// let session = CancellationToken::new();
+/// let invocation = session.child();
+///
+/// let shutdown = session.clone();
+/// thread::spawn(move || shutdown.cancel()).join().unwrap();
+///
+/// // The handler polls its invocation token, which follows the session.
+/// let mut steps = 0;
+/// while !invocation.is_cancelled() {
+///     steps += 1;
+/// }
+/// assert_eq!(steps, 0);
+///
+/// // Cancelling a child leaves the session alone.
+/// let session = CancellationToken::new();
+/// session.child().cancel();
+/// assert!(!session.is_cancelled());
+/// ```
+#[derive(Debug, Clone, Default)]
+pub struct CancellationToken {
+    cancelled: Arc<AtomicBool>,
+    parent: Option<Arc<AtomicBool>>,
+}
+
+impl CancellationToken {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// A token cancelled along with this one, that can also be cancelled on its own.
+    pub fn child(&self) -> Self {
+        Self {
+            cancelled: Arc::new(AtomicBool::new(false)),
+            parent: Some(self.cancelled.clone()),
+        }
+    }
+
+    pub fn cancel(&self) {
+        self.cancelled.store(true, Ordering::SeqCst);
+    }
+
+    /// Whether the token, or the token it is a child of, was cancelled.
+    pub fn is_cancelled(&self) -> bool {
+        self.cancelled.load(Ordering::SeqCst)
+            || self
+                .parent
+                .as_ref()
+                .map_or(false, |parent| parent.load(Ordering::SeqCst))
+    }
+}
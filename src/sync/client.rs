@@ -1,41 +1,274 @@
-use crate::{core::{
+use crate::{
+    capabilities::RouterCapabilities,
+    core::{tcp_stream, Socket, IDLE_WAIT},
     error::Error,
+    hello::HelloBuilder,
     messages::{
-        Cancel,
-        challenge::Challenge,
-        invocation::Invocation,
-        publish::Publish,
-        subscribe::Subscribe,
-        unregister::Unregister,
-        unsubscribe::Unsubscribe,
-        Call, Event, Goodbye, Interrupt, Messages, Published, Register, Registered, Subscribed,
-        Unregistered, Unsubscribed, WampErrorEvent, WampResult, Welcome,
+        publish_value, reply_request_id, set_reply_request_id, value_frame, PayloadField,
+        wamp_error, Serializer,
     },
-}, clients::core::Socket};
-use http::Response;
-use serde_json::{from_str, Value};
+    options::{CancelMode, MatchPolicy, PublishOptions, RegisterOptions, SubscribeOptions},
+};
 use std::{
-    net::TcpStream,
-    sync::{Arc, Mutex},
+    cell::{Cell, RefCell},
+    collections::{HashMap, HashSet, VecDeque},
+    mem::{discriminant, Discriminant},
+    rc::Rc,
+    sync::{
+        mpsc::{channel, Receiver, Sender},
+        Arc,
+    },
+    thread,
+    time::{Duration, Instant},
 };
-use tungstenite::{connect, stream::MaybeTlsStream, Message, WebSocket};
+use wamp_core::http::Response;
+use wamp_core::messages::*;
+use serde::Serialize;
+use wamp_core::serde_json::{from_str, json, Value};
+use wamp_core::tungstenite::protocol::{frame::coding::CloseCode, CloseFrame};
+use wamp_core::tungstenite::{self, Message};
+#[cfg(feature = "native-tls")]
+use wamp_core::tungstenite::Connector;
+use wamp_core::{publish, register, subscribe, unregister, unsubscribe};
 
 use super::{
-    context::{CallBack, CallBackResult, Context},
+    auth::AuthConfig,
+    builder::{ClientBuilder, WebSocketConfigSummary},
+    cancellation::CancellationToken,
+    context::{
+        call_raw_hook, CallBack, CallBackResult, CallFailure, CallToken, Context, PendingCall, RawHook,
+    },
+    events::EventIterator,
+    keepalive::{KeepAlive, PingSchedule},
+    snapshot::{RegistrationSpec, SessionSnapshot, SubscriptionSpec},
+    subscription::{RateLimit, SubscriptionHandle},
     WampRequest,
 };
 
+/// Default time the blocking helpers wait for a reply from the router.
+pub const DEFAULT_TIMEOUT: Duration = Duration::from_secs(10);
+
+/// Reason of the `Goodbye` that answers a `Goodbye`.
+const GOODBYE_AND_OUT: &str = "wamp.close.goodbye_and_out";
+
+/// Longest `Client::event_loop` waits for a frame before checking whether it was shut down.
+const SHUTDOWN_POLL: Duration = Duration::from_millis(100);
+
+/// Longest `Client::event_loop` waits for a frame while `register_cancellable` handlers run, so
+/// their results are sent soon after they return.
+const INVOCATION_POLL: Duration = Duration::from_millis(10);
+
+/// The invocation a `register_cancellable` handler answered, and its result.
+type InvocationResult = (u64, Result<Value, WampError>);
+
+/// Number of answered request ids remembered to detect duplicate replies.
+const ANSWERED_HISTORY: usize = 256;
+
 pub struct Client {
     pub socket: Socket,
     pub context: Context,
+    endpoint: String,
+    protocol: String,
+    timeout: Duration,
     on_welcome: Option<super::context::CallBack<Welcome>>,
     on_challenge: Option<super::context::CallBack<Challenge>>,
     on_goodbye: Option<super::context::CallBack<Goodbye>>,
     on_extension: Option<super::context::CallBack<Value>>,
+    on_unmatched_event: Option<CallBack<Event>>,
+    on_pong: Option<Box<dyn FnMut(&[u8])>>,
+    on_message: Option<Box<dyn FnMut(&Messages)>>,
+    on_close: Option<CallBack<CloseFrame<'static>>>,
+    close_frame: Option<CloseFrame<'static>>,
+    on_raw_send: Option<RawHook>,
+    on_raw_recv: Option<RawHook>,
+    serializer: Serializer,
+    hello_sent: bool,
+    buffered: bool,
+    write_buffer: VecDeque<Message>,
+    write_buffer_bytes: usize,
+    read_ahead_bytes: Rc<Cell<usize>>,
+    byte_budget: Option<(usize, Overflow)>,
+    dropped_messages: u64,
+    auto_goodbye: bool,
+    request_id_matcher: Option<Box<dyn FnMut(&Messages) -> Option<u64>>>,
+    on_protocol_violation: Option<Box<dyn FnMut(&Messages)>>,
+    on_authorization_failed: Option<Box<dyn FnMut(&WampError)>>,
+    answered: VecDeque<(Discriminant<Messages>, u64)>,
+    acknowledged_keys: Rc<RefCell<HashSet<String>>>,
+    router_capabilities: Option<RouterCapabilities>,
+    session_id: Option<u64>,
+    router_roles: Option<Value>,
+    last_errors: HashMap<RequestKind, (Instant, WampError)>,
+    send_metrics: HashMap<RequestKind, SendMetrics>,
+    auth: Option<AuthConfig>,
+    shutdown: CancellationToken,
+    invocation_tokens: Rc<RefCell<HashMap<u64, CancellationToken>>>,
+    finish_invocation: Sender<InvocationResult>,
+    finished_invocations: Receiver<InvocationResult>,
+    keepalive: Option<PingSchedule>,
+    state: ConnectionState,
+}
+
+/// Where the client is in the life cycle of a session.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ConnectionState {
+    /// The WebSocket is open, and the router has not answered the `Hello` yet.
+    Connecting,
+    /// The router sent a `Challenge` before welcoming the client.
+    Authenticating,
+    /// The router welcomed the client, requests can be sent.
+    Established,
+    /// The router sent a `Goodbye` the client has not answered yet.
+    Closing,
+    /// The goodbye handshake completed, the router aborted the session or the WebSocket was
+    /// closed.
+    Closed,
+}
+
+/// The kind of request a router `Error` answers.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum RequestKind {
+    Call,
+    Subscribe,
+    Unsubscribe,
+    Publish,
+    Register,
+    Unregister,
+    Cancel,
+}
+
+impl RequestKind {
+    /// The request kind of an `Error` sent by the router, `None` for errors only a client sends.
+    pub fn of(event: &WampErrorEvent) -> Option<Self> {
+        match event {
+            WampErrorEvent::Call => Some(RequestKind::Call),
+            WampErrorEvent::Subscribe => Some(RequestKind::Subscribe),
+            WampErrorEvent::Unsubscribe => Some(RequestKind::Unsubscribe),
+            WampErrorEvent::Publish => Some(RequestKind::Publish),
+            WampErrorEvent::Register => Some(RequestKind::Register),
+            WampErrorEvent::Unregister => Some(RequestKind::Unregister),
+            WampErrorEvent::Cancel => Some(RequestKind::Cancel),
+            WampErrorEvent::Invocation => None,
+        }
+    }
+
+    /// The request kind of a frame the client sends, `None` for frames that are not requests.
+    fn of_frame(code: u64) -> Option<Self> {
+        match code {
+            48 => Some(RequestKind::Call),
+            32 => Some(RequestKind::Subscribe),
+            34 => Some(RequestKind::Unsubscribe),
+            16 => Some(RequestKind::Publish),
+            64 => Some(RequestKind::Register),
+            66 => Some(RequestKind::Unregister),
+            49 => Some(RequestKind::Cancel),
+            _ => None,
+        }
+    }
+}
+
+/// # Send Metrics
+/// How many requests of one kind the client sent, and when it sent the first and the last.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct SendMetrics {
+    pub count: u64,
+    pub first: Instant,
+    pub last: Instant,
+}
+
+impl SendMetrics {
+    /// Requests sent per second between the first and the last one, `0.0` until two were sent.
+    pub fn rate(&self) -> f64 {
+        let elapsed = self.last.duration_since(self.first).as_secs_f64();
+        if self.count < 2 || elapsed == 0.0 {
+            0.0
+        } else {
+            (self.count - 1) as f64 / elapsed
+        }
+    }
+}
+
+/// What `Client::write` does when a message would take the queued bytes over the byte budget.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Overflow {
+    /// Refuse the message with `Error::BudgetExceeded`, leaving the queue as it is.
+    Error,
+    /// Drop the oldest queued messages until the new one fits, counting them in
+    /// `Client::dropped_messages`.
+    DropOldest,
+}
+
+/// Why `Client::event_loop` stopped reading.
+///
+/// ## Examples
+/// ```
+/// use wamp_client::hello::HelloBuilder;
+/// use wamp_client::sync::client::{Client, LoopExit};
+/// use wamp_client::sync::WampRequest;
+/// use wamp_client::test_util::MockRouter;
+/// use wamp_core::tungstenite::Message;
+///
+/// // Run the event loop against a router that ends the session with `frame`, or closes the
+/// // WebSocket without one.
+/// fn exit_after(frame: Option<&'static str>) -> LoopExit {
+///     let (uri, _) = MockRouter::spawn(move |router| {
+///         router.welcome();
+///         match frame {
+///             Some(frame) => router.socket().send(Message::Text(frame.to_string())).unwrap(),
+///             None => router.socket().close(None).unwrap(),
+///         }
+///     });
+///
+///     let (mut client, _) = Client::connect(WampRequest { uri, protocol: "wamp.2.json" }).unwrap();
+///     client.send(HelloBuilder::new("realm1").build().unwrap()).unwrap();
+///     client.event_loop().unwrap()
+/// }
+///
+/// match exit_after(Some(r#"[6,{},"wamp.close.system_shutdown"]"#)) {
+///     LoopExit::Goodbye(goodbye) => assert_eq!(goodbye.reason, "wamp.close.system_shutdown"),
+///     exit => panic!("expected a goodbye, got {:?}", exit),
+/// }
+/// match exit_after(Some(r#"[3,{},"wamp.error.system_shutdown"]"#)) {
+///     LoopExit::Aborted(abort) => assert_eq!(abort.reason, "wamp.error.system_shutdown"),
+///     exit => panic!("expected an abort, got {:?}", exit),
+/// }
+/// assert!(matches!(exit_after(None), LoopExit::Closed));
+/// ```
+#[derive(Debug)]
+pub enum LoopExit {
+    /// The router ended the session with a `Goodbye`.
+    Goodbye(Goodbye),
+    /// The WebSocket was closed.
+    Closed,
+    /// The router aborted the session.
+    Aborted(Abort),
+    /// `Client::shutdown` was called, or the session's `CancellationToken` cancelled.
+    Shutdown,
+}
+
+// A context for a callback, sharing the client socket and raw send hook. A macro rather than a
+// method, so it only borrows those two fields while a callback field is borrowed mutably.
+macro_rules! child_context {
+    ($client: expr) => {
+        Context::new(Some($client.socket.clone()))
+            .with_raw_send($client.on_raw_send.clone())
+            .with_serializer($client.serializer)
+    };
 }
 
 macro_rules! client_context_link {
     ($method_name: ident, $method_type: ident, $callback: ty) => {
+        pub fn $method_name(
+            &mut self,
+            $method_name: $method_type,
+            callback: $callback,
+        ) -> Result<(), Error> {
+            self.ensure_established()?;
+            self.context.$method_name($method_name, callback)
+        }
+    };
+    // Listeners are not sent to the router, so they can be attached in any state.
+    (listener: $method_name: ident, $method_type: ident, $callback: ty) => {
         pub fn $method_name(
             &mut self,
             $method_name: $method_type,
@@ -50,51 +283,2532 @@ impl Client {
     pub fn connect<U: ToString, P: ToString>(
         request: WampRequest<U, P>,
     ) -> Result<(Client, Response<Option<Vec<u8>>>), Error> {
-        let (socket, response) = connect(request)?;
-        let socket = Arc::new(Mutex::new(socket));
-        Ok((
-            Self {
-                socket: socket.clone(),
-                context: Context::new(Some(socket)),
-                on_welcome: None,
-                on_challenge: None,
-                on_goodbye: None,
-                on_extension: None,
-            },
-            response,
-        ))
+        ClientBuilder::new().connect(request)
+    }
+
+    /// # Client Connect With Tls Config
+    /// Connect like `Client::connect`, wrapping the stream with `connector` instead of the
+    /// default TLS settings, for routers with self-signed certificates or a private CA.
+    ///
+    /// Needs the `native-tls` feature.
+    ///
+    /// ## Examples
+    /// Trusting the CA that signed the router certificate, on top of the system roots:
+    /// ```
+    /// use std::fs;
+    /// use std::net::TcpListener;
+    /// use std::thread;
+    /// use native_tls::{Certificate, Identity, TlsAcceptor, TlsConnector};
+    /// use wamp_client::sync::client::Client;
+    /// use wamp_client::sync::WampRequest;
+    /// use wamp_client::test_util::establish;
+    /// use wamp_core::tungstenite::{accept, Connector, Message};
+    ///
+    /// fn fixture(name: &str) -> Vec<u8> {
+    ///     fs::read(format!("{}/fixtures/tls/{}", env!("CARGO_MANIFEST_DIR"), name)).unwrap()
+    /// }
+    ///
+    /// // A wss router whose certificate is signed by a private CA.
+    /// let identity = Identity::from_pkcs8(&fixture("router.pem"), &fixture("router-key.pem")).unwrap();
+    /// let acceptor = TlsAcceptor::new(identity).unwrap();
+    /// let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+    /// let uri = format!("wss://localhost:{}/ws", listener.local_addr().unwrap().port());
+    /// thread::spawn(move || {
+    ///     for stream in listener.incoming() {
+    ///         // The default TLS settings do not trust the CA, so that handshake fails.
+    ///         if let Ok(stream) = acceptor.accept(stream.unwrap()) {
+    ///             let mut socket = accept(stream).unwrap();
+    ///             while !socket.read().unwrap().is_text() {}
+    ///             socket.send(Message::Text(r#"[2,1,{"roles":{"broker":{}}}]"#.to_string())).unwrap();
+    ///             while socket.read().is_ok() {}
+    ///         }
+    ///     }
+    /// });
+    ///
+    /// assert!(Client::connect(WampRequest { uri: uri.clone(), protocol: "wamp.2.json" }).is_err());
+    ///
+    /// let ca = Certificate::from_pem(&fixture("router-ca.pem")).unwrap();
+    /// let connector = TlsConnector::builder().add_root_certificate(ca).build().unwrap();
+    ///
+    /// let (mut client, _) = Client::connect_with_tls_config(
+    ///     WampRequest { uri, protocol: "wamp.2.json" },
+    ///     Connector::NativeTls(connector),
+    /// )
+    /// .unwrap();
+    /// establish(&mut client);
+    /// ```
+    #[cfg(feature = "native-tls")]
+    pub fn connect_with_tls_config<U: ToString, P: ToString>(
+        request: WampRequest<U, P>,
+        connector: Connector,
+    ) -> Result<(Client, Response<Option<Vec<u8>>>), Error> {
+        ClientBuilder::new().tls_connector(connector).connect(request)
+    }
+
+    pub(crate) fn from_socket(
+        socket: Socket,
+        context: Context,
+        endpoint: String,
+        protocol: String,
+    ) -> Self {
+        let (finish_invocation, finished_invocations) = channel();
+        Self {
+            socket,
+            context,
+            endpoint,
+            protocol,
+            timeout: DEFAULT_TIMEOUT,
+            on_welcome: None,
+            on_challenge: None,
+            on_goodbye: None,
+            on_extension: None,
+            on_unmatched_event: None,
+            on_pong: None,
+            on_message: None,
+            on_close: None,
+            close_frame: None,
+            on_raw_send: None,
+            on_raw_recv: None,
+            serializer: Serializer::Json,
+            hello_sent: false,
+            buffered: false,
+            write_buffer: VecDeque::new(),
+            write_buffer_bytes: 0,
+            read_ahead_bytes: Rc::new(Cell::new(0)),
+            byte_budget: None,
+            dropped_messages: 0,
+            auto_goodbye: true,
+            request_id_matcher: None,
+            on_protocol_violation: None,
+            on_authorization_failed: None,
+            answered: VecDeque::with_capacity(ANSWERED_HISTORY),
+            acknowledged_keys: Rc::new(RefCell::new(HashSet::new())),
+            router_capabilities: None,
+            session_id: None,
+            router_roles: None,
+            last_errors: HashMap::new(),
+            send_metrics: HashMap::new(),
+            auth: None,
+            shutdown: CancellationToken::new(),
+            invocation_tokens: Rc::new(RefCell::new(HashMap::new())),
+            finish_invocation,
+            finished_invocations,
+            keepalive: None,
+            state: ConnectionState::Connecting,
+        }
+    }
+
+    /// # Client Endpoint
+    /// The URI the client connected to, as given in the `WampRequest`, for logs and for
+    /// connecting again.
+    ///
+    /// ## Examples
+    /// ```
+    /// use wamp_client::sync::{client::Client, WampRequest};
+    /// use wamp_client::test_util::MockRouter;
+    ///
+    /// let uri = MockRouter::silent();
+    ///
+    /// let (client, _) = Client::connect(WampRequest { uri: uri.clone(), protocol: "wamp.2.json" }).unwrap();
+    /// assert_eq!(client.endpoint(), uri);
+    /// assert_eq!(client.requested_protocol(), "wamp.2.json");
+    /// ```
+    pub fn endpoint(&self) -> &str {
+        &self.endpoint
+    }
+
+    /// The subprotocols the client asked for in the `WampRequest`, comma separated when it
+    /// offered several. The one the router picked is in the handshake response, see
+    /// `sync::negotiated_protocol`.
+    pub fn requested_protocol(&self) -> &str {
+        &self.protocol
+    }
+
+    /// Time the blocking helpers (such as `unregister_sync`) wait for a reply.
+    pub fn timeout(&self) -> Duration {
+        self.timeout
+    }
+
+    pub fn set_timeout(&mut self, timeout: Duration) -> &mut Self {
+        self.timeout = timeout;
+        self
+    }
+
+    /// # Client State
+    /// Where the client is in the life cycle of its session.
+    ///
+    /// Requests sent through the client before the router's `Welcome` return
+    /// `Error::NotEstablished`. Send the `Hello` (and `Authenticate`) with `Client::send`.
+    ///
+    /// ## Examples
+    /// ```
+    /// use wamp_client::error::Error;
+    /// use wamp_client::hello::HelloBuilder;
+    /// use wamp_client::sync::{client::{Client, ConnectionState}, WampRequest};
+    /// use wamp_client::test_util::MockRouter;
+    /// use wamp_core::call;
+    ///
+    /// let (uri, router) = MockRouter::spawn(move |router| {
+    ///     // The rejected call never reaches the router, the first frame is the `Hello`.
+    ///     assert_eq!(router.recv()[0], 1);
+    ///     router.send(r#"[2,1,{"roles":{"dealer":{}}}]"#);
+    /// });
+    ///
+    /// let (mut client, _) = Client::connect(WampRequest { uri, protocol: "wamp.2.json" }).unwrap();
+    /// assert_eq!(client.state(), ConnectionState::Connecting);
+    /// assert!(matches!(
+    ///     client.call(call!("com.example.add"), Box::new(|ctx, _| ctx)),
+    ///     Err(Error::NotEstablished(ConnectionState::Connecting))
+    /// ));
+    ///
+    /// client.send(HelloBuilder::new("realm1").build().unwrap()).unwrap();
+    /// while client.state() == ConnectionState::Connecting {
+    ///     let message = client.read().unwrap();
+    ///     client.read_contexts(message).unwrap();
+    /// }
+    /// assert_eq!(client.state(), ConnectionState::Established);
+    /// drop(client);
+    /// router.join().unwrap();
+    /// ```
+    pub fn state(&self) -> ConnectionState {
+        self.state
+    }
+
+    /// Credentials the client authenticates with.
+    pub fn set_auth(&mut self, auth: AuthConfig) -> &mut Self {
+        self.auth = Some(auth);
+        self
+    }
+
+    /// # Client Supported Auth Methods
+    /// The auth methods the client is configured for with `Client::set_auth`, in order of
+    /// preference. Empty without auth, which routers treat as `anonymous`.
+    ///
+    /// ## Examples
+    /// ```no_run
+    /// use wamp_client::sync::{auth::AuthConfig, client::Client, WampRequest};
+    ///
+    /// let (mut client, _) = Client::connect(WampRequest { uri: "ws://localhost:8080/ws", protocol: "wamp.2.json" }).unwrap();
+    /// client.set_auth(AuthConfig::new("alice").ticket("t0ps3cr3t").wampcra("s3cr3t"));
+    ///
+    /// assert_eq!(client.supported_auth_methods(), vec!["wampcra", "ticket"]);
+    ///
+    /// let hello = client.hello_builder("realm1").build().unwrap();
+    /// assert_eq!(hello.details["authmethods"][1], "ticket");
+    /// client.send(hello).unwrap();
+    /// ```
+    pub fn supported_auth_methods(&self) -> Vec<String> {
+        self.auth.as_ref().map(AuthConfig::methods).unwrap_or_default()
+    }
+
+    /// A `HelloBuilder` for `realm`, advertising the auth the client is configured for.
+    pub fn hello_builder<R: ToString>(&self, realm: R) -> HelloBuilder {
+        match &self.auth {
+            Some(auth) => HelloBuilder::new(realm).auth(auth),
+            None => HelloBuilder::new(realm),
+        }
+    }
+
+    /// The session's `CancellationToken`, cancelled by `Client::shutdown`. Clones can be
+    /// cancelled from another thread.
+    pub fn cancellation_token(&self) -> CancellationToken {
+        self.shutdown.clone()
+    }
+
+    /// # Client Shutdown
+    /// Cancel the session's `CancellationToken`, and with it the token of every invocation
+    /// handled by `Client::register_cancellable`.
+    pub fn shutdown(&self) {
+        self.shutdown.cancel();
+    }
+
+    /// Return `Error::NotEstablished` unless the router has welcomed the client.
+    fn ensure_established(&self) -> Result<(), Error> {
+        match self.state {
+            ConnectionState::Established => Ok(()),
+            state => Err(Error::NotEstablished(state)),
+        }
+    }
+
+    /// # Client Last Error
+    /// When the router last answered a request of `kind` with an `Error`, and the error itself.
+    ///
+    /// ## Examples
+    /// ```
+    /// use wamp_client::sync::client::{Client, RequestKind};
+    /// use wamp_client::sync::WampRequest;
+    /// use wamp_client::test_util::{establish, MockRouter};
+    /// use wamp_core::call;
+    ///
+    /// let (uri, router) = MockRouter::spawn(move |router| {
+    ///     router.welcome();
+    ///     let call = router.recv();
+    ///     assert_eq!(call[0], 48);
+    ///     let error = format!(r#"[8,48,{},{{}},"wamp.error.no_such_procedure"]"#, call[1]);
+    ///     router.send(error);
+    /// });
+    ///
+    /// let (mut client, _) = Client::connect(WampRequest { uri, protocol: "wamp.2.json" }).unwrap();
+    /// establish(&mut client);
+    /// assert!(client.last_error(RequestKind::Call).is_none());
+    ///
+    /// client.call(call!("com.example.missing"), Box::new(|ctx, _| ctx)).unwrap();
+    /// while client.last_error(RequestKind::Call).is_none() {
+    ///     let message = client.read().unwrap();
+    ///     client.read_contexts(message).unwrap();
+    /// }
+    ///
+    /// let (_, error) = client.last_error(RequestKind::Call).unwrap();
+    /// assert_eq!(error.error, "wamp.error.no_such_procedure");
+    /// assert!(client.last_error(RequestKind::Subscribe).is_none());
+    /// drop(client);
+    /// router.join().unwrap();
+    /// ```
+    pub fn last_error(&self, kind: RequestKind) -> Option<&(Instant, WampError)> {
+        self.last_errors.get(&kind)
+    }
+
+    /// # Client Send Metrics
+    /// How many requests of `kind` went through `Client::send` or `Client::write`, and how
+    /// fast, `None` until the first one. Requests sent by the contexts handed to callbacks are
+    /// not counted.
+    ///
+    /// ## Examples
+    /// ```
+    /// use wamp_client::sync::client::{Client, RequestKind};
+    /// use wamp_client::sync::WampRequest;
+    /// use wamp_client::test_util::MockRouter;
+    /// use wamp_core::publish;
+    ///
+    /// let uri = MockRouter::silent();
+    ///
+    /// let (mut client, _) = Client::connect(WampRequest { uri, protocol: "wamp.2.json" }).unwrap();
+    /// assert!(client.send_metrics(RequestKind::Publish).is_none());
+    ///
+    /// client.send(publish!("com.example.topic")).unwrap();
+    /// client.write(publish!("com.example.topic")).unwrap();
+    /// client.flush().unwrap();
+    ///
+    /// let metrics = client.send_metrics(RequestKind::Publish).unwrap();
+    /// assert_eq!(metrics.count, 2);
+    /// assert!(metrics.first <= metrics.last);
+    /// assert!(metrics.rate() >= 0.0);
+    /// assert!(client.send_metrics(RequestKind::Call).is_none());
+    /// ```
+    pub fn send_metrics(&self, kind: RequestKind) -> Option<&SendMetrics> {
+        self.send_metrics.get(&kind)
+    }
+
+    /// # Client Router Capabilities
+    /// The roles and features the router announced in its `Welcome`, `None` until the session
+    /// is established.
+    pub fn router_capabilities(&self) -> Option<&RouterCapabilities> {
+        self.router_capabilities.as_ref()
+    }
+
+    /// # Client Session Id
+    /// The session id the router assigned in its `Welcome`, for meta API calls and logging.
+    /// `None` until the session is established, and again once a `Goodbye` or `Abort` ends it.
+    ///
+    /// ## Examples
+    /// ```
+    /// use wamp_client::sync::client::{Client, ConnectionState};
+    /// use wamp_client::sync::WampRequest;
+    /// use wamp_client::test_util::{establish, MockRouter};
+    /// use wamp_core::serde_json::json;
+    ///
+    /// let (uri, router) = MockRouter::spawn(move |router| {
+    ///     router.socket().read().unwrap();
+    ///     router.send(r#"[2,4242,{"roles":{"broker":{},"dealer":{}}}]"#);
+    ///     // Wait for the client's ping, sent once it looked at the session, before ending it.
+    ///     router.socket().read().unwrap();
+    ///     router.send(r#"[6,{},"wamp.close.system_shutdown"]"#);
+    /// });
+    ///
+    /// let (mut client, _) = Client::connect(WampRequest { uri, protocol: "wamp.2.json" }).unwrap();
+    /// assert_eq!(client.session_id(), None);
+    /// assert_eq!(client.router_roles(), None);
+    ///
+    /// establish(&mut client);
+    /// assert_eq!(client.session_id(), Some(4242));
+    /// assert_eq!(client.router_roles(), Some(&json!({"broker": {}, "dealer": {}})));
+    ///
+    /// client.ping(vec![]).unwrap();
+    /// while client.state() == ConnectionState::Established {
+    ///     let message = client.read().unwrap();
+    ///     client.read_contexts(message).unwrap();
+    /// }
+    /// assert_eq!(client.session_id(), None);
+    /// assert_eq!(client.router_roles(), None);
+    /// drop(client);
+    /// router.join().unwrap();
+    /// ```
+    pub fn session_id(&self) -> Option<u64> {
+        self.session_id
+    }
+
+    /// # Client Router Roles
+    /// The `roles` the router announced in the details of its `Welcome`, as sent. `None` until
+    /// the session is established, and again once a `Goodbye` or `Abort` ends it.
+    pub fn router_roles(&self) -> Option<&Value> {
+        self.router_roles.as_ref()
+    }
+
+    /// # Client Connection Config
+    /// The max message and frame sizes and compression state in effect on the live socket.
+    ///
+    /// ## Examples
+    /// ```
+    /// use wamp_client::sync::{builder::ClientBuilder, WampRequest};
+    /// use wamp_client::test_util::MockRouter;
+    /// use wamp_core::tungstenite::protocol::WebSocketConfig;
+    ///
+    /// let uri = MockRouter::silent();
+    ///
+    /// let mut config = WebSocketConfig::default();
+    /// config.max_message_size = Some(1 << 20);
+    /// config.max_frame_size = Some(1 << 16);
+    /// let write_buffer_size = config.write_buffer_size;
+    ///
+    /// let (client, _) = ClientBuilder::new()
+    ///     .websocket_config(config)
+    ///     .connect(WampRequest { uri, protocol: "wamp.2.json" })
+    ///     .unwrap();
+    ///
+    /// let summary = client.connection_config();
+    /// assert_eq!(summary.max_message_size, Some(1 << 20));
+    /// assert_eq!(summary.max_frame_size, Some(1 << 16));
+    /// assert_eq!(summary.write_buffer_size, write_buffer_size);
+    /// assert!(!summary.compression);
+    /// ```
+    pub fn connection_config(&self) -> WebSocketConfigSummary {
+        WebSocketConfigSummary::from(self.socket.lock().unwrap().get_config())
+    }
+
+    /// # Client Serializer
+    /// The serialization frames are sent and read in. `ClientBuilder::connect` picks it from the
+    /// subprotocol the router accepted in the handshake, JSON if it accepted none the client
+    /// speaks.
+    pub fn serializer(&self) -> Serializer {
+        self.serializer
+    }
+
+    /// Send frames in `serializer` from now on, for sockets set up outside `Client::connect`.
+    pub fn set_serializer(&mut self, serializer: Serializer) -> &mut Self {
+        self.serializer = serializer;
+        self.context.serializer = serializer;
+        self
     }
 
     client_context_link!(publish, Publish, CallBackResult<Published>);
     client_context_link!(register, Register, CallBackResult<Registered>);
     client_context_link!(unregister, Unregister, CallBackResult<Unregistered>);
-    client_context_link!(event, Subscribed, CallBack<Event>);
-    client_context_link!(unsubscribe, Unsubscribe, CallBackResult<Unsubscribed>);
+    client_context_link!(listener: event, Subscribed, CallBack<Event>);
     client_context_link!(subscribe, Subscribe, CallBackResult<Subscribed>);
     client_context_link!(call, Call, CallBackResult<WampResult>);
-    client_context_link!(invocation, Registered, CallBackResult<Invocation>);
+    client_context_link!(listener: invocation, Registered, CallBackResult<Invocation>);
     client_context_link!(cancel, Cancel, CallBackResult<Interrupt>);
 
+    /// # Client Call Pending
+    /// Call a procedure, returning a handle that cancels exactly this call.
+    /// See `Context::call_pending`.
+    pub fn call_pending(
+        &mut self,
+        call: Call,
+        callback: CallBackResult<WampResult>,
+    ) -> Result<PendingCall, Error> {
+        self.ensure_established()?;
+        self.context.call_pending(call, callback)
+    }
+
+    /// # Client Call Cancellable
+    /// Call a procedure, returning a token to cancel exactly this call with
+    /// `Client::cancel_call`. See `Context::call_cancellable`.
+    pub fn call_cancellable(
+        &mut self,
+        call: Call,
+        callback: CallBackResult<WampResult>,
+    ) -> Result<CallToken, Error> {
+        self.ensure_established()?;
+        self.context.call_cancellable(call, callback)
+    }
+
+    /// # Client Cancel Call
+    /// Cancel the call `token` was returned for. See `Context::cancel_call`.
+    pub fn cancel_call(&mut self, token: CallToken, mode: CancelMode) -> Result<Cancel, Error> {
+        self.ensure_established()?;
+        self.context.cancel_call(token, mode)
+    }
+
+    /// # Client Call Correlated
+    /// Call a procedure, handing a failed call's callback the original `Call` with the error.
+    /// See `Context::call_correlated`.
+    pub fn call_correlated(
+        &mut self,
+        call: Call,
+        callback: CallBack<Result<WampResult, CallFailure>>,
+    ) -> Result<(), Error> {
+        self.ensure_established()?;
+        self.context.call_correlated(call, callback)
+    }
+
+    /// # Client Publish With Sent
+    /// Publish with a local `on_sent` signal when `acknowledge` is off.
+    /// See `Context::publish_with_sent`.
+    pub fn publish_with_sent(
+        &mut self,
+        publish: Publish,
+        callback: CallBackResult<Published>,
+        on_sent: impl FnOnce(&Publish),
+    ) -> Result<(), Error> {
+        self.ensure_established()?;
+        self.context.publish_with_sent(publish, callback, on_sent)
+    }
+
+    /// # Client Publish With Options
+    /// Publish with typed options, such as publisher exclusion and eligibility.
+    /// See `Context::publish_with_options`.
+    pub fn publish_with_options(
+        &mut self,
+        publish: Publish,
+        options: PublishOptions,
+        callback: CallBackResult<Option<Published>>,
+    ) -> Result<(), Error> {
+        self.ensure_established()?;
+        self.context.publish_with_options(publish, options, callback)
+    }
+
+    /// # Client Register With Options
+    /// Register a procedure with typed options, such as a shared registration's `invoke` policy.
+    /// See `Context::register_with_options`.
+    pub fn register_with_options(
+        &mut self,
+        register: Register,
+        options: RegisterOptions,
+        callback: CallBackResult<Registered>,
+    ) -> Result<(), Error> {
+        self.ensure_established()?;
+        self.context.register_with_options(register, options, callback)
+    }
+
+    /// # Client Unsubscribe
+    /// Unsubscribe from a subscription the router has confirmed with a `Subscribed`.
+    ///
+    /// Returns `Error::NoSubscription` if the subscription id is not known to the client context
+    /// yet, since unsubscribing before the `Subscribed` arrives would leave the entries dangling.
+    ///
+    /// ## Examples
+    /// ```
+    /// use wamp_client::error::Error;
+    /// use wamp_client::options::SubscribeOptions;
+    /// use wamp_client::sync::client::Client;
+    /// use wamp_client::sync::WampRequest;
+    /// use wamp_client::test_util::{establish, MockRouter};
+    /// use wamp_core::serde_json::json;
+    /// use wamp_core::unsubscribe;
+    ///
+    /// let (uri, router) = MockRouter::spawn(move |router| {
+    ///     router.welcome();
+    ///     let subscribe = router.recv();
+    ///     router.send(json!([33, subscribe[1], 5]));
+    ///
+    ///     // Only the unsubscribe sent after the `Subscribed` reaches the router.
+    ///     let unsubscribe = router.recv();
+    ///     assert_eq!((unsubscribe[0].clone(), unsubscribe[2].clone()), (json!(34), json!(5)));
+    ///     router.send(json!([35, unsubscribe[1]]));
+    /// });
+    ///
+    /// let (mut client, _) = Client::connect(WampRequest { uri, protocol: "wamp.2.json" }).unwrap();
+    /// establish(&mut client);
+    ///
+    /// let _events = client.event_channel("topic", SubscribeOptions::default()).unwrap();
+    /// let early = client.unsubscribe(unsubscribe!(5), Box::new(|ctx, _| ctx));
+    /// assert!(matches!(early, Err(Error::NoSubscription)));
+    ///
+    /// while !client.context.is_subscribed(5) {
+    ///     let message = client.read().unwrap();
+    ///     client.read_contexts(message).unwrap();
+    /// }
+    /// client.unsubscribe(unsubscribe!(5), Box::new(|ctx, _| ctx)).unwrap();
+    /// while client.context.is_subscribed(5) {
+    ///     let message = client.read().unwrap();
+    ///     client.read_contexts(message).unwrap();
+    /// }
+    /// assert_eq!(client.context.pending_total(), 0);
+    /// drop(client);
+    /// router.join().unwrap();
+    /// ```
+    pub fn unsubscribe(
+        &mut self,
+        unsubscribe: Unsubscribe,
+        callback: CallBackResult<Unsubscribed>,
+    ) -> Result<(), Error> {
+        self.ensure_established()?;
+        if !self.context.is_subscribed(unsubscribe.subscription) {
+            return Err(Error::NoSubscription);
+        }
+        self.context.unsubscribe(unsubscribe, callback)
+    }
+
     pub fn on_welcome(&mut self, on_welcome: CallBack<Welcome>) -> &mut Self {
         self.on_welcome = Some(on_welcome);
         self
     }
 
+    /// # Client On Challenge
+    /// Called with every `Challenge`, answer it by sending an `Authenticate` on the context.
+    ///
+    /// Routers may re-challenge an established session to re-authenticate it. Subscriptions,
+    /// registrations and pending requests are left as they are, and the client stays
+    /// `ConnectionState::Established` throughout.
+    ///
+    /// ## Examples
+    /// A subscription made before the router re-challenges keeps receiving events:
+    /// ```
+    /// use wamp_client::options::SubscribeOptions;
+    /// use wamp_client::sync::auth::ticket_auth;
+    /// use wamp_client::sync::client::{Client, ConnectionState};
+    /// use wamp_client::sync::WampRequest;
+    /// use wamp_client::test_util::{establish, MockRouter};
+    /// use wamp_core::serde_json::json;
+    ///
+    /// let (uri, router) = MockRouter::spawn(move |router| {
+    ///     router.welcome();
+    ///     let subscribe = router.recv();
+    ///     router.send(json!([33, subscribe[1], 5]));
+    ///
+    ///     router.send(r#"[4,"ticket",{}]"#);
+    ///     let authenticate = router.recv();
+    ///     assert_eq!(authenticate[0], 5);
+    ///     assert_eq!(authenticate[1], "s3cr3t");
+    ///
+    ///     router.send(r#"[36,5,1,{},["after"]]"#);
+    /// });
+    ///
+    /// let (mut client, _) = Client::connect(WampRequest { uri, protocol: "wamp.2.json" }).unwrap();
+    /// client.on_challenge(Box::new(|mut ctx, _| {
+    ///     ctx.send(ticket_auth("s3cr3t")).unwrap();
+    ///     ctx
+    /// }));
+    /// establish(&mut client);
+    ///
+    /// let events = client.event_channel("topic", SubscribeOptions::default()).unwrap();
+    /// let event = loop {
+    ///     if let Ok(event) = events.try_recv() {
+    ///         break event;
+    ///     }
+    ///     let message = client.read().unwrap();
+    ///     client.read_contexts(message).unwrap();
+    /// };
+    ///
+    /// assert_eq!(event.args, json!(["after"]));
+    /// assert_eq!(client.state(), ConnectionState::Established);
+    /// assert!(client.context.is_subscribed(5));
+    /// drop(client);
+    /// router.join().unwrap();
+    /// ```
     pub fn on_challenge(&mut self, on_challenge: CallBack<Challenge>) -> &mut Self {
         self.on_challenge = Some(on_challenge);
         self
     }
 
+    /// # Client On Goodbye
+    /// Called with the `Goodbye` the router ends the session with.
+    ///
+    /// Unless turned off with `Client::set_auto_goodbye`, the client answers it with a
+    /// `wamp.close.goodbye_and_out` `Goodbye` of its own after the callback returns.
     pub fn on_goodbye(&mut self, on_goodbye: CallBack<Goodbye>) -> &mut Self {
         self.on_goodbye = Some(on_goodbye);
         self
     }
 
+    /// # Client On Extension
+    /// Called with every extension frame, a message type outside the WAMP spec, as the raw JSON
+    /// array it was received as.
+    ///
+    /// ## Examples
+    /// ```
+    /// use std::cell::RefCell;
+    /// use std::rc::Rc;
+    /// use wamp_client::hello::HelloBuilder;
+    /// use wamp_client::sync::client::{Client, LoopExit};
+    /// use wamp_client::sync::WampRequest;
+    /// use wamp_client::test_util::MockRouter;
+    /// use wamp_core::serde_json::json;
+    ///
+    /// let (uri, router) = MockRouter::spawn(move |router| {
+    ///     router.welcome();
+    ///     router.send(r#"[300,{"trace":"abc"},42]"#);
+    ///     router.send(r#"[6,{},"wamp.close.system_shutdown"]"#);
+    /// });
+    ///
+    /// let (mut client, _) = Client::connect(WampRequest { uri, protocol: "wamp.2.json" }).unwrap();
+    /// let frames = Rc::new(RefCell::new(vec![]));
+    /// let frames2 = frames.clone();
+    /// client.on_extension(Box::new(move |ctx, frame| {
+    ///     frames2.borrow_mut().push(frame);
+    ///     ctx
+    /// }));
+    /// client.send(HelloBuilder::new("realm1").build().unwrap()).unwrap();
+    ///
+    /// assert!(matches!(client.event_loop().unwrap(), LoopExit::Goodbye(_)));
+    /// assert_eq!(*frames.borrow(), vec![json!([300, { "trace": "abc" }, 42])]);
+    /// drop(client);
+    /// router.join().unwrap();
+    /// ```
     pub fn on_extension(&mut self, on_extension: CallBack<Value>) -> &mut Self {
         self.on_extension = Some(on_extension);
         self
     }
 
+    /// # Client Set Auto Goodbye
+    /// Whether a router `Goodbye` is answered automatically, completing the closing handshake,
+    /// on by default. Turn it off to answer from `Client::on_goodbye` instead.
+    ///
+    /// The reply is written right away, in buffered mode too.
+    ///
+    /// ## Examples
+    /// ```
+    /// use wamp_client::hello::HelloBuilder;
+    /// use wamp_client::sync::client::{Client, ConnectionState};
+    /// use wamp_client::sync::WampRequest;
+    /// use wamp_client::test_util::MockRouter;
+    ///
+    /// let (uri, router) = MockRouter::spawn(move |router| {
+    ///     router.welcome();
+    ///     router.send(r#"[6,{},"wamp.close.system_shutdown"]"#);
+    ///     router.recv()
+    /// });
+    ///
+    /// let (mut client, _) = Client::connect(WampRequest { uri, protocol: "wamp.2.json" }).unwrap();
+    /// client.send(HelloBuilder::new("realm1").build().unwrap()).unwrap();
+    /// client.set_buffered(true);
+    /// while client.state() != ConnectionState::Closed {
+    ///     let message = client.read().unwrap();
+    ///     client.read_contexts(message).unwrap();
+    /// }
+    ///
+    /// // Answered without a `Client::flush`.
+    /// drop(client);
+    /// let reply = router.join().unwrap();
+    /// assert_eq!(reply[0], 6);
+    /// assert_eq!(reply[2], "wamp.close.goodbye_and_out");
+    /// ```
+    pub fn set_auto_goodbye(&mut self, auto_goodbye: bool) -> &mut Self {
+        self.auto_goodbye = auto_goodbye;
+        self
+    }
+
+    /// # Client On Unmatched Event
+    /// Called with every `Event` whose subscription id has no event listener, which points at a
+    /// leaked subscription or a listener removed while events were in flight.
+    ///
+    /// ## Examples
+    /// ```
+    /// use std::cell::RefCell;
+    /// use std::rc::Rc;
+    /// use wamp_client::sync::client::Client;
+    /// use wamp_client::sync::WampRequest;
+    /// use wamp_client::test_util::{establish, MockRouter};
+    /// use wamp_core::messages::Subscribed;
+    ///
+    /// let (uri, router) = MockRouter::spawn(move |router| {
+    ///     router.welcome();
+    ///     router.send(r#"[36,5,1,{},["known"]]"#);
+    ///     router.send(r#"[36,9,2,{},["unknown"]]"#);
+    ///     router.send(r#"[6,{},"wamp.close.system_shutdown"]"#);
+    /// });
+    ///
+    /// let (mut client, _) = Client::connect(WampRequest { uri, protocol: "wamp.2.json" }).unwrap();
+    /// establish(&mut client);
+    ///
+    /// let matched = Rc::new(RefCell::new(vec![]));
+    /// let unmatched = Rc::new(RefCell::new(vec![]));
+    /// let (matched2, unmatched2) = (matched.clone(), unmatched.clone());
+    /// client
+    ///     .event(Subscribed { request_id: 1, subscription: 5 }, Box::new(move |ctx, event| {
+    ///         matched2.borrow_mut().push(event.subscription);
+    ///         ctx
+    ///     }))
+    ///     .unwrap();
+    /// client.on_unmatched_event(Box::new(move |ctx, event| {
+    ///     unmatched2.borrow_mut().push(event.subscription);
+    ///     ctx
+    /// }));
+    ///
+    /// client.event_loop().unwrap();
+    /// assert_eq!(*matched.borrow(), vec![5]);
+    /// assert_eq!(*unmatched.borrow(), vec![9]);
+    /// drop(client);
+    /// router.join().unwrap();
+    /// ```
+    pub fn on_unmatched_event(&mut self, on_unmatched_event: CallBack<Event>) -> &mut Self {
+        self.on_unmatched_event = Some(on_unmatched_event);
+        self
+    }
+
+    /// # Client On Pong
+    /// Called with the payload of every WebSocket `Pong` frame read from the socket.
+    ///
+    /// Pair this with a `Ping` carrying a timestamp to measure the round trip time to the router.
+    ///
+    /// ## Examples
+    /// ```
+    /// use std::cell::RefCell;
+    /// use std::rc::Rc;
+    /// use std::time::{SystemTime, UNIX_EPOCH};
+    /// use wamp_client::hello::HelloBuilder;
+    /// use wamp_client::sync::client::Client;
+    /// use wamp_client::sync::WampRequest;
+    /// use wamp_client::test_util::MockRouter;
+    ///
+    /// // Tungstenite answers pings while the router reads.
+    /// let uri = MockRouter::silent();
+    ///
+    /// let (mut client, _) = Client::connect(WampRequest { uri, protocol: "wamp.2.json" }).unwrap();
+    /// let pongs = Rc::new(RefCell::new(vec![]));
+    /// let pongs2 = pongs.clone();
+    /// client.on_pong(move |payload| pongs2.borrow_mut().push(payload.to_vec()));
+    /// // The router never welcomes the client, the `Hello` only lets it read.
+    /// client.send(HelloBuilder::new("realm1").build().unwrap()).unwrap();
+    ///
+    /// let sent_at = SystemTime::now().duration_since(UNIX_EPOCH).unwrap().as_nanos();
+    /// client.ping(sent_at.to_be_bytes().to_vec()).unwrap();
+    /// while pongs.borrow().is_empty() {
+    ///     client.read().unwrap();
+    /// }
+    ///
+    /// assert_eq!(*pongs.borrow(), vec![sent_at.to_be_bytes().to_vec()]);
+    /// ```
+    pub fn on_pong(&mut self, on_pong: impl FnMut(&[u8]) + 'static) -> &mut Self {
+        self.on_pong = Some(Box::new(on_pong));
+        self
+    }
+
+    /// # Client On Message
+    /// Called with every frame passed to `Client::read_contexts`, which `Client::event_loop`
+    /// does for each frame it reads, before it is routed. Error frames, duplicate replies and
+    /// frames no callback is waiting for are observed too, so this is a single tap for logging
+    /// and metrics.
+    ///
+    /// ## Examples
+    /// ```
+    /// use std::cell::RefCell;
+    /// use std::rc::Rc;
+    /// use wamp_client::hello::HelloBuilder;
+    /// use wamp_client::sync::{client::Client, WampRequest};
+    /// use wamp_client::test_util::MockRouter;
+    ///
+    /// let (uri, router) = MockRouter::spawn(move |router| {
+    ///     router.socket().read().unwrap();
+    ///     for frame in &[
+    ///         r#"[2,1,{"roles":{"broker":{}}}]"#,
+    ///         // Neither the error nor the event has a callback waiting for it.
+    ///         r#"[8,48,99,{},"wamp.error.no_such_procedure"]"#,
+    ///         r#"[36,5,6,{},[]]"#,
+    ///         r#"[6,{},"wamp.close.system_shutdown"]"#,
+    ///     ] {
+    ///         router.send(frame);
+    ///     }
+    /// });
+    ///
+    /// let (mut client, _) = Client::connect(WampRequest { uri, protocol: "wamp.2.json" }).unwrap();
+    /// let observed = Rc::new(RefCell::new(0));
+    /// let observed2 = observed.clone();
+    /// client.on_message(move |_message| *observed2.borrow_mut() += 1);
+    /// client.send(HelloBuilder::new("realm1").build().unwrap()).unwrap();
+    ///
+    /// client.event_loop().unwrap();
+    /// assert_eq!(*observed.borrow(), 4);
+    /// drop(client);
+    /// router.join().unwrap();
+    /// ```
+    pub fn on_message(&mut self, on_message: impl FnMut(&Messages) + 'static) -> &mut Self {
+        self.on_message = Some(Box::new(on_message));
+        self
+    }
+
+    /// # Client On Close
+    /// Called with the WebSocket `Close` frame the router closes the connection with, carrying
+    /// its close code and reason. A close frame without a payload is reported with
+    /// `CloseCode::Status`, the code for no status received.
+    ///
+    /// Once the close frame is read the client stops reading: `Client::read` returns
+    /// `Error::ConnectionClosed` and the frame stays available through `Client::close_frame`.
+    ///
+    /// ## Examples
+    /// ```
+    /// use std::cell::RefCell;
+    /// use std::rc::Rc;
+    /// use wamp_client::error::Error;
+    /// use wamp_client::hello::HelloBuilder;
+    /// use wamp_client::sync::client::{Client, LoopExit};
+    /// use wamp_client::sync::WampRequest;
+    /// use wamp_client::test_util::MockRouter;
+    /// use wamp_core::tungstenite::protocol::{frame::coding::CloseCode, CloseFrame};
+    ///
+    /// let (uri, router) = MockRouter::spawn(move |router| {
+    ///     router.socket().read().unwrap();
+    ///     socket
+    ///         .close(Some(CloseFrame { code: CloseCode::Away, reason: "maintenance".into() }))
+    ///         .unwrap();
+    /// });
+    ///
+    /// let (mut client, _) = Client::connect(WampRequest { uri, protocol: "wamp.2.json" }).unwrap();
+    /// let closed = Rc::new(RefCell::new(None));
+    /// let closed2 = closed.clone();
+    /// client.on_close(Box::new(move |ctx, frame| {
+    ///     *closed2.borrow_mut() = Some((frame.code, frame.reason.to_string()));
+    ///     ctx
+    /// }));
+    /// client.send(HelloBuilder::new("realm1").build().unwrap()).unwrap();
+    ///
+    /// assert!(matches!(client.event_loop(), Ok(LoopExit::Closed)));
+    /// assert_eq!(*closed.borrow(), Some((CloseCode::Away, "maintenance".to_string())));
+    /// assert!(matches!(client.read(), Err(Error::ConnectionClosed)));
+    /// router.join().unwrap();
+    /// ```
+    pub fn on_close(&mut self, on_close: CallBack<CloseFrame<'static>>) -> &mut Self {
+        self.on_close = Some(on_close);
+        self
+    }
+
+    /// The WebSocket `Close` frame the router closed the connection with, once it was read.
+    pub fn close_frame(&self) -> Option<&CloseFrame<'static>> {
+        self.close_frame.as_ref()
+    }
+
+    /// # Client On Raw Send
+    /// Called with the exact bytes of every text or binary frame written to the socket, by the
+    /// client or by the contexts handed to callbacks, for wire level debugging and audit logs.
+    ///
+    /// Frames queued on a context without a socket are observed when `Client::read_contexts`
+    /// flushes them, and frames buffered with `Client::write` when they are flushed, so each
+    /// frame is seen once, right before it is written. Nothing is done per frame while unset.
+    ///
+    /// ## Examples
+    /// The hooks see the frames byte for byte as the router reads and writes them:
+    /// ```
+    /// use std::cell::RefCell;
+    /// use std::rc::Rc;
+    /// use std::sync::mpsc::channel;
+    /// use wamp_client::sync::client::Client;
+    /// use wamp_client::sync::WampRequest;
+    /// use wamp_client::test_util::{establish, MockRouter, WELCOME};
+    /// use wamp_core::call;
+    ///
+    /// let (frames, received) = channel();
+    /// let (uri, router) = MockRouter::spawn(move |router| {
+    ///     router.welcome();
+    ///     frames.send(router.socket().read().unwrap().into_text().unwrap()).unwrap();
+    /// });
+    ///
+    /// let (mut client, _) = Client::connect(WampRequest { uri, protocol: "wamp.2.json" }).unwrap();
+    /// let sent = Rc::new(RefCell::new(vec![]));
+    /// let read = Rc::new(RefCell::new(vec![]));
+    /// let (sent2, read2) = (sent.clone(), read.clone());
+    /// client.on_raw_send(move |bytes| sent2.borrow_mut().push(bytes.to_vec()));
+    /// client.on_raw_recv(move |bytes| read2.borrow_mut().push(bytes.to_vec()));
+    ///
+    /// establish(&mut client);
+    /// assert_eq!(read.borrow().last().unwrap(), WELCOME.as_bytes());
+    ///
+    /// client.call(call!("com.example.add"), Box::new(|ctx, _| ctx)).unwrap();
+    /// let call = received.recv().unwrap();
+    /// assert!(call.starts_with("[48,"));
+    /// assert_eq!(sent.borrow().last().unwrap(), call.as_bytes());
+    /// drop(client);
+    /// router.join().unwrap();
+    /// ```
+    ///
+    /// Publishes sent directly and queued ones both reach the hook:
+    /// ```
+    /// use std::cell::RefCell;
+    /// use std::rc::Rc;
+    /// use wamp_client::sync::client::Client;
+    /// use wamp_client::sync::context::Context;
+    /// use wamp_client::sync::WampRequest;
+    /// use wamp_client::test_util::{establish, MockRouter};
+    /// use wamp_core::publish;
+    /// use wamp_core::serde_json::{from_slice, Value};
+    ///
+    /// let (uri, router) = MockRouter::spawn(move |router| {
+    ///     router.welcome();
+    ///     assert_eq!(router.recv()[3], "com.example.direct");
+    ///     router.send(r#"[6,{},"wamp.close.system_shutdown"]"#);
+    ///     assert_eq!(router.recv()[3], "com.example.queued");
+    /// });
+    ///
+    /// let (mut client, _) = Client::connect(WampRequest { uri, protocol: "wamp.2.json" }).unwrap();
+    /// let sent = Rc::new(RefCell::new(vec![]));
+    /// let sent2 = sent.clone();
+    /// client.on_raw_send(move |bytes| sent2.borrow_mut().push(from_slice::<Value>(bytes).unwrap()));
+    /// establish(&mut client);
+    ///
+    /// client.send(publish!("com.example.direct")).unwrap();
+    /// let mut queued = Context::new(None);
+    /// queued.send(publish!("com.example.queued")).unwrap();
+    /// client.context.extend(queued);
+    /// client.event_loop().unwrap();
+    ///
+    /// let published: Vec<Value> = sent
+    ///     .borrow()
+    ///     .iter()
+    ///     .filter(|frame| frame[0] == 16)
+    ///     .map(|frame| frame[3].clone())
+    ///     .collect();
+    /// assert_eq!(published, vec!["com.example.direct", "com.example.queued"]);
+    /// drop(client);
+    /// router.join().unwrap();
+    /// ```
+    pub fn on_raw_send(&mut self, on_raw_send: impl FnMut(&[u8]) + 'static) -> &mut Self {
+        let on_raw_send: RawHook = Rc::new(RefCell::new(on_raw_send));
+        self.context.on_raw_send = Some(on_raw_send.clone());
+        self.on_raw_send = Some(on_raw_send);
+        self
+    }
+
+    /// # Client On Raw Recv
+    /// Called with the exact bytes of every text or binary frame read from the socket, before it
+    /// is parsed.
+    pub fn on_raw_recv(&mut self, on_raw_recv: impl FnMut(&[u8]) + 'static) -> &mut Self {
+        self.on_raw_recv = Some(Rc::new(RefCell::new(on_raw_recv)));
+        self
+    }
+
+    /// # Client Request Id Matcher
+    /// Override how the request id of an inbound reply is extracted before it is matched against
+    /// pending requests, for routers that echo it somewhere non-standard.
+    ///
+    /// The matcher is called for every reply frame (`Error`, `Published`, `Subscribed`,
+    /// `Unsubscribed`, `Result`, `Registered`, `Unregistered`, `Interrupt`). Returning `Some(id)`
+    /// matches the reply on `id`, returning `None` falls back to the spec standard `request_id`.
+    ///
+    /// ## Examples
+    /// ```
+    /// use std::cell::Cell;
+    /// use std::rc::Rc;
+    /// use wamp_client::sync::client::Client;
+    /// use wamp_client::sync::WampRequest;
+    /// use wamp_client::test_util::{establish, MockRouter};
+    /// use wamp_core::call;
+    /// use wamp_core::messages::Messages;
+    ///
+    /// let (uri, router) = MockRouter::spawn(move |router| {
+    ///     router.welcome();
+    ///     let call = router.recv();
+    ///     // The request id goes in the details, the standard field holds garbage.
+    ///     let result = format!(r#"[50,999999,{{"request_id":{}}},["ok"]]"#, call[1]);
+    ///     router.send(result);
+    /// });
+    ///
+    /// let (mut client, _) = Client::connect(WampRequest { uri, protocol: "wamp.2.json" }).unwrap();
+    ///
+    /// // This router echoes the request id of a `Result` in its details instead.
+    /// client.set_request_id_matcher(|message| match message {
+    ///     Messages::Result(result) => result.details["request_id"].as_u64(),
+    ///     _ => None,
+    /// });
+    ///
+    /// establish(&mut client);
+    ///
+    /// let answered = Rc::new(Cell::new(false));
+    /// let answered2 = answered.clone();
+    /// client.call(call!("com.example.echo"), Box::new(move |ctx, result| {
+    ///     assert_eq!(result.unwrap().args[0], "ok");
+    ///     answered2.set(true);
+    ///     ctx
+    /// })).unwrap();
+    /// while !answered.get() {
+    ///     let message = client.read().unwrap();
+    ///     client.read_contexts(message).unwrap();
+    /// }
+    /// drop(client);
+    /// router.join().unwrap();
+    /// ```
+    pub fn set_request_id_matcher(
+        &mut self,
+        matcher: impl FnMut(&Messages) -> Option<u64> + 'static,
+    ) -> &mut Self {
+        self.request_id_matcher = Some(Box::new(matcher));
+        self
+    }
+
+    /// # Client On Protocol Violation
+    /// Called with every reply frame that answers a request id which already had a reply of the
+    /// same kind, from a router bug or a reused request id.
+    ///
+    /// Duplicate replies are never routed to the request callbacks, whether this hook is set or
+    /// not. Progressive call results are not duplicates.
+    ///
+    /// ## Examples
+    /// A second call reusing a pending request id is refused, and the router answering the first
+    /// one twice reaches the hook instead of the call's callback:
+    /// ```
+    /// use std::cell::Cell;
+    /// use std::rc::Rc;
+    /// use wamp_client::error::Error;
+    /// use wamp_client::sync::client::Client;
+    /// use wamp_client::sync::WampRequest;
+    /// use wamp_client::test_util::{establish, MockRouter};
+    /// use wamp_core::call;
+    /// use wamp_core::messages::{Call, Messages};
+    ///
+    /// fn call_seven() -> Call {
+    ///     let mut call = call!("com.example.answer");
+    ///     call.request_id = 7;
+    ///     call
+    /// }
+    ///
+    /// let (uri, router) = MockRouter::spawn(move |router| {
+    ///     router.welcome();
+    ///     assert_eq!(router.recv()[1], 7);
+    ///     router.send(r#"[50,7,{},[42]]"#);
+    ///     router.send(r#"[50,7,{},[42]]"#);
+    /// });
+    ///
+    /// let (mut client, _) = Client::connect(WampRequest { uri, protocol: "wamp.2.json" }).unwrap();
+    /// let violations = Rc::new(Cell::new(0));
+    /// let violations2 = violations.clone();
+    /// client.on_protocol_violation(move |message| {
+    ///     assert!(matches!(message, Messages::Result(_)));
+    ///     violations2.set(violations2.get() + 1);
+    /// });
+    /// establish(&mut client);
+    ///
+    /// let results = Rc::new(Cell::new(0));
+    /// let results2 = results.clone();
+    /// client.call(call_seven(), Box::new(move |ctx, _| {
+    ///     results2.set(results2.get() + 1);
+    ///     ctx
+    /// })).unwrap();
+    /// let reused = client.call(call_seven(), Box::new(|ctx, _| ctx));
+    /// assert!(matches!(reused, Err(Error::DuplicateRequestId(7))));
+    ///
+    /// while violations.get() == 0 {
+    ///     let message = client.read().unwrap();
+    ///     client.read_contexts(message).unwrap();
+    /// }
+    /// assert_eq!((results.get(), violations.get()), (1, 1));
+    /// drop(client);
+    /// router.join().unwrap();
+    /// ```
+    pub fn on_protocol_violation(
+        &mut self,
+        on_protocol_violation: impl FnMut(&Messages) + 'static,
+    ) -> &mut Self {
+        self.on_protocol_violation = Some(Box::new(on_protocol_violation));
+        self
+    }
+
+    /// # Client On Authorization Failed
+    /// Called with every `Error` whose URI ends in `authorization_failed` or `not_authorized`,
+    /// whichever request it answers, before the request's own callback. Apps can handle
+    /// them uniformly there, for example by asking the user to log in again.
+    ///
+    /// ## Examples
+    /// ```
+    /// use std::cell::RefCell;
+    /// use std::rc::Rc;
+    /// use wamp_client::sync::client::Client;
+    /// use wamp_client::sync::WampRequest;
+    /// use wamp_client::test_util::{establish, MockRouter};
+    /// use wamp_core::call;
+    /// use wamp_core::serde_json::json;
+    ///
+    /// let (uri, router) = MockRouter::spawn(move |router| {
+    ///     router.welcome();
+    ///     let call = router.recv();
+    ///     let error = json!([8, 48, call[1], {}, "wamp.error.authorization_failed"]);
+    ///     router.send(error);
+    /// });
+    ///
+    /// let (mut client, _) = Client::connect(WampRequest { uri, protocol: "wamp.2.json" }).unwrap();
+    /// establish(&mut client);
+    ///
+    /// // Both hooks log what they saw, in the order they were called.
+    /// let seen = Rc::new(RefCell::new(vec![]));
+    /// let (seen2, seen3) = (seen.clone(), seen.clone());
+    /// client.on_authorization_failed(move |error| {
+    ///     seen2.borrow_mut().push(format!("global {}", error.error));
+    /// });
+    /// client.call(call!("com.example.admin"), Box::new(move |ctx, result| {
+    ///     // Still called, with `Err(error)`.
+    ///     if let Err(error) = result {
+    ///         seen3.borrow_mut().push(format!("call {}", error.error));
+    ///     }
+    ///     ctx
+    /// })).unwrap();
+    ///
+    /// while seen.borrow().len() < 2 {
+    ///     let message = client.read().unwrap();
+    ///     client.read_contexts(message).unwrap();
+    /// }
+    /// assert_eq!(*seen.borrow(), vec![
+    ///     "global wamp.error.authorization_failed",
+    ///     "call wamp.error.authorization_failed",
+    /// ]);
+    /// drop(client);
+    /// router.join().unwrap();
+    /// ```
+    pub fn on_authorization_failed(
+        &mut self,
+        on_authorization_failed: impl FnMut(&WampError) + 'static,
+    ) -> &mut Self {
+        self.on_authorization_failed = Some(Box::new(on_authorization_failed));
+        self
+    }
+
+    /// # Client Start Keep Alive
+    /// Send an empty WebSocket ping every `interval` while the client waits for frames, so an
+    /// idle session is not dropped by NAT or load balancers. A later call replaces the interval.
+    ///
+    /// Pings stop when the returned `KeepAlive` is stopped or dropped.
+    ///
+    /// No thread is spawned, pings are only sent from inside the client's reads. A client that
+    /// is not reading, because a callback runs long or a hand written loop does other work
+    /// between calls to `Client::read`, sends no ping until it reads again. Use
+    /// `threads::client::Client::start_heartbeat` when pings must go out on their own.
+    ///
+    /// ## Examples
+    /// An idle session, where the router only hears the pings:
+    /// ```
+    /// use std::time::{Duration, Instant};
+    /// use wamp_client::hello::HelloBuilder;
+    /// use wamp_client::sync::client::{Client, LoopExit};
+    /// use wamp_client::sync::WampRequest;
+    /// use wamp_client::test_util::MockRouter;
+    /// use wamp_core::tungstenite::Message;
+    ///
+    /// let (uri, router) = MockRouter::spawn(move |router| {
+    ///     router.welcome();
+    ///
+    ///     // Stay silent, timing the client's first two pings.
+    ///     let mut pings = vec![];
+    ///     while pings.len() < 2 {
+    ///         if let Message::Ping(_) = router.socket().read().unwrap() {
+    ///             pings.push(Instant::now());
+    ///         }
+    ///     }
+    ///     router.send(r#"[6,{},"wamp.close.system_shutdown"]"#);
+    ///     pings[1] - pings[0]
+    /// });
+    ///
+    /// let (mut client, _) = Client::connect(WampRequest { uri, protocol: "wamp.2.json" }).unwrap();
+    /// let keepalive = client.start_keepalive(Duration::from_millis(50));
+    /// client.send(HelloBuilder::new("realm1").build().unwrap()).unwrap();
+    ///
+    /// assert!(matches!(client.event_loop().unwrap(), LoopExit::Goodbye(_)));
+    /// assert!(keepalive.is_running());
+    /// drop(client);
+    ///
+    /// let spacing = router.join().unwrap();
+    /// assert!(spacing >= Duration::from_millis(25) && spacing < Duration::from_secs(1));
+    /// ```
+    pub fn start_keepalive(&mut self, interval: Duration) -> KeepAlive {
+        let (keepalive, schedule) = KeepAlive::start(interval);
+        self.keepalive = Some(schedule);
+        keepalive
+    }
+
+    /// # Client Unregister Sync
+    /// Sends an `Unregister` for the given registration id and pumps the event loop until the
+    /// matching `Unregistered` or `Error` arrives, or the timeout elapses.
+    ///
+    /// The unregistration entry is removed from the client context once the router replies,
+    /// and a successful `Unregistered` also drops the invocation listeners for the registration.
+    ///
+    /// The result has two layers. The outer `Err` is the client failing, with
+    /// `Error::TimeOutError` when no reply arrives before `timeout` or `Error::ConnectionClosed`
+    /// when the connection drops. The inner `Result` is the router's answer, the `Unregistered`
+    /// or the `WampError` it refused the unregister with, like the other request methods that
+    /// wait on a reply.
+    ///
+    /// ## Examples
+    /// ```
+    /// use std::time::Duration;
+    /// use wamp_client::sync::client::Client;
+    /// use wamp_client::sync::WampRequest;
+    /// use wamp_client::test_util::{establish, MockRouter};
+    /// use wamp_core::serde_json::json;
+    ///
+    /// let (uri, router) = MockRouter::spawn(move |router| {
+    ///     router.welcome();
+    ///
+    ///     let unregister = router.recv();
+    ///     assert_eq!((unregister[0].clone(), unregister[2].clone()), (json!(66), json!(9)));
+    ///     router.send(json!([67, unregister[1]]));
+    ///
+    ///     let unregister = router.recv();
+    ///     let error = json!([8, 66, unregister[1], {}, "wamp.error.no_such_registration"]);
+    ///     router.send(error);
+    /// });
+    ///
+    /// let (mut client, _) = Client::connect(WampRequest { uri, protocol: "wamp.2.json" }).unwrap();
+    /// establish(&mut client);
+    ///
+    /// match client.unregister_sync(9, Duration::from_secs(2)) {
+    ///     Ok(Ok(_unregistered)) => {}
+    ///     Ok(Err(refused)) => panic!("the router refused with {}", refused.error),
+    ///     Err(error) => panic!("the client failed with {:?}", error),
+    /// }
+    /// match client.unregister_sync(9, Duration::from_secs(2)) {
+    ///     Ok(Err(refused)) => assert_eq!(refused.error, "wamp.error.no_such_registration"),
+    ///     other => panic!("expected the router's refusal, got {:?}", other),
+    /// }
+    /// assert_eq!(client.context.pending_total(), 0);
+    /// drop(client);
+    /// router.join().unwrap();
+    /// ```
+    ///
+    /// A router that never answers fails the outer layer once `timeout` elapses:
+    /// ```
+    /// use std::time::{Duration, Instant};
+    /// use wamp_client::error::Error;
+    /// use wamp_client::sync::client::Client;
+    /// use wamp_client::sync::WampRequest;
+    /// use wamp_client::test_util::{establish, MockRouter};
+    ///
+    /// let (uri, router) = MockRouter::spawn(move |router| {
+    ///     router.welcome();
+    /// });
+    ///
+    /// let (mut client, _) = Client::connect(WampRequest { uri, protocol: "wamp.2.json" }).unwrap();
+    /// establish(&mut client);
+    ///
+    /// let started = Instant::now();
+    /// let result = client.unregister_sync(9, Duration::from_millis(200));
+    /// assert!(matches!(result, Err(Error::TimeOutError(_))));
+    /// assert!(started.elapsed() < Duration::from_secs(5));
+    /// assert_eq!(client.context.pending_total(), 0);
+    /// drop(client);
+    /// router.join().unwrap();
+    /// ```
+    pub fn unregister_sync(
+        &mut self,
+        registration: u64,
+        timeout: Duration,
+    ) -> Result<Result<Unregistered, WampError>, Error> {
+        self.ensure_established()?;
+        let unregister = unregister!(registration);
+        let request_id = unregister.request_id;
+        let reply: Rc<RefCell<Option<Result<Unregistered, WampError>>>> = Rc::new(RefCell::new(None));
+        let reply2 = reply.clone();
+
+        self.context.unregister(
+            unregister,
+            Box::new(move |ctx, unregistered| {
+                *reply2.borrow_mut() = Some(unregistered);
+                ctx
+            }),
+        )?;
+
+        let result = self.wait_for(
+            &reply,
+            timeout,
+            "The client did not receive an `Unregistered` message from the WAMP implementation before the timeout...",
+        );
+
+        self.context
+            .unregistrations
+            .retain(|(unregister, _)| unregister.request_id != request_id);
+        result
+    }
+
+    /// # Client Publish Value
+    /// Publish any serializable value on `topic`, in the args or kwargs picked by `field`.
+    ///
+    /// The publish is sent without a callback, set `acknowledge` and use `Client::publish` when
+    /// the `Published` reply matters. Returns the request id of the publish.
+    ///
+    /// ## Examples
+    /// ```no_run
+    /// use serde::Serialize;
+    /// use wamp_client::messages::PayloadField;
+    /// use wamp_client::options::PublishOptions;
+    /// use wamp_client::sync::{client::Client, WampRequest};
+    ///
+    /// #[derive(Serialize)]
+    /// struct Reading {
+    ///     sensor: String,
+    ///     celsius: f64,
+    /// }
+    ///
+    /// let (mut client, _) = Client::connect(WampRequest { uri: "ws://localhost:8080/ws", protocol: "wamp.2.json" }).unwrap();
+    ///
+    /// let reading = Reading { sensor: "kitchen".to_string(), celsius: 21.5 };
+    /// client.publish_value("sensors.reading", &reading, PublishOptions::default(), PayloadField::Kwargs).unwrap();
+    /// ```
+    pub fn publish_value<T: ToString, V: Serialize>(
+        &mut self,
+        topic: T,
+        value: &V,
+        options: PublishOptions,
+        field: PayloadField,
+    ) -> Result<u64, Error> {
+        self.ensure_established()?;
+        let publish = publish_value(topic, value, options, field)?;
+        let request_id = publish.request_id;
+        self.send(publish)?;
+        Ok(request_id)
+    }
+
+    /// # Client Publish Confirmed
+    /// Publish `payload` as the args of a `Publish` on `topic` with `acknowledge` set, and block
+    /// until the router confirms it, returning the publication id. A refused publish returns
+    /// `Error::WampMessageError` with the router's error.
+    ///
+    /// ## Examples
+    /// ```
+    /// use std::time::Duration;
+    /// use wamp_client::error::Error;
+    /// use wamp_client::options::PublishOptions;
+    /// use wamp_client::sync::client::Client;
+    /// use wamp_client::sync::WampRequest;
+    /// use wamp_client::test_util::{establish, MockRouter};
+    /// use wamp_core::serde_json::json;
+    ///
+    /// let (uri, router) = MockRouter::spawn(move |router| {
+    ///     router.welcome();
+    ///
+    ///     let publish = router.recv();
+    ///     assert_eq!(publish[2]["acknowledge"], true);
+    ///     assert_eq!(publish[4], json!([1, 2]));
+    ///     let published = format!("[17,{},4242]", publish[1]);
+    ///     router.send(published);
+    ///
+    ///     let publish = router.recv();
+    ///     let error = format!(r#"[8,16,{},{{}},"wamp.error.not_authorized"]"#, publish[1]);
+    ///     router.send(error);
+    /// });
+    ///
+    /// let (mut client, _) = Client::connect(WampRequest { uri, protocol: "wamp.2.json" }).unwrap();
+    /// establish(&mut client);
+    ///
+    /// let publication = client
+    ///     .publish_confirmed("topic", json!([1, 2]), PublishOptions::default(), Duration::from_secs(2))
+    ///     .unwrap();
+    /// assert_eq!(publication, 4242);
+    ///
+    /// let refused =
+    ///     client.publish_confirmed("topic", json!([3]), PublishOptions::default(), Duration::from_secs(2));
+    /// match refused {
+    ///     Err(Error::WampMessageError(error)) => assert_eq!(error.error, "wamp.error.not_authorized"),
+    ///     other => panic!("expected the router's error, got {:?}", other),
+    /// }
+    /// drop(client);
+    /// router.join().unwrap();
+    /// ```
+    pub fn publish_confirmed<T: ToString>(
+        &mut self,
+        topic: T,
+        payload: Value,
+        options: PublishOptions,
+        timeout: Duration,
+    ) -> Result<u64, Error> {
+        self.ensure_established()?;
+        let mut publish = publish!(topic.to_string());
+        publish.options = PublishOptions {
+            acknowledge: Some(true),
+            ..options
+        }
+        .to_value();
+        publish.args = payload;
+
+        let reply: Rc<RefCell<Option<Result<Published, WampError>>>> = Rc::new(RefCell::new(None));
+        let reply2 = reply.clone();
+        self.context.publish(
+            publish,
+            Box::new(move |ctx, published| {
+                *reply2.borrow_mut() = Some(published);
+                ctx
+            }),
+        )?;
+
+        let published = self.wait_for(
+            &reply,
+            timeout,
+            "The client did not receive a `Published` message before the timeout...",
+        )?;
+        Ok(published?.publication)
+    }
+
+    /// # Client Publish Idempotent
+    /// Publish with a stable idempotency key, for at-least-once publishers that resend after a
+    /// reconnect.
+    ///
+    /// The key is stamped into the `idempotency_key` kwarg so the router can dedup, and the
+    /// publish is sent with `acknowledge` set. Once the router confirms it with `Published`, the
+    /// key is remembered and later publishes with the same key are skipped, returning `false`.
+    ///
+    /// Remembered keys are part of `Client::snapshot`, so they carry over to the client of a new
+    /// connection with `Client::restore`.
+    ///
+    /// ## Examples
+    /// ```
+    /// use std::thread;
+    /// use wamp_client::sync::client::Client;
+    /// use wamp_client::sync::WampRequest;
+    /// use wamp_client::test_util::{establish, MockRouter};
+    /// use wamp_core::publish;
+    /// use wamp_core::serde_json::json;
+    /// use wamp_core::tungstenite::Message;
+    ///
+    /// fn join(uri: String) -> Client {
+    ///     let (mut client, _) = Client::connect(WampRequest { uri, protocol: "wamp.2.json" }).unwrap();
+    ///     establish(&mut client);
+    ///     client
+    /// }
+    ///
+    /// let (uri, listener) = MockRouter::listen();
+    /// let router = thread::spawn(move || {
+    ///     let mut router = MockRouter::accept(&listener);
+    ///     router.welcome();
+    ///     let publish = router.recv();
+    ///     assert_eq!(publish[2]["acknowledge"], true);
+    ///     assert_eq!(publish[5]["idempotency_key"], "order-42");
+    ///     router.send(json!([17, publish[1], 99]));
+    ///
+    ///     // After reconnecting only the `Hello` arrives, the replayed publish is skipped.
+    ///     let mut router = MockRouter::accept(&listener);
+    ///     router.recv();
+    ///     router.send(r#"[2,2,{"roles":{"broker":{}}}]"#);
+    ///     while let Ok(message) = router.socket().read() {
+    ///         assert!(!matches!(message, Message::Text(_)));
+    ///     }
+    /// });
+    ///
+    /// let mut client = join(uri.clone());
+    /// // Options that are not a map are replaced rather than indexed into.
+    /// let mut order = publish!("orders");
+    /// order.options = json!([]);
+    /// assert!(client.publish_idempotent(order, "order-42", Box::new(|ctx, _| ctx)).unwrap());
+    /// client.drain(client.timeout()).unwrap();
+    /// assert!(client.is_acknowledged("order-42"));
+    /// let snapshot = client.snapshot();
+    /// drop(client);
+    ///
+    /// let mut client = join(uri);
+    /// client.restore(&snapshot, |_| Box::new(|ctx, _| ctx), |_| Box::new(|ctx, _| ctx)).unwrap();
+    /// assert!(!client.publish_idempotent(publish!("orders"), "order-42", Box::new(|ctx, _| ctx)).unwrap());
+    /// drop(client);
+    /// router.join().unwrap();
+    /// ```
+    pub fn publish_idempotent<K: ToString>(
+        &mut self,
+        mut publish: Publish,
+        key: K,
+        mut callback: CallBackResult<Published>,
+    ) -> Result<bool, Error> {
+        self.ensure_established()?;
+        let key = key.to_string();
+        if self.is_acknowledged(&key) {
+            return Ok(false);
+        }
+
+        if !publish.options.is_object() {
+            publish.options = json!({});
+        }
+        publish.options["acknowledge"] = json!(true);
+        if !publish.kwargs.is_object() {
+            publish.kwargs = json!({});
+        }
+        publish.kwargs["idempotency_key"] = json!(key);
+
+        let acknowledged_keys = self.acknowledged_keys.clone();
+        self.context.publish(
+            publish,
+            Box::new(move |ctx, published| {
+                if published.is_ok() {
+                    acknowledged_keys.borrow_mut().insert(key.clone());
+                }
+                callback(ctx, published)
+            }),
+        )?;
+        Ok(true)
+    }
+
+    /// Whether the router acknowledged a publish sent with `publish_idempotent` under `key`.
+    pub fn is_acknowledged(&self, key: &str) -> bool {
+        self.acknowledged_keys.borrow().contains(key)
+    }
+
+    /// # Client Register Fn
+    /// Register `procedure` and answer each of its invocations with the result of `procedure_fn`,
+    /// called with the invocation args and kwargs.
+    ///
+    /// `Ok(value)` is sent back as a `Yield`, with `value` as the args if it is an array or as the
+    /// only arg otherwise. `Err(error)` is sent back as an `Error` answering the invocation,
+    /// which `messages::wamp_error` builds.
+    ///
+    /// `procedure_fn` runs on the read loop, which waits for it to return. Long running handlers
+    /// belong in `Client::register_cancellable`.
+    ///
+    /// ## Examples
+    /// ```
+    /// use wamp_client::messages::wamp_error;
+    /// use wamp_client::sync::client::{Client, LoopExit};
+    /// use wamp_client::sync::WampRequest;
+    /// use wamp_client::test_util::{establish, MockRouter};
+    /// use wamp_core::messages::WampErrorEvent;
+    /// use wamp_core::serde_json::json;
+    ///
+    /// let (uri, router) = MockRouter::spawn(move |router| {
+    ///     router.welcome();
+    ///     let register = router.recv();
+    ///     assert_eq!(register[3], "com.example.add");
+    ///     router.send(json!([65, register[1], 100]));
+    ///
+    ///     router.send(r#"[68,7,100,{},[2,3]]"#);
+    ///     let r#yield = router.recv();
+    ///     assert_eq!((r#yield[0].clone(), r#yield[1].clone()), (json!(70), json!(7)));
+    ///     assert_eq!(r#yield[3], json!([5]));
+    ///
+    ///     router.send(r#"[68,8,100,{},["two","three"]]"#);
+    ///     let error = router.recv();
+    ///     assert_eq!((error[0].clone(), error[1].clone(), error[2].clone()), (json!(8), json!(68), json!(8)));
+    ///     assert_eq!(error[4], "wamp.error.invalid_argument");
+    ///
+    ///     router.send(r#"[6,{},"wamp.close.system_shutdown"]"#);
+    /// });
+    ///
+    /// let (mut client, _) = Client::connect(WampRequest { uri, protocol: "wamp.2.json" }).unwrap();
+    /// establish(&mut client);
+    ///
+    /// client
+    ///     .register_fn("com.example.add", |args, _kwargs| {
+    ///         match (args[0].as_i64(), args[1].as_i64()) {
+    ///             (Some(a), Some(b)) => Ok(json!(a + b)),
+    ///             _ => Err(wamp_error(WampErrorEvent::Invocation, 0, "wamp.error.invalid_argument")),
+    ///         }
+    ///     })
+    ///     .unwrap();
+    ///
+    /// assert!(matches!(client.event_loop().unwrap(), LoopExit::Goodbye(_)));
+    /// drop(client);
+    /// router.join().unwrap();
+    /// ```
+    ///
+    /// Invocations are routed on the registration id the router assigned, so with two procedures
+    /// registered only the handler of the invoked one runs:
+    /// ```
+    /// use std::cell::Cell;
+    /// use std::rc::Rc;
+    /// use wamp_client::sync::client::{Client, LoopExit};
+    /// use wamp_client::sync::WampRequest;
+    /// use wamp_client::test_util::{establish, MockRouter};
+    /// use wamp_core::serde_json::json;
+    ///
+    /// let (uri, router) = MockRouter::spawn(move |router| {
+    ///     router.welcome();
+    ///     for registration in [100, 200].iter() {
+    ///         let register = router.recv();
+    ///         router.send(json!([65, register[1], registration]));
+    ///     }
+    ///
+    ///     router.send(r#"[68,7,200,{},[]]"#);
+    ///     let r#yield = router.recv();
+    ///     assert_eq!((r#yield[0].clone(), r#yield[1].clone()), (json!(70), json!(7)));
+    ///     assert_eq!(r#yield[3], json!(["second"]));
+    ///     router.send(r#"[6,{},"wamp.close.system_shutdown"]"#);
+    /// });
+    ///
+    /// let (mut client, _) = Client::connect(WampRequest { uri, protocol: "wamp.2.json" }).unwrap();
+    /// establish(&mut client);
+    ///
+    /// let first_calls = Rc::new(Cell::new(0));
+    /// let first_calls2 = first_calls.clone();
+    /// client
+    ///     .register_fn("com.example.first", move |_, _| {
+    ///         first_calls2.set(first_calls2.get() + 1);
+    ///         Ok(json!("first"))
+    ///     })
+    ///     .unwrap();
+    /// client.register_fn("com.example.second", |_, _| Ok(json!("second"))).unwrap();
+    ///
+    /// assert!(matches!(client.event_loop().unwrap(), LoopExit::Goodbye(_)));
+    /// assert_eq!(first_calls.get(), 0);
+    /// drop(client);
+    /// router.join().unwrap();
+    /// ```
+    pub fn register_fn<T: ToString>(
+        &mut self,
+        procedure: T,
+        procedure_fn: impl FnMut(Value, Value) -> Result<Value, WampError> + 'static,
+    ) -> Result<(), Error> {
+        self.ensure_established()?;
+        let mut procedure_fn = Some(procedure_fn);
+        self.context.register(
+            register!(procedure.to_string()),
+            Box::new(move |mut ctx, registered| {
+                if let (Ok(registered), Some(mut procedure_fn)) = (registered, procedure_fn.take()) {
+                    // Invocation listeners are never sent, so attaching one cannot fail.
+                    let _ = ctx.invocation(
+                        registered,
+                        Box::new(move |mut ctx, invocation| {
+                            let invocation = match invocation {
+                                Ok(invocation) => invocation,
+                                Err(_) => return ctx,
+                            };
+                            let result = procedure_fn(invocation.args, invocation.kwargs);
+                            // A failed send means the socket is gone, which the read loop reports.
+                            let _ = match invocation_reply(invocation.request_id, result) {
+                                Ok(reply) => ctx.send(reply),
+                                Err(error) => ctx.send(error),
+                            };
+                            ctx
+                        }),
+                    );
+                }
+                ctx
+            }),
+        )
+    }
+
+    /// # Client Register Cancellable
+    /// Register `procedure` like `Client::register_fn`, also handing `procedure_fn` a
+    /// `CancellationToken` for the invocation.
+    ///
+    /// Each invocation runs on its own thread, so `Client::event_loop` keeps reading while the
+    /// handler works and sends its `Yield` or `Error` once it returns. The token is cancelled by
+    /// `Client::shutdown`, or by an `Interrupt` for the invocation while it is in flight. A
+    /// handler that polls it can stop early and return an error such as `wamp.error.canceled`.
+    ///
+    /// ## Examples
+    /// A router that interrupts the invocation it just sent:
+    /// ```
+    /// use std::thread;
+    /// use std::time::{Duration, Instant};
+    /// use wamp_client::messages::wamp_error;
+    /// use wamp_client::sync::client::{Client, LoopExit};
+    /// use wamp_client::sync::WampRequest;
+    /// use wamp_client::test_util::{establish, MockRouter};
+    /// use wamp_core::messages::WampErrorEvent;
+    /// use wamp_core::serde_json::json;
+    ///
+    /// let (uri, router) = MockRouter::spawn(move |router| {
+    ///     router.welcome();
+    ///
+    ///     let register = router.recv();
+    ///     router.send(json!([65, register[1], 7]));
+    ///     router.send(r#"[68,1,7,{},[]]"#);
+    ///     router.send(r#"[69,1,{"mode":"kill"}]"#);
+    ///
+    ///     let error = router.recv();
+    ///     assert_eq!(error[0], 8);
+    ///     assert_eq!(error[1], 68);
+    ///     assert_eq!(error[2], 1);
+    ///     assert_eq!(error[4], "wamp.error.canceled");
+    ///     router.send(r#"[6,{},"wamp.close.close_realm"]"#);
+    ///     router.recv();
+    /// });
+    ///
+    /// let (mut client, _) = Client::connect(WampRequest { uri, protocol: "wamp.2.json" }).unwrap();
+    /// establish(&mut client);
+    ///
+    /// client
+    ///     .register_cancellable("com.example.crunch", |_args, _kwargs, token| {
+    ///         let started = Instant::now();
+    ///         while !token.is_cancelled() {
+    ///             if started.elapsed() > Duration::from_secs(5) {
+    ///                 return Ok(json!("never interrupted"));
+    ///             }
+    ///             thread::sleep(Duration::from_millis(10));
+    ///         }
+    ///         Err(wamp_error(WampErrorEvent::Invocation, 0, "wamp.error.canceled"))
+    ///     })
+    ///     .unwrap();
+    ///
+    /// assert!(matches!(client.event_loop().unwrap(), LoopExit::Goodbye(_)));
+    /// drop(client);
+    /// router.join().unwrap();
+    /// ```
+    pub fn register_cancellable<T: ToString>(
+        &mut self,
+        procedure: T,
+        procedure_fn: impl Fn(Value, Value, &CancellationToken) -> Result<Value, WampError>
+            + Send
+            + Sync
+            + 'static,
+    ) -> Result<(), Error> {
+        self.ensure_established()?;
+        let shutdown = self.shutdown.clone();
+        let invocation_tokens = self.invocation_tokens.clone();
+        let finish_invocation = self.finish_invocation.clone();
+        let procedure_fn = Arc::new(procedure_fn);
+        self.context.register(
+            register!(procedure.to_string()),
+            Box::new(move |mut ctx, registered| {
+                if let Ok(registered) = registered {
+                    let shutdown = shutdown.clone();
+                    let invocation_tokens = invocation_tokens.clone();
+                    let finish_invocation = finish_invocation.clone();
+                    let procedure_fn = procedure_fn.clone();
+                    // Invocation listeners are never sent, so attaching one cannot fail.
+                    let _ = ctx.invocation(
+                        registered,
+                        Box::new(move |ctx, invocation| {
+                            let invocation = match invocation {
+                                Ok(invocation) => invocation,
+                                Err(_) => return ctx,
+                            };
+                            let request_id = invocation.request_id;
+                            let token = shutdown.child();
+                            invocation_tokens.borrow_mut().insert(request_id, token.clone());
+                            let procedure_fn = procedure_fn.clone();
+                            let finish_invocation = finish_invocation.clone();
+                            thread::spawn(move || {
+                                let result = procedure_fn(invocation.args, invocation.kwargs, &token);
+                                // The client is gone if nobody receives it, and so is the session.
+                                let _ = finish_invocation.send((request_id, result));
+                            });
+                            ctx
+                        }),
+                    );
+                }
+                ctx
+            }),
+        )
+    }
+
+    /// Answer the invocations whose `register_cancellable` handler returned.
+    fn finish_invocations(&mut self) -> Result<(), Error> {
+        while let Ok((request_id, result)) = self.finished_invocations.try_recv() {
+            self.invocation_tokens.borrow_mut().remove(&request_id);
+            match invocation_reply(request_id, result) {
+                Ok(reply) => self.send(reply)?,
+                Err(error) => self.send(error)?,
+            }
+        }
+        Ok(())
+    }
+
+    /// # Client Subscribe Sync
+    /// Subscribe to `topic` and block until the router confirms it with `Subscribed`.
+    pub fn subscribe_sync<T: ToString>(
+        &mut self,
+        topic: T,
+        timeout: Duration,
+    ) -> Result<Subscribed, Error> {
+        self.ensure_established()?;
+        let subscribed: Rc<RefCell<Option<Result<Subscribed, WampError>>>> = Rc::new(RefCell::new(None));
+        let subscribed2 = subscribed.clone();
+        self.context.subscribe(
+            subscribe!(topic.to_string()),
+            Box::new(move |ctx, result| {
+                *subscribed2.borrow_mut() = Some(result);
+                ctx
+            }),
+        )?;
+        Ok(self.wait_for(
+            &subscribed,
+            timeout,
+            "The client did not receive a `Subscribed` message before the timeout...",
+        )??)
+    }
+
+    /// # Client Subscribe With Options
+    /// Subscribe to `topic` with typed options, attaching `on_event` as the event listener once
+    /// the router confirms the subscription.
+    ///
+    /// With `SubscribeOptions::rate_limit` set, events beyond the limit are dropped before they
+    /// reach `on_event` and counted on the returned handle.
+    ///
+    /// ## Examples
+    /// ```no_run
+    /// use wamp_client::options::SubscribeOptions;
+    /// use wamp_client::sync::{client::Client, WampRequest};
+    ///
+    /// let (mut client, _) = Client::connect(WampRequest { uri: "ws://localhost:8080/ws", protocol: "wamp.2.json" }).unwrap();
+    ///
+    /// let options = SubscribeOptions { rate_limit: Some(10), ..Default::default() };
+    /// let handle = client
+    ///     .subscribe_with_options("noisy.topic", options, Box::new(|ctx, _event| ctx))
+    ///     .unwrap();
+    ///
+    /// client.event_loop().unwrap();
+    /// println!("dropped {} events", handle.dropped());
+    /// ```
+    ///
+    /// Pattern subscriptions are requested with `SubscribeOptions::prefix` or
+    /// `SubscribeOptions::wildcard`. The router confirms them with a single subscription id, so
+    /// events for every matching topic reach `on_event`, which reads the concrete topic with
+    /// `EventExt::topic`:
+    /// ```
+    /// use std::cell::RefCell;
+    /// use std::rc::Rc;
+    /// use wamp_client::messages::EventExt;
+    /// use wamp_client::options::SubscribeOptions;
+    /// use wamp_client::sync::client::Client;
+    /// use wamp_client::sync::WampRequest;
+    /// use wamp_client::test_util::{establish, MockRouter};
+    /// use wamp_core::serde_json::json;
+    ///
+    /// let (uri, router) = MockRouter::spawn(move |router| {
+    ///     router.welcome();
+    ///
+    ///     let subscribe = router.recv();
+    ///     assert_eq!(subscribe[2], json!({ "match": "prefix" }));
+    ///     assert_eq!(subscribe[3], "com.myapp.sensor");
+    ///     router.send(json!([33, subscribe[1], 7]));
+    ///
+    ///     for topic in &["com.myapp.sensor.kitchen", "com.myapp.sensor.hall.door"] {
+    ///         let event = json!([36, 7, 1, { "topic": topic }, [1]]);
+    ///         router.send(event);
+    ///     }
+    ///     router.send(r#"[6,{},"wamp.close.system_shutdown"]"#);
+    /// });
+    ///
+    /// let (mut client, _) = Client::connect(WampRequest { uri, protocol: "wamp.2.json" }).unwrap();
+    /// establish(&mut client);
+    ///
+    /// let topics = Rc::new(RefCell::new(vec![]));
+    /// let topics2 = topics.clone();
+    /// let handle = client
+    ///     .subscribe_with_options(
+    ///         "com.myapp.sensor",
+    ///         SubscribeOptions::prefix(),
+    ///         Box::new(move |ctx, event| {
+    ///             topics2.borrow_mut().push(event.topic().unwrap().to_string());
+    ///             ctx
+    ///         }),
+    ///     )
+    ///     .unwrap();
+    ///
+    /// client.event_loop().unwrap();
+    /// assert!(handle.is_pattern());
+    /// assert_eq!(*topics.borrow(), vec!["com.myapp.sensor.kitchen", "com.myapp.sensor.hall.door"]);
+    /// drop(client);
+    /// router.join().unwrap();
+    /// ```
+    pub fn subscribe_with_options<T: ToString>(
+        &mut self,
+        topic: T,
+        options: SubscribeOptions,
+        on_event: CallBack<Event>,
+    ) -> Result<SubscriptionHandle, Error> {
+        self.ensure_established()?;
+        let handle = SubscriptionHandle {
+            subscribed: Rc::new(RefCell::new(None)),
+            rate_limit: options
+                .rate_limit
+                .map(|per_second| Rc::new(RefCell::new(RateLimit::new(per_second)))),
+            match_policy: options.match_policy.unwrap_or(MatchPolicy::Exact),
+        };
+
+        let mut subscribe = subscribe!(topic.to_string());
+        subscribe.options = options.to_value();
+
+        let subscribed_slot = handle.subscribed.clone();
+        let rate_limit = handle.rate_limit.clone();
+        let mut on_event = Some(on_event);
+        self.context.subscribe(
+            subscribe,
+            Box::new(move |mut ctx, result| {
+                if let (Ok(subscribed), Some(mut on_event)) = (result, on_event.take()) {
+                    *subscribed_slot.borrow_mut() = Some(subscribed.clone());
+                    let rate_limit = rate_limit.clone();
+                    // Event listeners are never sent, so attaching one cannot fail.
+                    let _ = ctx.event(
+                        subscribed,
+                        Box::new(move |ctx, event| match &rate_limit {
+                            Some(rate_limit) if !rate_limit.borrow_mut().allow() => ctx,
+                            _ => on_event(ctx, event),
+                        }),
+                    );
+                }
+                ctx
+            }),
+        )?;
+        Ok(handle)
+    }
+
+    /// # Client Events Iter
+    /// Subscribe to `topic` and iterate its events, blocking for each one, instead of attaching
+    /// an event callback.
+    ///
+    /// See `EventIterator` for how other frames are handled while iterating.
+    pub fn events_iter<'a, T: ToString>(&'a mut self, topic: T) -> Result<EventIterator<'a>, Error> {
+        let timeout = self.timeout;
+        let subscribed = self.subscribe_sync(topic, timeout)?;
+        EventIterator::new(self, subscribed)
+    }
+
+    /// # Client Event Channel
+    /// Subscribe to `topic` and forward its events into a channel, so they can be consumed on
+    /// another thread while this one runs the event loop.
+    ///
+    /// Pass a prefix or wildcard `match_policy` in `options` to forward every matching topic.
+    /// Events arriving after the `Receiver` is dropped are discarded.
+    ///
+    /// ## Examples
+    /// ```
+    /// use std::thread;
+    /// use wamp_client::options::SubscribeOptions;
+    /// use wamp_client::sync::client::{Client, LoopExit};
+    /// use wamp_client::sync::WampRequest;
+    /// use wamp_client::test_util::{establish, MockRouter};
+    /// use wamp_core::serde_json::json;
+    ///
+    /// let (uri, router) = MockRouter::spawn(move |router| {
+    ///     router.welcome();
+    ///     let subscribe = router.recv();
+    ///     assert_eq!(subscribe[3], "sensors.reading");
+    ///     router.send(json!([33, subscribe[1], 5]));
+    ///     for reading in 0..3 {
+    ///         router.send(json!([36, 5, reading, {}, [reading]]));
+    ///     }
+    ///     router.send(r#"[6,{},"wamp.close.system_shutdown"]"#);
+    /// });
+    ///
+    /// let (mut client, _) = Client::connect(WampRequest { uri, protocol: "wamp.2.json" }).unwrap();
+    /// establish(&mut client);
+    ///
+    /// let events = client.event_channel("sensors.reading", SubscribeOptions::default()).unwrap();
+    /// let consumer = thread::spawn(move || {
+    ///     events.iter().take(3).map(|event| event.args).collect::<Vec<_>>()
+    /// });
+    ///
+    /// assert!(matches!(client.event_loop().unwrap(), LoopExit::Goodbye(_)));
+    /// assert_eq!(consumer.join().unwrap(), vec![json!([0]), json!([1]), json!([2])]);
+    /// drop(client);
+    /// router.join().unwrap();
+    /// ```
+    pub fn event_channel<T: ToString>(
+        &mut self,
+        topic: T,
+        options: SubscribeOptions,
+    ) -> Result<Receiver<Event>, Error> {
+        let (sender, receiver) = channel();
+        self.subscribe_with_options(
+            topic,
+            options,
+            Box::new(move |ctx, event| {
+                // A dropped receiver only means nobody consumes the events anymore.
+                let _ = sender.send(event);
+                ctx
+            }),
+        )?;
+        Ok(receiver)
+    }
+
+    /// # Client Request Over PubSub
+    /// RPC-like request/response for apps that implement it on top of pub/sub.
+    ///
+    /// Subscribes to `reply_topic`, publishes `payload` as the args of a `Publish` on
+    /// `request_topic` with a generated `correlation_id` kwarg, then pumps the event loop until an
+    /// `Event` on the reply topic echoes the same `correlation_id` in its kwargs. The reply
+    /// subscription is unsubscribed again before returning, when the request fails too. A
+    /// `Subscribe` the router did not answer in time is dropped from the pending requests.
+    ///
+    /// ## Examples
+    /// ```
+    /// use std::time::Duration;
+    /// use wamp_client::sync::client::Client;
+    /// use wamp_client::sync::WampRequest;
+    /// use wamp_client::test_util::{establish, MockRouter};
+    /// use wamp_core::serde_json::json;
+    ///
+    /// let (uri, router) = MockRouter::spawn(move |router| {
+    ///     router.welcome();
+    ///     let subscribe = router.recv();
+    ///     assert_eq!(subscribe[3], "com.example.pong");
+    ///     router.send(json!([33, subscribe[1], 5]));
+    ///
+    ///     let publish = router.recv();
+    ///     assert_eq!(publish[3], "com.example.ping");
+    ///     let correlation_id = publish[5]["correlation_id"].clone();
+    ///     // A reply to someone else's request comes first, and is skipped.
+    ///     let other = json!([36, 5, 1, {}, ["other"], {"correlation_id": 0}]);
+    ///     router.send(other);
+    ///     let reply = json!([36, 5, 2, {}, ["pong"], {"correlation_id": correlation_id}]);
+    ///     router.send(reply);
+    ///
+    ///     let unsubscribe = router.recv();
+    ///     assert_eq!((unsubscribe[0].clone(), unsubscribe[2].clone()), (json!(34), json!(5)));
+    /// });
+    ///
+    /// let (mut client, _) = Client::connect(WampRequest { uri, protocol: "wamp.2.json" }).unwrap();
+    /// establish(&mut client);
+    ///
+    /// let reply = client
+    ///     .request_over_pubsub("com.example.ping", "com.example.pong", json!(["ping"]), Duration::from_secs(2))
+    ///     .unwrap();
+    /// assert_eq!(reply.args, json!(["pong"]));
+    /// drop(client);
+    /// router.join().unwrap();
+    /// ```
+    ///
+    /// A request nobody answers still leaves the reply topic:
+    /// ```
+    /// use std::time::Duration;
+    /// use wamp_client::error::Error;
+    /// use wamp_client::sync::client::Client;
+    /// use wamp_client::sync::WampRequest;
+    /// use wamp_client::test_util::{establish, MockRouter};
+    /// use wamp_core::serde_json::json;
+    ///
+    /// let (uri, router) = MockRouter::spawn(move |router| {
+    ///     router.welcome();
+    ///     let subscribe = router.recv();
+    ///     router.send(json!([33, subscribe[1], 5]));
+    ///     assert_eq!(router.recv()[0], 16);
+    ///     router.recv()
+    /// });
+    ///
+    /// let (mut client, _) = Client::connect(WampRequest { uri, protocol: "wamp.2.json" }).unwrap();
+    /// establish(&mut client);
+    ///
+    /// let result = client.request_over_pubsub("com.example.ping", "com.example.pong", json!([]), Duration::from_millis(200));
+    /// assert!(matches!(result, Err(Error::TimeOutError(_))));
+    /// drop(client);
+    ///
+    /// let unsubscribe = router.join().unwrap();
+    /// assert_eq!((unsubscribe[0].clone(), unsubscribe[2].clone()), (json!(34), json!(5)));
+    /// ```
+    ///
+    /// Neither does a reply topic the router never confirms stay pending:
+    /// ```
+    /// use std::time::Duration;
+    /// use wamp_client::error::Error;
+    /// use wamp_client::sync::client::Client;
+    /// use wamp_client::sync::WampRequest;
+    /// use wamp_client::test_util::{establish, MockRouter};
+    /// use wamp_core::serde_json::json;
+    ///
+    /// let (uri, router) = MockRouter::spawn(move |router| {
+    ///     router.welcome();
+    /// });
+    ///
+    /// let (mut client, _) = Client::connect(WampRequest { uri, protocol: "wamp.2.json" }).unwrap();
+    /// establish(&mut client);
+    ///
+    /// let result = client.request_over_pubsub("com.example.ping", "com.example.pong", json!([]), Duration::from_millis(200));
+    /// assert!(matches!(result, Err(Error::TimeOutError(_))));
+    /// assert_eq!(client.context.pending_total(), 0);
+    /// drop(client);
+    /// router.join().unwrap();
+    /// ```
+    pub fn request_over_pubsub<R: ToString, T: ToString>(
+        &mut self,
+        request_topic: R,
+        reply_topic: T,
+        payload: Value,
+        timeout: Duration,
+    ) -> Result<Event, Error> {
+        self.ensure_established()?;
+        let subscribe = subscribe!(reply_topic.to_string());
+        let subscribe_id = subscribe.request_id;
+        let subscribed: Rc<RefCell<Option<Result<Subscribed, WampError>>>> = Rc::new(RefCell::new(None));
+        let subscribed2 = subscribed.clone();
+        self.context.subscribe(
+            subscribe,
+            Box::new(move |ctx, result| {
+                *subscribed2.borrow_mut() = Some(result);
+                ctx
+            }),
+        )?;
+        let subscribed = match self.wait_for(
+            &subscribed,
+            timeout,
+            "The client did not receive a `Subscribed` message for the reply topic before the timeout...",
+        ) {
+            Ok(subscribed) => subscribed?,
+            Err(error) => {
+                self.context
+                    .subscriptions
+                    .retain(|(subscribe, _)| subscribe.request_id != subscribe_id);
+                return Err(error);
+            }
+        };
+
+        let mut publish = publish!(request_topic.to_string());
+        let correlation_id = publish.request_id;
+        publish.args = payload;
+        publish.kwargs = json!({ "correlation_id": correlation_id });
+
+        let reply: Rc<RefCell<Option<Event>>> = Rc::new(RefCell::new(None));
+        let reply2 = reply.clone();
+        let result = self
+            .context
+            .event(
+                subscribed.clone(),
+                Box::new(move |ctx, event| {
+                    if event.kwargs.get("correlation_id").and_then(Value::as_u64) == Some(correlation_id) {
+                        *reply2.borrow_mut() = Some(event);
+                    }
+                    ctx
+                }),
+            )
+            .and_then(|()| self.send(publish))
+            .and_then(|()| {
+                self.wait_for(
+                    &reply,
+                    timeout,
+                    "The client did not receive a correlated reply on the reply topic before the timeout...",
+                )
+            });
+
+        // The reply topic only served this request, so it is left whatever the outcome.
+        let unsubscribed = self.context.unsubscribe(
+            unsubscribe!(subscribed.subscription),
+            Box::new(|ctx, _| ctx),
+        );
+        let reply = result?;
+        unsubscribed?;
+        Ok(reply)
+    }
+
+    /// # Client Drain
+    /// Pump the event loop until every pending request has been answered by the router, for a
+    /// clean shutdown.
+    ///
+    /// Event and invocation listeners are not pending requests, so they do not hold up the drain.
+    /// Publishes without `acknowledge` never get a reply, send those with `Client::send` instead.
+    ///
+    /// ## Examples
+    /// ```
+    /// use std::thread;
+    /// use std::time::Duration;
+    /// use wamp_client::sync::client::Client;
+    /// use wamp_client::sync::WampRequest;
+    /// use wamp_client::test_util::{establish, MockRouter};
+    /// use wamp_core::call;
+    /// use wamp_core::serde_json::json;
+    ///
+    /// let (uri, router) = MockRouter::spawn(move |router| {
+    ///     router.welcome();
+    ///     let call = router.recv();
+    ///     // Answer while the client is draining.
+    ///     thread::sleep(Duration::from_millis(100));
+    ///     router.send(json!([50, call[1], {}, [1]]));
+    /// });
+    ///
+    /// let (mut client, _) = Client::connect(WampRequest { uri, protocol: "wamp.2.json" }).unwrap();
+    /// establish(&mut client);
+    ///
+    /// client.call(call!("com.example.slow"), Box::new(|ctx, _| ctx)).unwrap();
+    /// assert_eq!(client.context.pending_total(), 1);
+    /// client.drain(Duration::from_secs(2)).unwrap();
+    /// assert_eq!(client.context.pending_total(), 0);
+    /// drop(client);
+    /// router.join().unwrap();
+    /// ```
+    ///
+    /// On a router that never answers the drain gives up once `timeout` elapses, leaving the
+    /// requests pending:
+    /// ```
+    /// use std::time::{Duration, Instant};
+    /// use wamp_client::error::Error;
+    /// use wamp_client::sync::client::Client;
+    /// use wamp_client::sync::WampRequest;
+    /// use wamp_client::test_util::{establish, MockRouter};
+    /// use wamp_core::call;
+    ///
+    /// let (uri, router) = MockRouter::spawn(move |router| {
+    ///     router.welcome();
+    /// });
+    ///
+    /// let (mut client, _) = Client::connect(WampRequest { uri, protocol: "wamp.2.json" }).unwrap();
+    /// establish(&mut client);
+    ///
+    /// client.call(call!("com.example.dead"), Box::new(|ctx, _| ctx)).unwrap();
+    /// let started = Instant::now();
+    /// let result = client.drain(Duration::from_millis(200));
+    /// assert!(matches!(result, Err(Error::TimeOutError(_))));
+    /// assert!(started.elapsed() < Duration::from_secs(5));
+    /// assert_eq!(client.context.pending_total(), 1);
+    /// drop(client);
+    /// router.join().unwrap();
+    /// ```
+    pub fn drain(&mut self, timeout: Duration) -> Result<(), Error> {
+        let deadline = Instant::now() + timeout;
+        while self.context.pending_total() > 0 {
+            if Instant::now() >= deadline {
+                return Err(Error::TimeOutError(
+                    "The router did not answer every pending request before the drain timeout...",
+                ));
+            }
+
+            let message = self.read_until(deadline)?;
+            if message.is_some() {
+                self.read_contexts(message)?;
+            }
+        }
+        Ok(())
+    }
+
+    /// # Client Close
+    /// Leave the session with the two way goodbye handshake, then close the WebSocket.
+    ///
+    /// Sends a `Goodbye` with `reason`, such as `wamp.close.system_shutdown`, and routes frames
+    /// until the router answers with its own `Goodbye`, so the last replies still reach their
+    /// callbacks. The client timeout bounds the whole handshake, the WebSocket close included, so
+    /// on a silent router the WebSocket is closed anyway and `Error::TimeOutError` returned.
+    ///
+    /// ## Examples
+    /// ```
+    /// use wamp_client::sync::client::{Client, ConnectionState};
+    /// use wamp_client::sync::WampRequest;
+    /// use wamp_client::test_util::{establish, MockRouter};
+    ///
+    /// let (uri, router) = MockRouter::spawn(move |router| {
+    ///     router.welcome();
+    ///
+    ///     let goodbye = router.recv();
+    ///     assert_eq!(goodbye[2], "wamp.close.system_shutdown");
+    ///     router.send(r#"[6,{},"wamp.close.goodbye_and_out"]"#);
+    /// });
+    ///
+    /// let (mut client, _) = Client::connect(WampRequest { uri, protocol: "wamp.2.json" }).unwrap();
+    /// establish(&mut client);
+    ///
+    /// client.close("wamp.close.system_shutdown").unwrap();
+    /// assert_eq!(client.state(), ConnectionState::Closed);
+    /// router.join().unwrap();
+    /// ```
+    ///
+    /// A router that never answers, neither the `Goodbye` nor the close frame:
+    /// ```
+    /// use std::net::TcpListener;
+    /// use std::sync::mpsc::channel;
+    /// use std::thread;
+    /// use std::time::{Duration, Instant};
+    /// use wamp_client::error::Error;
+    /// use wamp_client::sync::client::{Client, ConnectionState};
+    /// use wamp_client::sync::WampRequest;
+    /// use wamp_client::test_util::establish;
+    /// use wamp_core::tungstenite::{accept, Message};
+    ///
+    /// let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+    /// let uri = format!("ws://{}/ws", listener.local_addr().unwrap());
+    /// let (done, wait) = channel::<()>();
+    /// let router = thread::spawn(move || {
+    ///     let mut socket = accept(listener.accept().unwrap().0).unwrap();
+    ///     socket.read().unwrap();
+    ///     socket.send(Message::Text(r#"[2,1,{"roles":{"broker":{}}}]"#.to_string())).unwrap();
+    ///     // Reading on would answer the close frame, so stay silent until the client gave up.
+    ///     wait.recv().unwrap();
+    /// });
+    ///
+    /// let (mut client, _) = Client::connect(WampRequest { uri, protocol: "wamp.2.json" }).unwrap();
+    /// client.set_timeout(Duration::from_millis(200));
+    /// establish(&mut client);
+    ///
+    /// let started = Instant::now();
+    /// let result = client.close("wamp.close.system_shutdown");
+    /// assert!(matches!(result, Err(Error::TimeOutError(_))));
+    /// assert!(started.elapsed() < Duration::from_secs(5));
+    /// assert_eq!(client.state(), ConnectionState::Closed);
+    /// done.send(()).unwrap();
+    /// router.join().unwrap();
+    /// ```
+    pub fn close(&mut self, reason: &str) -> Result<(), Error> {
+        self.send(Goodbye {
+            details: json!({}),
+            reason: reason.to_string(),
+        })?;
+        self.state = ConnectionState::Closing;
+
+        let deadline = Instant::now() + self.timeout;
+        let answered = loop {
+            if Instant::now() >= deadline {
+                break false;
+            }
+            let message = match self.read_until(deadline) {
+                Ok(message) => message,
+                // The router dropped the connection instead of answering, nothing left to close.
+                Err(Error::ConnectionClosed) => return Ok(()),
+                Err(error) => return Err(error),
+            };
+            let goodbye = matches!(message, Some(Messages::Goodbye(_)));
+            if message.is_some() {
+                self.read_contexts(message)?;
+            }
+            if goodbye || self.is_closed() {
+                break true;
+            }
+        };
+
+        let closed = self.close_socket(deadline);
+        if !answered {
+            return Err(Error::TimeOutError(
+                "The router did not answer the `Goodbye` before the timeout...",
+            ));
+        }
+        closed
+    }
+
+    /// Send a WebSocket close frame and read until the router acknowledges it or `deadline`
+    /// passes.
+    fn close_socket(&mut self, deadline: Instant) -> Result<(), Error> {
+        self.state = ConnectionState::Closed;
+        match self.socket.lock().unwrap().close(None) {
+            Ok(()) => {}
+            Err(tungstenite::Error::ConnectionClosed) | Err(tungstenite::Error::AlreadyClosed) => {
+                return Ok(())
+            }
+            Err(error) => return Err(Error::from(error)),
+        }
+        loop {
+            let remaining = deadline.saturating_duration_since(Instant::now());
+            if remaining == Duration::from_secs(0) {
+                break Err(Error::TimeOutError(
+                    "The router did not acknowledge the WebSocket close before the timeout...",
+                ));
+            }
+            match crate::core::read_timeout(&self.socket, remaining) {
+                Ok(_) => {}
+                Err(tungstenite::Error::ConnectionClosed) | Err(tungstenite::Error::AlreadyClosed) => {
+                    break Ok(())
+                }
+                Err(error) => break Err(Error::from(error)),
+            }
+        }
+    }
+
+    /// # Client Snapshot
+    /// Capture the subscriptions and registrations the router has confirmed, without their
+    /// callbacks, so they can be persisted and re-issued on a fresh session with `restore`.
+    ///
+    /// The keys acknowledged for `Client::publish_idempotent` are captured as well.
+    pub fn snapshot(&self) -> SessionSnapshot {
+        let mut snapshot = SessionSnapshot::from(&self.context);
+        snapshot.acknowledged_keys = self.acknowledged_keys.borrow().iter().cloned().collect();
+        snapshot.acknowledged_keys.sort();
+        snapshot
+    }
+
+    /// # Client Restore
+    /// Re-issue every `Subscribe` and `Register` captured in a snapshot, and remember its
+    /// acknowledged idempotency keys so those publishes are not sent again.
+    ///
+    /// Since callbacks cannot be persisted, `on_subscribed` and `on_registered` are asked for a
+    /// fresh callback for each spec, which is where event and invocation listeners get attached.
+    ///
+    /// ## Examples
+    /// Two subscriptions snapshotted on one connection are re-sent on the next:
+    /// ```
+    /// use std::thread;
+    /// use wamp_client::options::SubscribeOptions;
+    /// use wamp_client::sync::client::Client;
+    /// use wamp_client::sync::snapshot::SessionSnapshot;
+    /// use wamp_client::sync::WampRequest;
+    /// use wamp_client::test_util::{establish, MockRouter};
+    /// use wamp_core::serde_json::{self, json, Value};
+    ///
+    /// let (uri, listener) = MockRouter::listen();
+    /// let router = thread::spawn(move || {
+    ///     let mut router = MockRouter::accept(&listener);
+    ///     router.welcome();
+    ///     for subscription in [5, 6].iter() {
+    ///         let subscribe = router.recv();
+    ///         router.send(json!([33, subscribe[1], subscription]));
+    ///     }
+    ///
+    ///     let mut router = MockRouter::accept(&listener);
+    ///     router.recv();
+    ///     router.send(r#"[2,2,{"roles":{"broker":{}}}]"#);
+    ///     vec![router.recv(), router.recv()]
+    /// });
+    ///
+    /// let (mut client, _) = Client::connect(WampRequest { uri: uri.clone(), protocol: "wamp.2.json" }).unwrap();
+    /// establish(&mut client);
+    /// let _a = client.event_channel("com.example.a", SubscribeOptions::default()).unwrap();
+    /// let _b = client.event_channel("com.example.b", SubscribeOptions::default()).unwrap();
+    /// while !(client.context.is_subscribed(5) && client.context.is_subscribed(6)) {
+    ///     let message = client.read().unwrap();
+    ///     client.read_contexts(message).unwrap();
+    /// }
+    /// let persisted = serde_json::to_string(&client.snapshot()).unwrap();
+    /// drop(client);
+    ///
+    /// let snapshot: SessionSnapshot = serde_json::from_str(&persisted).unwrap();
+    /// assert_eq!(snapshot.subscriptions.len(), 2);
+    /// let (mut client, _) = Client::connect(WampRequest { uri, protocol: "wamp.2.json" }).unwrap();
+    /// establish(&mut client);
+    /// client
+    ///     .restore(&snapshot, |_| Box::new(|ctx, _| ctx), |_| Box::new(|ctx, _| ctx))
+    ///     .unwrap();
+    ///
+    /// let mut topics: Vec<Value> = router
+    ///     .join()
+    ///     .unwrap()
+    ///     .into_iter()
+    ///     .map(|subscribe| {
+    ///         assert_eq!(subscribe[0], 32);
+    ///         subscribe[3].clone()
+    ///     })
+    ///     .collect();
+    /// topics.sort_by_key(|topic| topic.to_string());
+    /// assert_eq!(topics, vec!["com.example.a", "com.example.b"]);
+    /// ```
+    pub fn restore(
+        &mut self,
+        snapshot: &SessionSnapshot,
+        mut on_subscribed: impl FnMut(&SubscriptionSpec) -> CallBackResult<Subscribed>,
+        mut on_registered: impl FnMut(&RegistrationSpec) -> CallBackResult<Registered>,
+    ) -> Result<(), Error> {
+        for spec in &snapshot.subscriptions {
+            let mut subscribe = subscribe!(spec.topic.clone());
+            subscribe.options = spec.options.clone();
+            self.context.subscribe(subscribe, on_subscribed(spec))?;
+        }
+        for spec in &snapshot.registrations {
+            let mut register = register!(spec.procedure.clone());
+            register.options = spec.options.clone();
+            self.context.register(register, on_registered(spec))?;
+        }
+        self.acknowledged_keys
+            .borrow_mut()
+            .extend(snapshot.acknowledged_keys.iter().cloned());
+        Ok(())
+    }
+
+    /// Pump the event loop until a callback fills `slot` or the timeout elapses.
+    pub(crate) fn wait_for<T>(
+        &mut self,
+        slot: &Rc<RefCell<Option<T>>>,
+        timeout: Duration,
+        timeout_error: &'static str,
+    ) -> Result<T, Error> {
+        let deadline = Instant::now() + timeout;
+        loop {
+            if let Some(value) = slot.borrow_mut().take() {
+                break Ok(value);
+            }
+
+            if Instant::now() >= deadline {
+                break Err(Error::TimeOutError(timeout_error));
+            }
+
+            let message = self.read_until(deadline)?;
+            if message.is_some() {
+                self.read_contexts(message)?;
+            }
+        }
+    }
+
+    /// Cancel the calls made with `Context::call_with_timeout` whose deadline passed, failing
+    /// them with a `wamp.error.timeout` error.
+    fn expire_calls(&mut self) -> Result<(), Error> {
+        for (call, mut callback) in self.context.take_expired_calls(Instant::now()) {
+            self.send(Cancel {
+                request_id: call.request_id,
+                options: json!({ "mode": CancelMode::KillNoWait }),
+            })?;
+            let error = wamp_error(WampErrorEvent::Call, call.request_id, "wamp.error.timeout");
+            let context = callback(child_context!(self), Err(error));
+            self.context.extend(context);
+        }
+        Ok(())
+    }
+
+    /// Whether a `Close` frame has been read from the socket, or the connection dropped.
+    pub(crate) fn is_closed(&self) -> bool {
+        self.state == ConnectionState::Closed
+    }
+
     pub fn handle_and_empty_contexts(
         &mut self,
         message: Messages,
@@ -105,94 +2819,245 @@ impl Client {
                 WampErrorEvent::Unsubscribe => {
                     self.context
                         .unsubscriptions
-                        .retain(|(unsubscribe, _)| unsubscribe.request_id == error.request_id);
+                        .retain(|(unsubscribe, _)| unsubscribe.request_id != error.request_id);
                     Ok(Some(Messages::from(error)))
                 }
                 WampErrorEvent::Subscribe => {
                     self.context
                         .subscriptions
-                        .retain(|(subscribe, _)| subscribe.request_id == error.request_id);
+                        .retain(|(subscribe, _)| subscribe.request_id != error.request_id);
                     Ok(Some(Messages::from(error)))
                 }
                 WampErrorEvent::Publish => {
                     self.context
                         .publications
-                        .retain(|(publish, _)| publish.request_id == error.request_id);
+                        .retain(|(publish, _)| publish.request_id != error.request_id);
                     Ok(Some(Messages::from(error)))
                 }
                 WampErrorEvent::Register => {
                     self.context
                         .registrations
-                        .retain(|(register, _)| register.request_id == error.request_id);
+                        .retain(|(register, _)| register.request_id != error.request_id);
                     Ok(Some(Messages::from(error)))
                 }
                 WampErrorEvent::Unregister => {
                     self.context
                         .unregistrations
-                        .retain(|(unregister, _)| unregister.request_id == error.request_id);
+                        .retain(|(unregister, _)| unregister.request_id != error.request_id);
                     Ok(Some(Messages::from(error)))
                 }
                 WampErrorEvent::Invocation => {
                     self.context
                         .invocations
-                        .retain(|(invocation, _)| invocation.request_id == error.request_id);
+                        .retain(|(invocation, _)| invocation.request_id != error.request_id);
                     Ok(Some(Messages::from(error)))
                 }
                 WampErrorEvent::Cancel => {
                     self.context
-                        .invocations
-                        .retain(|(invocation, _)| invocation.request_id == error.request_id);
+                        .cancelations
+                        .retain(|(cancel, _)| cancel.request_id != error.request_id);
                     Ok(Some(Messages::from(error)))
                 }
             },
             Messages::Unsubscribed(unsubscribed) => {
-                match self.context.unsubscriptions.iter().find(|(i, _)|{ i.request_id == unsubscribed.request_id }) {
-                    Some((unsubscribe, _)) => {
-                        let subscription = unsubscribe.subscription;
-                        match self.context.events.iter().find(|(i, _)| { i.subscription == subscription }) {
-                            Some((subscribed, _)) => {
-                                let request_id = subscribed.request_id;
-                                self.context.unsubscriptions.retain(|(i, _)| {
-                                    i.subscription != subscription
-                                });
-                                self.context.events.retain(|(i, _)| {
-                                    i.subscription != subscription
-                                });
-                                self.context.subscriptions.retain(|(i, _)|{
-                                    i.request_id != request_id
-                                });
-                                Ok(Some(Messages::from(unsubscribed)))
-                            }, None => Ok(Some(Messages::from(unsubscribed)))
-                        }
-                        
-                    }, None => Ok(Some(Messages::from(unsubscribed)))
+                if let Some(subscription) = self
+                    .context
+                    .unsubscriptions
+                    .iter()
+                    .find(|(i, _)| i.request_id == unsubscribed.request_id)
+                    .map(|(unsubscribe, _)| unsubscribe.subscription)
+                {
+                    self.context.remove_subscription(subscription);
                 }
+                Ok(Some(Messages::from(unsubscribed)))
             }
             _ => Ok(None),
         }
     }
 
-    pub fn event_loop(&mut self) -> Result<(), Error> {
+    /// # Client Event Loop
+    /// Read and route frames until the session ends.
+    ///
+    /// A session ending normally is returned as `Ok` with the reason it ended, while `Err` is
+    /// reserved for transport and protocol errors.
+    ///
+    /// The session's `CancellationToken` is checked at least every 100 milliseconds while
+    /// waiting for a frame, so cancelling it from another thread, or calling `Client::shutdown`
+    /// from a callback, ends the loop with `LoopExit::Shutdown` even on a silent connection.
+    ///
+    /// ## Examples
+    /// Shutting down from another thread while the router stays silent:
+    /// ```
+    /// use std::thread;
+    /// use std::time::{Duration, Instant};
+    /// use wamp_client::hello::HelloBuilder;
+    /// use wamp_client::sync::client::{Client, LoopExit};
+    /// use wamp_client::sync::WampRequest;
+    /// use wamp_client::test_util::MockRouter;
+    ///
+    /// let uri = MockRouter::silent();
+    ///
+    /// let (mut client, _) = Client::connect(WampRequest { uri, protocol: "wamp.2.json" }).unwrap();
+    /// client.send(HelloBuilder::new("realm1").build().unwrap()).unwrap();
+    ///
+    /// let shutdown = client.cancellation_token();
+    /// thread::spawn(move || {
+    ///     thread::sleep(Duration::from_millis(100));
+    ///     shutdown.cancel();
+    /// });
+    ///
+    /// let started = Instant::now();
+    /// assert!(matches!(client.event_loop().unwrap(), LoopExit::Shutdown));
+    /// assert!(started.elapsed() < Duration::from_secs(5));
+    /// ```
+    ///
+    /// Entering the loop before sending a `Hello` would block forever, so it fails right away:
+    /// ```
+    /// use wamp_client::error::Error;
+    /// use wamp_client::sync::{client::Client, WampRequest};
+    /// use wamp_client::test_util::MockRouter;
+    ///
+    /// let uri = MockRouter::silent();
+    ///
+    /// let (mut client, _) = Client::connect(WampRequest { uri, protocol: "wamp.2.json" }).unwrap();
+    ///
+    /// assert!(matches!(client.event_loop(), Err(Error::HelloNotSent)));
+    /// ```
+    pub fn event_loop(&mut self) -> Result<LoopExit, Error> {
         loop {
-            let message = self.read()?;
-            if let Some(_) = message {
-                self.read_contexts(message)?;
+            if self.shutdown.is_cancelled() {
+                break Ok(LoopExit::Shutdown);
+            }
+            self.expire_calls()?;
+            self.finish_invocations()?;
+            let mut deadline = Instant::now() + SHUTDOWN_POLL;
+            if let Some(call_deadline) = self.context.next_call_deadline() {
+                deadline = deadline.min(call_deadline);
+            }
+            if !self.invocation_tokens.borrow().is_empty() {
+                deadline = deadline.min(Instant::now() + INVOCATION_POLL);
+            }
+            let message = match self.read_until(deadline) {
+                Err(Error::ConnectionClosed) => break Ok(LoopExit::Closed),
+                result => result?,
+            };
+            if self.is_closed() {
+                break Ok(LoopExit::Closed);
+            }
+
+            let goodbye = match &message {
+                Some(Messages::Goodbye(goodbye)) => Some(goodbye.clone()),
+                _ => None,
+            };
+            if message.is_some() {
+                match self.read_contexts(message) {
+                    Err(Error::Abort(abort)) => break Ok(LoopExit::Aborted(abort)),
+                    result => {
+                        result?;
+                    }
+                }
+            }
+            if let Some(goodbye) = goodbye {
+                break Ok(LoopExit::Goodbye(goodbye));
             }
         }
     }
 
-    pub fn read_contexts(&mut self, message: Option<Messages>) -> Result<Option<Messages>, Error> {
-        let socket = self.socket.clone();
-        self.context.messages.retain(move |i| {
-            let mut socket = socket.lock().unwrap();
-            socket.send(i.clone()).unwrap();
-            false
-        });
+    /// # Client Read Contexts
+    /// Route a message read with `Client::read` to the callbacks waiting for it, taking answered
+    /// requests out of the context.
+    ///
+    /// A `Cancel` sent with `Client::cancel` is settled by the `Interrupt`, the call's `Error` or
+    /// its final `Result`, whichever arrives.
+    ///
+    /// ## Examples
+    /// ```
+    /// use wamp_client::sync::client::Client;
+    /// use wamp_client::sync::WampRequest;
+    /// use wamp_client::test_util::{establish, MockRouter};
+    /// use wamp_core::call;
+    /// use wamp_core::messages::Cancel;
+    /// use wamp_core::serde_json::json;
+    /// use wamp_core::tungstenite::Message;
+    ///
+    /// let (uri, router) = MockRouter::spawn(move |router| {
+    ///     router.welcome();
+    ///
+    ///     let call = router.recv();
+    ///     let cancel = router.recv();
+    ///     assert_eq!(cancel[0], 49);
+    ///     assert_eq!(cancel[1], call[1]);
+    ///     socket
+    ///         .send(Message::Text(json!([8, 48, call[1], {}, "wamp.error.canceled"]).to_string()))
+    ///         .unwrap();
+    /// });
+    ///
+    /// let (mut client, _) = Client::connect(WampRequest { uri, protocol: "wamp.2.json" }).unwrap();
+    /// establish(&mut client);
+    ///
+    /// let call = call!("com.example.slow");
+    /// let request_id = call.request_id;
+    /// client.call(call, Box::new(|ctx, _| ctx)).unwrap();
+    /// let cancel = Cancel { request_id, options: json!({ "mode": "kill" }) };
+    /// client.cancel(cancel, Box::new(|ctx, _| ctx)).unwrap();
+    /// assert_eq!(client.context.pending_cancelations(), 1);
+    ///
+    /// while client.context.pending_calls() > 0 {
+    ///     let message = client.read().unwrap();
+    ///     client.read_contexts(message).unwrap();
+    /// }
+    /// assert_eq!(client.context.pending_cancelations(), 0);
+    /// drop(client);
+    /// router.join().unwrap();
+    /// ```
+    pub fn read_contexts(&mut self, mut message: Option<Messages>) -> Result<Option<Messages>, Error> {
+        if let (Some(message), Some(on_message)) = (&message, &mut self.on_message) {
+            on_message(message);
+        }
+        if let (Some(message), Some(matcher)) = (&mut message, &mut self.request_id_matcher) {
+            if let Some(request_id) = reply_request_id(message).and_then(|_| matcher(message)) {
+                set_reply_request_id(message, request_id);
+            }
+        }
+        if let Some(reply) = &message {
+            if self.is_duplicate_reply(reply) {
+                if let Some(on_protocol_violation) = &mut self.on_protocol_violation {
+                    on_protocol_violation(reply);
+                }
+                return Ok(None);
+            }
+        }
+        for queued in self.context.drain_messages() {
+            self.send(queued)?;
+        }
         let ctx = self.get_message_context(message)?;
         let ctx = self.extend_context(ctx)?;
         Ok(ctx)
     }
 
+    /// Whether a reply answers a request id that already had a reply of the same kind.
+    fn is_duplicate_reply(&mut self, message: &Messages) -> bool {
+        let request_id = match reply_request_id(message) {
+            Some(request_id) => request_id,
+            None => return false,
+        };
+        if let Messages::Result(result) = message {
+            if is_progress(result) {
+                return false;
+            }
+        }
+
+        let key = (discriminant(message), request_id);
+        if self.answered.contains(&key) {
+            return true;
+        }
+        if self.answered.len() == ANSWERED_HISTORY {
+            self.answered.pop_front();
+        }
+        self.answered.push_back(key);
+        false
+    }
+
     fn extend_context(
         &mut self,
         contexts: Option<(Messages, Option<Context>)>,
@@ -213,110 +3078,187 @@ impl Client {
     ) -> Result<Option<(Messages, Option<Context>)>, Error> {
         match message {
             Some(message) => match message {
-                Messages::Abort(abort) => Err(Error::Abort(abort)),
-                Messages::Error(error) => match error.event {
-                    WampErrorEvent::Call => {
-                        if let Some((_, callback)) = self.context.find_by_error_call(&error) {
-                            let context = callback(
-                                Context::new(Some(Arc::clone(&self.socket))),
-                                Err(error.clone()),
-                            );
-                            Ok(Some((Messages::from(error), Some(context))))
-                        } else {
-                            Ok(Some((Messages::from(error), None)))
-                        }
+                Messages::Abort(abort) => {
+                    self.state = ConnectionState::Closed;
+                    self.session_id = None;
+                    self.router_roles = None;
+                    Err(Error::Abort(abort))
+                }
+                Messages::Error(error) => {
+                    if let Some(kind) = RequestKind::of(&error.event) {
+                        self.last_errors.insert(kind, (Instant::now(), error.clone()));
                     }
-                    WampErrorEvent::Unsubscribe => {
-                        if let Some((_, callback)) = self.context.find_by_error_unsubscribe(&error)
-                        {
-                            let context = callback(
-                                Context::new(Some(Arc::clone(&self.socket))),
-                                Err(error.clone()),
-                            );
-                            Ok(Some((Messages::from(error), Some(context))))
-                        } else {
-                            Ok(Some((Messages::from(error), None)))
+                    if let Some(on_authorization_failed) = &mut self.on_authorization_failed {
+                        if is_authorization_failure(&error.error) {
+                            on_authorization_failed(&error);
                         }
                     }
-                    WampErrorEvent::Subscribe => {
-                        if let Some((_, callback)) = self.context.find_by_error_subscribe(&error) {
-                            let context = callback(
-                                Context::new(Some(Arc::clone(&self.socket))),
-                                Err(error.clone()),
-                            );
-                            Ok(Some((Messages::from(error), Some(context))))
-                        } else {
-                            Ok(Some((Messages::from(error), None)))
+                    match error.event {
+                        WampErrorEvent::Call => {
+                            let mut context = None;
+                            if let Some((_, mut callback)) =
+                                self.context.find_by_error_call(&error)
+                            {
+                                context = Some(callback(
+                                    child_context!(self),
+                                    Err(error.clone()),
+                                ));
+                            }
+                            // A canceled call is answered with an `Error`, settling the cancel too.
+                            if let Some((_, mut callback)) =
+                                self.context.find_by_error_cancel(&error)
+                            {
+                                let cancel_context = callback(
+                                    child_context!(self),
+                                    Err(error.clone()),
+                                );
+                                match &mut context {
+                                    Some(context) => context.extend(cancel_context),
+                                    None => context = Some(cancel_context),
+                                }
+                            }
+                            Ok(Some((Messages::from(error), context)))
                         }
-                    }
-                    WampErrorEvent::Publish => {
-                        if let Some((_, callback)) = self.context.find_by_error_publish(&error) {
-                            let context = callback(
-                                Context::new(Some(Arc::clone(&self.socket))),
-                                Err(error.clone()),
-                            );
-                            Ok(Some((Messages::from(error), Some(context))))
-                        } else {
-                            Ok(Some((Messages::from(error), None)))
+                        WampErrorEvent::Unsubscribe => {
+                            if let Some((_, mut callback)) =
+                                self.context.find_by_error_unsubscribe(&error)
+                            {
+                                let context = callback(
+                                    child_context!(self),
+                                    Err(error.clone()),
+                                );
+                                Ok(Some((Messages::from(error), Some(context))))
+                            } else {
+                                Ok(Some((Messages::from(error), None)))
+                            }
                         }
-                    }
-                    WampErrorEvent::Register => {
-                        if let Some((_, callback)) = self.context.find_by_error_register(&error) {
-                            let context = callback(
-                                Context::new(Some(Arc::clone(&self.socket))),
-                                Err(error.clone()),
-                            );
-                            Ok(Some((Messages::from(error), Some(context))))
-                        } else {
-                            Ok(Some((Messages::from(error), None)))
+                        WampErrorEvent::Subscribe => {
+                            if let Some((_, mut callback)) =
+                                self.context.find_by_error_subscribe(&error)
+                            {
+                                let context = callback(
+                                    child_context!(self),
+                                    Err(error.clone()),
+                                );
+                                Ok(Some((Messages::from(error), Some(context))))
+                            } else {
+                                Ok(Some((Messages::from(error), None)))
+                            }
                         }
-                    }
-                    WampErrorEvent::Unregister => {
-                        if let Some((_, callback)) = self.context.find_by_error_unregister(&error) {
-                            let context = callback(
-                                Context::new(Some(Arc::clone(&self.socket))),
-                                Err(error.clone()),
-                            );
-                            Ok(Some((Messages::from(error), Some(context))))
-                        } else {
-                            Ok(Some((Messages::from(error), None)))
+                        WampErrorEvent::Publish => {
+                            if let Some((_, mut callback)) =
+                                self.context.find_by_error_publish(&error)
+                            {
+                                let context = callback(
+                                    child_context!(self),
+                                    Err(error.clone()),
+                                );
+                                Ok(Some((Messages::from(error), Some(context))))
+                            } else {
+                                Ok(Some((Messages::from(error), None)))
+                            }
                         }
-                    }
-                    WampErrorEvent::Invocation => Ok(Some((Messages::from(error), None))),
-                    WampErrorEvent::Cancel => {
-                        if let Some((_, callback)) = self.context.find_by_error_cancel(&error) {
-                            let context = callback(
-                                Context::new(Some(self.socket.clone())),
-                                Err(error.clone()),
-                            );
-                            Ok(Some((Messages::from(error), Some(context))))
-                        } else {
-                            Ok(Some((Messages::from(error), None)))
+                        WampErrorEvent::Register => {
+                            if let Some((_, mut callback)) =
+                                self.context.find_by_error_register(&error)
+                            {
+                                let context = callback(
+                                    child_context!(self),
+                                    Err(error.clone()),
+                                );
+                                Ok(Some((Messages::from(error), Some(context))))
+                            } else {
+                                Ok(Some((Messages::from(error), None)))
+                            }
+                        }
+                        WampErrorEvent::Unregister => {
+                            if let Some((_, mut callback)) =
+                                self.context.find_by_error_unregister(&error)
+                            {
+                                let context = callback(
+                                    child_context!(self),
+                                    Err(error.clone()),
+                                );
+                                Ok(Some((Messages::from(error), Some(context))))
+                            } else {
+                                Ok(Some((Messages::from(error), None)))
+                            }
+                        }
+                        WampErrorEvent::Invocation => Ok(Some((Messages::from(error), None))),
+                        WampErrorEvent::Cancel => {
+                            if let Some((_, mut callback)) =
+                                self.context.find_by_error_cancel(&error)
+                            {
+                                let context = callback(
+                                    child_context!(self),
+                                    Err(error.clone()),
+                                );
+                                Ok(Some((Messages::from(error), Some(context))))
+                            } else {
+                                Ok(Some((Messages::from(error), None)))
+                            }
                         }
                     }
-                },
+                }
                 Messages::Event(event) => {
-                    if let Some((_, callback)) = self.context.find_event(&event) {
-                        let context =
-                            callback(Context::new(Some(Arc::clone(&self.socket))), event.clone());
+                    let mut context = None;
+                    if self.context.dedupe_subscriptions {
+                        // A subscription shared with `Context::set_dedupe_subscriptions` can have
+                        // several listeners, which all get the event.
+                        for (_, callback) in self
+                            .context
+                            .events
+                            .iter_mut()
+                            .filter(|(subscribed, _)| subscribed.subscription == event.subscription)
+                        {
+                            let ctx = match context.take() {
+                                Some(ctx) => ctx,
+                                None => child_context!(self),
+                            };
+                            context = Some(callback(ctx, event.clone()));
+                        }
+                    } else if let Some((_, callback)) = self.context.find_event(&event) {
+                        context = Some(callback(child_context!(self), event.clone()));
+                    }
+                    if context.is_some() {
+                        Ok(Some((Messages::from(event), context)))
+                    } else if let Some(callback) = &mut self.on_unmatched_event {
+                        let context = callback(child_context!(self), event.clone());
                         Ok(Some((Messages::from(event), Some(context))))
                     } else {
                         Ok(Some((Messages::from(event), None)))
                     }
                 }
                 Messages::Goodbye(goodbye) => {
-                    if let Some(callback) = &mut self.on_goodbye {
-                        let context =
-                            callback(Context::new(Some(self.socket.clone())), goodbye.clone());
-                        Ok(Some((Messages::from(goodbye), Some(context))))
-                    } else {
-                        Ok(Some((Messages::from(goodbye), None)))
+                    self.state = ConnectionState::Closing;
+                    self.session_id = None;
+                    self.router_roles = None;
+                    let context = match &mut self.on_goodbye {
+                        Some(callback) => Some(callback(child_context!(self), goodbye.clone())),
+                        None => None,
+                    };
+                    // `goodbye_and_out` is the router answering our own `Goodbye`, which must
+                    // not be answered again.
+                    if goodbye.reason == GOODBYE_AND_OUT {
+                        self.state = ConnectionState::Closed;
+                    } else if self.auto_goodbye {
+                        // Written right away, since nothing flushes a buffered client once the
+                        // session is closed.
+                        self.send_now(Goodbye {
+                            details: json!({}),
+                            reason: GOODBYE_AND_OUT.to_string(),
+                        })?;
+                        self.state = ConnectionState::Closed;
                     }
+                    Ok(Some((Messages::from(goodbye), context)))
                 }
                 Messages::Interrupt(interrupt) => {
-                    if let Some((_, callback)) = self.context.find_cancel(&interrupt) {
+                    if let Some(token) = self.invocation_tokens.borrow().get(&interrupt.request_id) {
+                        token.cancel();
+                    }
+                    if let Some((_, mut callback)) = self.context.find_cancel(&interrupt) {
                         let context = callback(
-                            Context::new(Some(self.socket.clone())),
+                            child_context!(self),
                             Ok(interrupt.clone()),
                         );
                         Ok(Some((Messages::from(interrupt), Some(context))))
@@ -325,9 +3267,9 @@ impl Client {
                     }
                 }
                 Messages::Published(published) => {
-                    if let Some((_, callback)) = self.context.find_publish(&published) {
+                    if let Some((_, mut callback)) = self.context.find_publish(&published) {
                         let context = callback(
-                            Context::new(Some(Arc::clone(&self.socket))),
+                            child_context!(self),
                             Ok(published.clone()),
                         );
                         Ok(Some((Messages::from(published), Some(context))))
@@ -336,42 +3278,64 @@ impl Client {
                     }
                 }
                 Messages::Registered(registered) => {
-                    if let Some((_, callback)) = self.context.find_register(&registered) {
+                    if let Some((register, mut callback)) = self.context.find_register(&registered) {
                         let context = callback(
-                            Context::new(Some(Arc::clone(&self.socket))),
+                            child_context!(self),
                             Ok(registered.clone()),
                         );
+                        self.context
+                            .active_registrations
+                            .push((register, registered.clone()));
                         Ok(Some((Messages::from(registered), Some(context))))
                     } else {
                         Ok(Some((Messages::from(registered), None)))
                     }
                 }
                 Messages::Result(result) => {
-                    if let Some((_, callback)) = self.context.find_call(&result) {
-                        let context =
-                            callback(Context::new(Some(self.socket.clone())), Ok(result.clone()));
-                        Ok(Some((Messages::from(result), Some(context))))
+                    // Progressive results keep the call pending until the final result arrives.
+                    if is_progress(&result) {
+                        if let Some((_, callback)) = self.context.find_call_progress(&result) {
+                            let context = callback(
+                                child_context!(self),
+                                Ok(result.clone()),
+                            );
+                            return Ok(Some((Messages::from(result), Some(context))));
+                        }
                     } else {
-                        Ok(Some((Messages::from(result), None)))
+                        // A call that completes anyway, such as with `CancelMode::Skip`, settles
+                        // the cancel sent for it.
+                        self.context
+                            .cancelations
+                            .retain(|(cancel, _)| cancel.request_id != result.request_id);
+                        if let Some((_, mut callback)) = self.context.find_call(&result) {
+                            let context = callback(child_context!(self), Ok(result.clone()));
+                            return Ok(Some((Messages::from(result), Some(context))));
+                        }
                     }
+                    Ok(Some((Messages::from(result), None)))
                 }
                 Messages::Subscribed(subscribed) => {
-                    if let Some((_, callback)) = self.context.find_subscribe(&subscribed) {
+                    if let Some((subscribe, mut callback)) = self.context.find_subscribe(&subscribed) {
                         let context = callback(
-                            Context::new(Some(self.socket.clone())),
+                            child_context!(self),
                             Ok(subscribed.clone()),
                         );
+                        self.context
+                            .active_subscriptions
+                            .push((subscribe, subscribed.clone()));
                         Ok(Some((Messages::from(subscribed), Some(context))))
                     } else {
                         Ok(Some((Messages::from(subscribed), None)))
                     }
                 }
                 Messages::Unregistered(unregistered) => {
-                    if let Some((_, callback)) = self.context.find_unregister(&unregistered) {
+                    if let Some((unregister, mut callback)) = self.context.find_unregister(&unregistered) {
+                        let registration = unregister.registration;
                         let context = callback(
-                            Context::new(Some(self.socket.clone())),
+                            child_context!(self),
                             Ok(unregistered.clone()),
                         );
+                        self.context.remove_registration(registration);
                         Ok(Some((Messages::from(unregistered), Some(context))))
                     } else {
                         Ok(Some((Messages::from(unregistered), None)))
@@ -380,7 +3344,7 @@ impl Client {
                 Messages::Invocation(invocation) => {
                     if let Some((_, callback)) = self.context.find_invocation(&invocation) {
                         let context = callback(
-                            Context::new(Some(self.socket.clone())),
+                            child_context!(self),
                             Ok(invocation.clone()),
                         );
                         Ok(Some((Messages::from(invocation), Some(context))))
@@ -389,35 +3353,52 @@ impl Client {
                     }
                 }
                 Messages::Unsubscribed(unsubscribed) => {
-                    if let Some((_, callback)) = self.context.find_unsubscribe(&unsubscribed) {
+                    if let Some((unsubscribe, mut callback)) = self.context.find_unsubscribe(&unsubscribed) {
+                        let subscription = unsubscribe.subscription;
                         let context = callback(
-                            Context::new(Some(self.socket.clone())),
+                            child_context!(self),
                             Ok(unsubscribed.clone()),
                         );
+                        self.context.remove_subscription(subscription);
                         Ok(Some((Messages::from(unsubscribed), Some(context))))
                     } else {
                         Ok(Some((Messages::from(unsubscribed), None)))
                     }
                 }
                 Messages::Welcome(welcome) => {
+                    self.state = ConnectionState::Established;
+                    self.router_capabilities = Some(RouterCapabilities::from(&welcome));
+                    self.session_id = Some(welcome.session);
+                    self.router_roles = Some(welcome.details["roles"].clone());
                     if let Some(callback) = &mut self.on_welcome {
-                        let context =
-                            callback(Context::new(Some(self.socket.clone())), welcome.clone());
+                        let context = callback(child_context!(self), welcome.clone());
                         Ok(Some((Messages::from(welcome), Some(context))))
                     } else {
                         Ok(Some((Messages::from(welcome), None)))
                     }
                 }
                 Messages::Challenge(challenge) => {
+                    // A challenge after `Welcome` re-authenticates the session, which keeps its
+                    // state, so only a challenge during the opening handshake changes it.
+                    if self.state == ConnectionState::Connecting {
+                        self.state = ConnectionState::Authenticating;
+                    }
                     if let Some(callback) = &mut self.on_challenge {
-                        let context =
-                            callback(Context::new(Some(self.socket.clone())), challenge.clone());
+                        let context = callback(child_context!(self), challenge.clone());
                         Ok(Some((Messages::from(challenge), Some(context))))
                     } else {
                         Ok(Some((Messages::from(challenge), None)))
                     }
                 }
-                Messages::Extension(_) => todo!(),
+                Messages::Extension(extension) => {
+                    if let Some(callback) = &mut self.on_extension {
+                        let context =
+                            callback(child_context!(self), Value::from(extension.clone()));
+                        Ok(Some((Messages::Extension(extension), Some(context))))
+                    } else {
+                        Ok(Some((Messages::Extension(extension), None)))
+                    }
+                }
                 Messages::Cancel(cancel) => Err(Error::InvalidFrameReceived(cancel.into())),
                 Messages::Call(call) => Err(Error::InvalidFrameReceived(call.into())),
                 Messages::Yield(r#yield) => Err(Error::InvalidFrameReceived(r#yield.into())),
@@ -441,23 +3422,538 @@ impl Client {
         }
     }
 
+    /// # Client Read
+    /// Read the next WAMP frame from the socket, `None` for control frames.
+    ///
+    /// A dropped or already closed connection is returned as `Error::ConnectionClosed`, and the
+    /// client is then `ConnectionState::Closed`. A ping is answered with a pong carrying its
+    /// payload right away, so idle connections stay open while only reading.
+    ///
+    /// ## Examples
+    /// ```
+    /// use wamp_client::hello::HelloBuilder;
+    /// use wamp_client::sync::{client::Client, WampRequest};
+    /// use wamp_client::test_util::MockRouter;
+    /// use wamp_core::tungstenite::Message;
+    ///
+    /// let (uri, router) = MockRouter::spawn(move |router| {
+    ///     router.socket().send(Message::Ping(b"heartbeat".to_vec())).unwrap();
+    ///     loop {
+    ///         if let Message::Pong(payload) = router.socket().read().unwrap() {
+    ///             return payload;
+    ///         }
+    ///     }
+    /// });
+    ///
+    /// let (mut client, _) = Client::connect(WampRequest { uri, protocol: "wamp.2.json" }).unwrap();
+    /// client.send(HelloBuilder::new("realm1").build().unwrap()).unwrap();
+    ///
+    /// assert!(client.read().unwrap().is_none());
+    /// drop(client);
+    /// assert_eq!(router.join().unwrap(), b"heartbeat".to_vec());
+    /// ```
+    ///
+    /// Reading before a `Hello` was sent fails right away, instead of waiting on a router that
+    /// only answers a `Hello`:
+    /// ```
+    /// use wamp_client::error::Error;
+    /// use wamp_client::hello::HelloBuilder;
+    /// use wamp_client::sync::{client::Client, WampRequest};
+    /// use wamp_client::test_util::MockRouter;
+    /// use wamp_core::messages::Messages;
+    ///
+    /// let (uri, router) = MockRouter::spawn(move |router| {
+    ///     router.welcome();
+    /// });
+    ///
+    /// let (mut client, _) = Client::connect(WampRequest { uri, protocol: "wamp.2.json" }).unwrap();
+    /// assert!(matches!(client.read(), Err(Error::HelloNotSent)));
+    ///
+    /// client.send(HelloBuilder::new("realm1").build().unwrap()).unwrap();
+    /// assert!(matches!(client.read().unwrap(), Some(Messages::Welcome(_))));
+    /// drop(client);
+    /// router.join().unwrap();
+    /// ```
     pub fn read(&mut self) -> Result<Option<Messages>, Error> {
-        let message = self.socket.lock().unwrap().read().unwrap();
+        match self.read_frame()? {
+            Some(text) => Ok(Some(from_str(&text)?)),
+            None => Ok(None),
+        }
+    }
+
+    /// # Client Poll
+    /// Read the next WAMP frame like `Client::read`, returning `None` right away when no frame
+    /// is available instead of blocking, to drive the client from an existing poll loop.
+    ///
+    /// The socket is only non blocking for the duration of the call. Polling is supported on
+    /// plain `ws://` connections and on `wss://` ones made with the `native-tls` feature, and
+    /// returns `Error::Error` on others.
+    ///
+    /// ## Examples
+    /// ```
+    /// use std::sync::mpsc::channel;
+    /// use wamp_client::hello::HelloBuilder;
+    /// use wamp_client::sync::{client::Client, WampRequest};
+    /// use wamp_client::test_util::{MockRouter, WELCOME};
+    /// use wamp_core::messages::Messages;
+    ///
+    /// let (release, released) = channel::<()>();
+    /// let (uri, _) = MockRouter::spawn(move |router| {
+    ///     released.recv().unwrap();
+    ///     router.send(WELCOME);
+    /// });
+    ///
+    /// let (mut client, _) = Client::connect(WampRequest { uri, protocol: "wamp.2.json" }).unwrap();
+    /// client.send(HelloBuilder::new("realm1").build().unwrap()).unwrap();
+    ///
+    /// // Nothing was sent yet, so this does not block.
+    /// assert!(client.poll().unwrap().is_none());
+    ///
+    /// release.send(()).unwrap();
+    /// let message = loop {
+    ///     if let Some(message) = client.poll().unwrap() {
+    ///         break message;
+    ///     }
+    /// };
+    /// assert!(matches!(message, Messages::Welcome(_)));
+    /// ```
+    pub fn poll(&mut self) -> Result<Option<Messages>, Error> {
+        self.set_nonblocking(true)?;
+        let message = self.read_timeout(Duration::from_secs(0));
+        self.set_nonblocking(false)?;
+        message
+    }
+
+    fn set_nonblocking(&self, nonblocking: bool) -> Result<(), Error> {
+        match tcp_stream(&self.socket.lock().unwrap()) {
+            Some(stream) => Ok(stream.set_nonblocking(nonblocking)?),
+            None => Err(Error::Error("polling is not supported on this kind of connection")),
+        }
+    }
+
+    /// # Client Read Timeout
+    /// Read the next WAMP frame like `Client::read`, returning `None` if none arrives within
+    /// `timeout`.
+    ///
+    /// The socket is not locked while the connection is idle, so other threads holding the
+    /// socket can write to it meanwhile.
+    pub fn read_timeout(&mut self, timeout: Duration) -> Result<Option<Messages>, Error> {
+        self.read_until(Instant::now() + timeout)
+    }
+
+    /// Read the next WAMP frame like `Client::read`, `None` once `deadline` passes.
+    fn read_until(&mut self, deadline: Instant) -> Result<Option<Messages>, Error> {
+        match self.read_frame_until(Some(deadline))? {
+            Some(text) => Ok(Some(from_str(&text)?)),
+            None => Ok(None),
+        }
+    }
+
+    /// Wait for the next message on the socket, `None` once `deadline` passes, sending keep
+    /// alive pings while waiting.
+    fn next_message(&mut self, deadline: Option<Instant>) -> Result<Option<Message>, Error> {
+        loop {
+            let now = Instant::now();
+            let mut wait = deadline.map_or(IDLE_WAIT, |deadline| deadline.saturating_duration_since(now));
+            let socket = &self.socket;
+            match self.keepalive.as_mut().map(|keepalive| keepalive.poll(socket, now)) {
+                Some(Some(next_ping)) => wait = wait.min(next_ping),
+                Some(None) => self.keepalive = None,
+                None => {}
+            }
+            if let Some(message) = crate::core::read_timeout(&self.socket, wait)? {
+                return Ok(Some(message));
+            }
+            if deadline.map_or(false, |deadline| Instant::now() >= deadline) {
+                return Ok(None);
+            }
+        }
+    }
+
+    /// # Client Read Value
+    /// Read the next WAMP frame as a raw `Value`, without parsing it into a `Messages` variant.
+    ///
+    /// Frames read this way are not routed to any callbacks. Control frames are handled the same
+    /// as in `Client::read` and return `None`.
+    pub fn read_value(&mut self) -> Result<Option<Value>, Error> {
+        match self.read_frame()? {
+            Some(text) => Ok(Some(from_str(&text)?)),
+            None => Ok(None),
+        }
+    }
+
+    /// # Client Read Frame
+    /// Read the next WAMP frame as its raw text, without parsing it. Control frames are handled
+    /// the same as in `Client::read` and return `None`.
+    ///
+    /// Binary frames of a `wamp.2.msgpack` session are transcoded to JSON text.
+    ///
+    /// Pair this with `messages::visit_result_args` to stream the args of large results.
+    ///
+    /// Returns `Error::HelloNotSent` while connecting if no `Hello` was sent with `Client::send`
+    /// or `Client::write`, since the router only answers a `Hello`.
+    pub fn read_frame(&mut self) -> Result<Option<String>, Error> {
+        self.read_frame_until(None)
+    }
+
+    /// Read the next frame like `Client::read_frame`, `None` once `deadline` passes.
+    fn read_frame_until(&mut self, deadline: Option<Instant>) -> Result<Option<String>, Error> {
+        if self.state == ConnectionState::Connecting && !self.hello_sent {
+            return Err(Error::HelloNotSent);
+        }
+        if self.close_frame.is_some() {
+            return Err(Error::ConnectionClosed);
+        }
+        let message = match self.next_message(deadline) {
+            Ok(Some(message)) => message,
+            Ok(None) => return Ok(None),
+            Err(error) => {
+                if let Error::ConnectionClosed = error {
+                    self.state = ConnectionState::Closed;
+                }
+                return Err(error);
+            }
+        };
+        call_raw_hook(&self.on_raw_recv, &message);
         match message {
-            Message::Text(message) => Ok(Some(from_str(&message)?)),
-            Message::Ping(_) => Ok(None),
-            Message::Close(_) => Ok(None),
-            Message::Binary(_) => Err(Error::Error("Error: Binary frame received\n\nCurrently I have not added support for serialization beyond string json format. Please create an issue if you are interested in contributing. I am planning on implementing support for the msg_pack format as well.")),
-            Message::Pong(_) => Ok(None),
+            Message::Text(_) | Message::Binary(_) => self.serializer.decode(message),
+            Message::Ping(payload) => {
+                // tungstenite only flushes its own pong on the next read or write, which an idle
+                // connection may not get to. A pong sent here replaces that queued one.
+                let socket = &mut *self.socket.lock().unwrap();
+                socket.send(Message::Pong(payload))?;
+                Ok(None)
+            }
+            Message::Close(frame) => {
+                self.state = ConnectionState::Closed;
+                self.session_id = None;
+                self.router_roles = None;
+                // tungstenite queued the close reply, flushing it completes the closing
+                // handshake since nothing is read past this frame.
+                match self.socket.lock().unwrap().flush() {
+                    Ok(())
+                    | Err(tungstenite::Error::ConnectionClosed)
+                    | Err(tungstenite::Error::AlreadyClosed) => {}
+                    Err(error) => return Err(Error::from(error)),
+                }
+                let frame = frame.map(CloseFrame::into_owned).unwrap_or(CloseFrame {
+                    code: CloseCode::Status,
+                    reason: "".into(),
+                });
+                self.close_frame = Some(frame.clone());
+                if let Some(on_close) = &mut self.on_close {
+                    let context = on_close(child_context!(self), frame);
+                    self.context.extend(context);
+                }
+                Ok(None)
+            }
+            Message::Pong(payload) => {
+                if let Some(on_pong) = &mut self.on_pong {
+                    on_pong(&payload);
+                }
+                Ok(None)
+            }
             Message::Frame(_) => Err(Error::Error("frame received from tungstenite, which their docs say isnt possible\nif this happened, run.")),
         }
     }
 
+    /// # Client Send
+    /// Send a message right away, or queue it like `Client::write` in buffered mode.
     pub fn send<T: TryInto<Message>>(&mut self, message: T) -> Result<(), Error>
     where
         Error: From<<T as TryInto<Message>>::Error>,
     {
+        if self.buffered {
+            return self.write(message);
+        }
+        self.send_now(message)
+    }
+
+    /// Send a message right away, in buffered mode too.
+    fn send_now<T: TryInto<Message>>(&mut self, message: T) -> Result<(), Error>
+    where
+        Error: From<<T as TryInto<Message>>::Error>,
+    {
+        let message = self.outbound(message.try_into()?)?;
+        call_raw_hook(&self.on_raw_send, &message);
+        let socket = &mut *self.socket.lock().unwrap();
+        Ok(socket.send(message)?)
+    }
+
+    /// # Client Set Buffered
+    /// In buffered mode `Client::send` queues messages until `Client::flush`, so bursts of
+    /// frames are coalesced. Messages sent by the contexts handed to callbacks, and pings sent
+    /// with `Client::ping`, still go out right away.
+    pub fn set_buffered(&mut self, buffered: bool) -> &mut Self {
+        self.buffered = buffered;
+        self
+    }
+
+    /// # Client Write
+    /// Queue a message without sending it, so several frames can be coalesced into fewer
+    /// writes. Call `Client::flush` to send them.
+    ///
+    /// ## Examples
+    /// ```no_run
+    /// use wamp_client::sync::{client::Client, WampRequest};
+    /// use wamp_core::publish;
+    ///
+    /// let (mut client, _) = Client::connect(WampRequest { uri: "ws://localhost:8080/ws", protocol: "wamp.2.json" }).unwrap();
+    ///
+    /// client.write(publish!("topic")).unwrap();
+    /// client.write(publish!("topic")).unwrap();
+    /// // Both publishes go out together.
+    /// client.flush().unwrap();
+    /// ```
+    pub fn write<T: TryInto<Message>>(&mut self, message: T) -> Result<(), Error>
+    where
+        Error: From<<T as TryInto<Message>>::Error>,
+    {
+        let message = self.outbound(message.try_into()?)?;
+        self.make_room(message.len())?;
+        self.write_buffer_bytes += message.len();
+        Ok(self.write_buffer.push_back(message))
+    }
+
+    /// Apply the byte budget to a message of `len` bytes about to be queued, dropping the oldest
+    /// queued messages with `Overflow::DropOldest`, those waiting for `Client::flush` first.
+    fn make_room(&mut self, len: usize) -> Result<(), Error> {
+        let (budget, overflow) = match self.byte_budget {
+            Some(byte_budget) => byte_budget,
+            None => return Ok(()),
+        };
+        while self.buffered_bytes() + len > budget {
+            if overflow == Overflow::Error {
+                return Err(Error::BudgetExceeded(budget));
+            }
+            if let Some(oldest) = self.write_buffer.pop_front() {
+                self.write_buffer_bytes -= oldest.len();
+            } else if !self.context.messages.is_empty() {
+                self.context.messages.remove(0);
+            } else {
+                // Only events read ahead are left, which the iterators holding them drop.
+                return Err(Error::BudgetExceeded(budget));
+            }
+            self.dropped_messages += 1;
+        }
+        Ok(())
+    }
+
+    /// # Client Set Byte Budget
+    /// Cap the bytes held in the client's buffers, for targets that need a hard ceiling on the
+    /// memory the client uses. Every buffer `Client::buffered_bytes` counts is held to it.
+    ///
+    /// `overflow` picks between refusing new messages and dropping the oldest queued ones.
+    /// Events read ahead by an `EventIterator` cannot be refused, so past the budget the oldest
+    /// of them are dropped with either overflow. `None` lifts the cap.
+    ///
+    /// ## Examples
+    /// ```
+    /// use wamp_client::error::Error;
+    /// use wamp_client::sync::client::{Client, Overflow};
+    /// use wamp_client::sync::WampRequest;
+    /// use wamp_client::test_util::MockRouter;
+    /// use wamp_core::messages::Publish;
+    /// use wamp_core::publish;
+    ///
+    /// let uri = MockRouter::silent();
+    ///
+    /// // Publishes of the same size.
+    /// fn notice() -> Publish {
+    ///     let mut publish = publish!("topic");
+    ///     publish.request_id = 1;
+    ///     publish
+    /// }
+    ///
+    /// let (mut client, _) = Client::connect(WampRequest { uri, protocol: "wamp.2.json" }).unwrap();
+    ///
+    /// // Without a socket the context queues what it sends, which counts towards the budget.
+    /// client.context.socket = None;
+    /// client.context.send(notice()).unwrap();
+    /// let size = client.buffered_bytes();
+    /// assert!(size > 0);
+    ///
+    /// client.set_byte_budget(Some((size * 2, Overflow::Error)));
+    /// client.write(notice()).unwrap();
+    /// assert_eq!(client.buffered_bytes(), size * 2);
+    /// assert!(matches!(client.write(notice()), Err(Error::BudgetExceeded(_))));
+    ///
+    /// client.set_byte_budget(Some((size * 2, Overflow::DropOldest)));
+    /// client.write(notice()).unwrap();
+    /// assert_eq!(client.dropped_messages(), 1);
+    /// assert_eq!(client.buffered_bytes(), size * 2);
+    /// ```
+    pub fn set_byte_budget(&mut self, byte_budget: Option<(usize, Overflow)>) -> &mut Self {
+        self.byte_budget = byte_budget;
+        self
+    }
+
+    /// # Client Buffered Bytes
+    /// Bytes held in the client's buffers: the messages queued for `Client::flush`, the
+    /// messages queued by a context without a socket, and the events read ahead by an
+    /// `EventIterator`.
+    pub fn buffered_bytes(&self) -> usize {
+        let context_bytes: usize = self.context.messages.iter().map(Message::len).sum();
+        self.write_buffer_bytes + context_bytes + self.read_ahead_bytes.get()
+    }
+
+    /// Number of queued messages and read ahead events dropped to stay within the byte budget.
+    pub fn dropped_messages(&self) -> u64 {
+        self.dropped_messages
+    }
+
+    /// The count of bytes read ahead, shared with the listener of an `EventIterator`.
+    pub(crate) fn read_ahead_bytes(&self) -> Rc<Cell<usize>> {
+        self.read_ahead_bytes.clone()
+    }
+
+    /// Drop the oldest events read ahead into `events` until the client is within its budget.
+    pub(crate) fn trim_read_ahead(&mut self, events: &mut VecDeque<(usize, Event)>) {
+        if let Some((budget, _)) = self.byte_budget {
+            while self.buffered_bytes() > budget {
+                match events.pop_front() {
+                    Some((size, _)) => {
+                        self.read_ahead_bytes.set(self.read_ahead_bytes.get() - size);
+                        self.dropped_messages += 1;
+                    }
+                    None => break,
+                }
+            }
+        }
+    }
+
+    /// Note a `Hello` on its way out, then encode the frame in the session's serialization.
+    fn outbound(&mut self, message: Message) -> Result<Message, Error> {
+        if self.state == ConnectionState::Connecting && !self.hello_sent {
+            self.hello_sent = is_hello(&message);
+        }
+        if let Some(kind) = frame_code(&message).and_then(RequestKind::of_frame) {
+            let now = Instant::now();
+            let metrics = self.send_metrics.entry(kind).or_insert(SendMetrics {
+                count: 0,
+                first: now,
+                last: now,
+            });
+            metrics.count += 1;
+            metrics.last = now;
+        }
+        self.serializer.encode(message)
+    }
+
+    /// # Client Flush
+    /// Send every message queued with `Client::write`, in order.
+    pub fn flush(&mut self) -> Result<(), Error> {
+        let socket = &mut *self.socket.lock().unwrap();
+        while let Some(message) = self.write_buffer.pop_front() {
+            self.write_buffer_bytes -= message.len();
+            call_raw_hook(&self.on_raw_send, &message);
+            socket.write(message)?;
+        }
+        Ok(socket.flush()?)
+    }
+
+    /// # Client Ping
+    /// Send a WebSocket ping right away, ahead of any messages queued in buffered mode, so round
+    /// trip times measured with `Client::on_pong` are not skewed by the write buffer.
+    ///
+    /// ## Examples
+    /// ```
+    /// use std::sync::mpsc::channel;
+    /// use std::time::Duration;
+    /// use wamp_client::sync::{client::Client, WampRequest};
+    /// use wamp_client::test_util::MockRouter;
+    /// use wamp_core::publish;
+    /// use wamp_core::tungstenite::Message;
+    ///
+    /// let (frames, received) = channel();
+    /// let (uri, router) = MockRouter::spawn(move |router| {
+    ///     while let Ok(frame) = router.socket().read() {
+    ///         frames.send(frame).unwrap();
+    ///     }
+    /// });
+    ///
+    /// let (mut client, _) = Client::connect(WampRequest { uri, protocol: "wamp.2.json" }).unwrap();
+    /// client.set_buffered(true);
+    ///
+    /// // The publish waits for the flush, the ping goes out immediately.
+    /// client.send(publish!("topic")).unwrap();
+    /// client.ping(b"rtt".to_vec()).unwrap();
+    /// let first = received.recv_timeout(Duration::from_secs(5)).unwrap();
+    /// assert_eq!(first, Message::Ping(b"rtt".to_vec()));
+    /// assert!(client.buffered_bytes() > 0);
+    ///
+    /// client.flush().unwrap();
+    /// match received.recv_timeout(Duration::from_secs(5)).unwrap() {
+    ///     Message::Text(text) => assert!(text.starts_with("[16,")),
+    ///     frame => panic!("expected the publish, got {:?}", frame),
+    /// }
+    /// drop(client);
+    /// router.join().unwrap();
+    /// ```
+    pub fn ping(&mut self, payload: Vec<u8>) -> Result<(), Error> {
         let socket = &mut *self.socket.lock().unwrap();
-        Ok(socket.send(message.try_into()?)?)
+        Ok(socket.send(Message::Ping(payload))?)
+    }
+
+    /// # Client Send Value
+    /// Send an already built WAMP frame, such as `[48, 1, {}, "procedure"]`, exactly as it is
+    /// serialized by `serde_json`.
+    ///
+    /// The frame is not checked against the WAMP spec and no callback is attached to it.
+    pub fn send_value(&mut self, value: &Value) -> Result<(), Error> {
+        self.send(value_frame(value)?)
+    }
+}
+
+/// Whether an error URI, such as `wamp.error.not_authorized`, denies the request.
+fn is_authorization_failure(uri: &str) -> bool {
+    uri.ends_with("authorization_failed") || uri.ends_with("not_authorized")
+}
+
+/// The message type code a JSON frame starts with, such as `48` for a `Call`.
+fn frame_code(message: &Message) -> Option<u64> {
+    match message {
+        Message::Text(text) => {
+            let code = text.trim_start().strip_prefix('[')?.trim_start();
+            let end = code.find(|c: char| !c.is_ascii_digit()).unwrap_or(code.len());
+            code[..end].parse().ok()
+        }
+        _ => None,
+    }
+}
+
+/// Whether a JSON frame is a `Hello`.
+fn is_hello(message: &Message) -> bool {
+    match message {
+        Message::Text(text) => from_str::<Value>(text)
+            .map(|frame| frame[0] == json!(1))
+            .unwrap_or(false),
+        _ => false,
+    }
+}
+
+/// Whether a `Result` is a progressive result, with more results to follow for the same call.
+fn is_progress(result: &WampResult) -> bool {
+    result
+        .details
+        .get("progress")
+        .and_then(Value::as_bool)
+        .unwrap_or(false)
+}
+
+/// The `Yield`, or the `Error`, answering invocation `request_id` with the result of its handler.
+fn invocation_reply(request_id: u64, result: Result<Value, WampError>) -> Result<Yield, WampError> {
+    match result {
+        Ok(value) => Ok(Yield {
+            request_id,
+            options: json!({}),
+            args: if value.is_array() { value } else { json!([value]) },
+            kwargs: json!({}),
+        }),
+        Err(mut error) => {
+            error.event = WampErrorEvent::Invocation;
+            error.request_id = request_id;
+            Err(error)
+        }
     }
 }
@@ -1,13 +1,34 @@
-use crate::core::{error::Error, messages::*};
-use tungstenite::Message;
-
-use super::client::{self};
-use super::super::core::Socket;
+use crate::{
+    core::Socket,
+    error::Error,
+    messages::{wamp_error, Serializer},
+    options::{CancelMode, MatchPolicy, PublishOptions, RegisterOptions, SubscribeOptions},
+};
+use std::cell::RefCell;
+use std::collections::HashMap;
+use std::rc::Rc;
+use std::time::{Duration, Instant};
+use wamp_core::messages::*;
+use wamp_core::serde_json::{json, Value};
+use wamp_core::tungstenite::Message;
+use wamp_core::{call, publish, register, subscribe};
 
 pub(crate) type CallBack<T> = Box<dyn FnMut(Context, T) -> Context>;
 pub(crate) type CallBackResult<T> = CallBack<Result<T, WampError>>;
 pub(crate) type CallBackVec<K, V> = Vec<(K, CallBack<V>)>;
 pub(crate) type CallBackVecResult<K, V> = CallBackVec<K, Result<V, WampError>>; 
+pub(crate) type RawHook = Rc<RefCell<dyn FnMut(&[u8])>>;
+
+/// Call a raw frame hook with the serialized bytes of a data frame.
+pub(crate) fn call_raw_hook(hook: &Option<RawHook>, message: &Message) {
+    if let Some(hook) = hook {
+        match message {
+            Message::Text(text) => (&mut *hook.borrow_mut())(text.as_bytes()),
+            Message::Binary(bytes) => (&mut *hook.borrow_mut())(bytes),
+            _ => {}
+        }
+    }
+}
 
 macro_rules! create_push_methods {
     (
@@ -20,6 +41,14 @@ macro_rules! create_push_methods {
             $method_name: $var_type,
             callback: $callback,
         ) -> Result<(), Error> {
+            // A second pending request with the same id would make its reply ambiguous.
+            if self
+                .$vec_name
+                .iter()
+                .any(|i| i.0.request_id == $method_name.request_id)
+            {
+                return Err(Error::DuplicateRequestId($method_name.request_id));
+            }
             self.send($method_name.clone())?;
             Ok(self.$vec_name.push(($method_name, callback)))
         }
@@ -41,14 +70,17 @@ macro_rules! create_push_methods {
 }
 
 macro_rules! create_find_methods {
+    // Replies answer a request once, so the pending entry is taken out of the context.
     ($method_name: ident, $var_name: ident, $vec_name: ident, $return_type: ident, $var_type: ident) => {
         pub(crate) fn $method_name(
             &mut self,
             $var_name: &$var_type,
-        ) -> Option<&mut ($return_type, CallBackResult<$var_type>)> {
-            self.$vec_name
-                .iter_mut()
-                .find(|i| i.0.request_id == $var_name.request_id)
+        ) -> Option<($return_type, CallBackResult<$var_type>)> {
+            let position = self
+                .$vec_name
+                .iter()
+                .position(|i| i.0.request_id == $var_name.request_id)?;
+            Some(self.$vec_name.remove(position))
         }
     };
     (event: $method_name: ident, $var_name: ident, $vec_name: ident, $return_type: ident, $var_type: ident) => {
@@ -61,6 +93,37 @@ macro_rules! create_find_methods {
                 .find(|i| i.0.subscription == $var_name.subscription)
         }
     };
+    (registration: $method_name: ident, $var_name: ident, $vec_name: ident, $return_type: ident, $var_type: ident) => {
+        pub(crate) fn $method_name(
+            &mut self,
+            $var_name: &$var_type,
+        ) -> Option<&mut ($return_type, CallBackResult<$var_type>)> {
+            self.$vec_name
+                .iter_mut()
+                .find(|i| i.0.registration == $var_name.registration)
+        }
+    };
+}
+
+macro_rules! create_raw_options_methods {
+    (
+        $(#[$attr:meta])*
+        {$method_name: ident, $push_method: ident, $macro_name: ident, $callback: ty}
+    ) => {
+        $(#[$attr])*
+        pub fn $method_name<T: ToString>(
+            &mut self,
+            uri: T,
+            options: Value,
+            callback: $callback,
+        ) -> Result<u64, Error> {
+            let mut message = $macro_name!(uri.to_string());
+            message.options = options;
+            let request_id = message.request_id;
+            self.$push_method(message, callback)?;
+            Ok(request_id)
+        }
+    };
 }
 
 macro_rules! create_find_by_error_method {
@@ -68,20 +131,27 @@ macro_rules! create_find_by_error_method {
         pub fn $method_name(
             &mut self,
             error: &WampError,
-        ) -> Option<&mut ($method_type, CallBackResult<$return_type>)> {
-            if let Some(info) = self
+        ) -> Option<($method_type, CallBackResult<$return_type>)> {
+            let position = self
                 .$vec_name
-                .iter_mut()
-                .find(|($method_name, _)| $method_name.request_id == error.request_id)
-            {
-                Some(info)
-            } else {
-                None
-            }
+                .iter()
+                .position(|($method_name, _)| $method_name.request_id == error.request_id)?;
+            Some(self.$vec_name.remove(position))
         }
     };
 }
 
+macro_rules! create_pending_methods {
+    ($($(#[$attr:meta])* $method_name: ident, $vec_name: ident;)*) => {
+        $(
+            $(#[$attr])*
+            pub fn $method_name(&self) -> usize {
+                self.$vec_name.len()
+            }
+        )*
+    };
+}
+
 pub struct Context {
     pub socket: Option<Socket>,
     pub(crate) registrations: CallBackVecResult<Register, Registered>,
@@ -90,11 +160,21 @@ pub struct Context {
     pub(crate) unsubscriptions: CallBackVecResult<Unsubscribe, Unsubscribed>,
     pub(crate) publications: CallBackVecResult<Publish, Published>,
     pub(crate) calls: CallBackVecResult<Call, WampResult>,
+    /// Deadlines of calls made with `call_with_timeout`, by request id.
+    pub(crate) call_deadlines: Vec<(u64, Instant)>,
     pub(crate) events: CallBackVec<Subscribed, Event>,
     pub(crate) invocations: CallBackVecResult<Registered, Invocation>,
     //pub(crate) errors: CallBackVecResult<Messages, WampError>,
     pub(crate) messages: Vec<Message>,
     pub(crate) cancelations: CallBackVecResult<Cancel, Interrupt>,
+    pub(crate) active_subscriptions: Vec<(Subscribe, Subscribed)>,
+    pub(crate) active_registrations: Vec<(Register, Registered)>,
+    pub(crate) on_raw_send: Option<RawHook>,
+    pub(crate) serializer: Serializer,
+    pub(crate) dedupe_subscriptions: bool,
+    /// Extra subscribes sharing a deduped subscription, by the request id of the `Subscribe`
+    /// that was sent for it.
+    pub(crate) subscription_refs: HashMap<u64, usize>,
 }
 
 impl Context {
@@ -106,7 +186,7 @@ impl Context {
     /// Primarily internal currently, im working on support to better manipulate this process.
     /// ## Examples
     /// ```
-    /// use wamp::client::context::Context;
+    /// use wamp_client::sync::context::Context;
     ///
     /// // Create a new context with no socket.
     /// let context = Context::new(None);
@@ -120,11 +200,18 @@ impl Context {
             unsubscriptions: vec![],
             publications: vec![],
             calls: vec![],
+            call_deadlines: vec![],
             events: vec![],
             invocations: vec![],
             messages: vec![],
             //errors: vec![],
             cancelations: vec![],
+            active_subscriptions: vec![],
+            active_registrations: vec![],
+            on_raw_send: None,
+            serializer: Serializer::Json,
+            dedupe_subscriptions: false,
+            subscription_refs: HashMap::new(),
         }
     }
 
@@ -140,9 +227,8 @@ impl Context {
     /// I may need to add functionality for erroring on capacity exceeded.
     /// ## Examples
     /// ```
-    /// use wamp::client::context::Context;
-    /// use wamp::core::messages::Call;
-    /// use wamp::call;
+    /// use wamp_client::sync::context::Context;
+    /// use wamp_core::call;
     ///
     /// let mut context = Context::new_with_capacity(None, 10);
     ///
@@ -163,11 +249,18 @@ impl Context {
             unsubscriptions: Vec::with_capacity(capacity),
             publications: Vec::with_capacity(capacity),
             calls: Vec::with_capacity(capacity),
+            call_deadlines: Vec::with_capacity(capacity),
             events: Vec::with_capacity(capacity),
             invocations: Vec::with_capacity(capacity),
             messages: Vec::with_capacity(capacity),
             //errors: Vec::with_capacity(capacity),
             cancelations: Vec::with_capacity(capacity),
+            active_subscriptions: Vec::with_capacity(capacity),
+            active_registrations: Vec::with_capacity(capacity),
+            on_raw_send: None,
+            serializer: Serializer::Json,
+            dedupe_subscriptions: false,
+            subscription_refs: HashMap::new(),
         }
     }
 
@@ -179,9 +272,8 @@ impl Context {
     ///
     /// ## Example
     /// ```
-    /// use wamp::client::context::Context;
-    /// use wamp::hello;
-    /// use wamp::core::messages::Hello;
+    /// use wamp_client::sync::context::Context;
+    /// use wamp_core::hello;
     ///
     /// // Create a context with no socket
     /// let mut ctx = Context::new(None);
@@ -193,14 +285,48 @@ impl Context {
     where
         Error: From<<T as TryInto<Message>>::Error>,
     {
+        let message = self.serializer.encode(message.try_into()?)?;
         if let Some(socket) = &self.socket {
+            call_raw_hook(&self.on_raw_send, &message);
             let socket = &mut *socket.lock().unwrap();
-            Ok(socket.send(message.try_into()?)?)
+            Ok(socket.send(message)?)
         } else {
-            Ok(self.messages.push(message.try_into()?))
+            Ok(self.messages.push(message))
         }
     }
 
+    /// Share the client's raw send hook with a context handed to a callback.
+    pub(crate) fn with_raw_send(mut self, on_raw_send: Option<RawHook>) -> Self {
+        self.on_raw_send = on_raw_send;
+        self
+    }
+
+    /// Send frames in the serialization negotiated by the client.
+    pub(crate) fn with_serializer(mut self, serializer: Serializer) -> Self {
+        self.serializer = serializer;
+        self
+    }
+
+    /// # Context Drain Messages
+    /// Take every message buffered by a context with no socket, in the order they were sent.
+    ///
+    /// ## Examples
+    /// ```
+    /// use wamp_client::sync::context::Context;
+    /// use wamp_core::{call, publish, subscribe};
+    ///
+    /// let mut context = Context::new(None);
+    /// context.send(call!("procedure")).unwrap();
+    /// context.send(publish!("topic")).unwrap();
+    /// context.send(subscribe!("topic")).unwrap();
+    ///
+    /// assert_eq!(context.drain_messages().len(), 3);
+    /// assert!(context.drain_messages().is_empty());
+    /// ```
+    pub fn drain_messages(&mut self) -> Vec<Message> {
+        std::mem::take(&mut self.messages)
+    }
+
     create_find_by_error_method!(
         find_by_error_unsubscribe,
         Unsubscribe,
@@ -230,9 +356,8 @@ impl Context {
         ///
         /// ## Examples
         /// ```
-        /// use wamp::core::messages::Register;
-        /// use wamp::client::context::Context;
-        /// use wamp::register;
+        /// use wamp_client::sync::context::Context;
+        /// use wamp_core::register;
         ///
         /// // Construct a context with no socket
         /// let mut context = Context::new(None);
@@ -257,20 +382,19 @@ impl Context {
         /// 
         /// ## Examples
         /// ```
-        /// use wamp::core::messages::Unregister;
-        /// use wamp::client::context::Context;
-        /// use wamp::unregister;
-        /// 
+        /// use wamp_client::sync::context::Context;
+        /// use wamp_core::unregister;
+        ///
         /// // Construct a context with no socket
         /// let mut context = Context::new(None);
-        /// 
+        ///
         /// // Dont forget to send your unregister message with the callback registration!
         /// context.unregister(unregister!(2), Box::new(|ctx, unregistered| {
         ///     // This never happens in this test, but if it did it would allow you to access the values returned.
         ///     // You must always return the created context object
         ///     ctx
         /// })).unwrap();
-        /// 
+        ///
         /// ```
         {
             unregister,
@@ -285,19 +409,18 @@ impl Context {
         /// 
         /// ## Examples
         /// ```
-        /// use wamp::core::messages::Subscribe;
-        /// use wamp::client::context::Context;
-        /// use wamp::subscribe;
-        /// 
+        /// use wamp_client::sync::context::Context;
+        /// use wamp_core::subscribe;
+        ///
         /// // Construct a context with no socket
         /// let mut context = Context::new(None);
-        /// 
+        ///
         /// // Dont forget to send your unsubscribe message with the callback registration!
         /// context.subscribe(subscribe!("topic"), Box::new(|mut ctx, subscribed| {
         ///     // This never happens in this test, but if it did it would allow you to access the values returned.
         ///     // You must always return the created context object
-        ///     
-        ///     // Using this mutable conext object you can now register your event listener 
+        ///
+        ///     // Using this mutable conext object you can now register your event listener
         ///     // We unwrap the subscribed result, since it can be an error
         ///     ctx.event(subscribed.unwrap(), Box::new(|ctx, event| {
         ///         // This callback also never happens but would be passed back to the client context to be used
@@ -316,75 +439,265 @@ impl Context {
         }
     );
 
-    create_push_methods!(
-        /// # Context Unsubscribe
-        /// Method that allows for unsubscribing easily with a callback to the wamp client.
-        /// 
-        /// ## Examples
-        /// ```
-        /// use wamp::core::messages::Unsubscribe;
-        /// use wamp::client::context::Context;
-        /// use wamp::unsubscribe;
-        /// 
-        /// // Construct a context with no socket
-        /// let mut context = Context::new(None);
-        /// 
-        /// // Dont forget to send your unsubscribe message with the callback registration!
-        /// context.unsubscribe(unsubscribe!(1), Box::new(|ctx, unsubscribed| {
-        ///     // This never happens in this test, but if it did it would allow you to access the values returned.
-        ///     // You must always return the created context object
-        ///     ctx
-        /// })).unwrap();
-        /// ```
+    /// # Context Unsubscribe
+    /// Method that allows for unsubscribing easily with a callback to the wamp client.
+    ///
+    /// A subscription shared by subscribes deduped with `Context::set_dedupe_subscriptions`
+    /// is only unsubscribed from by the last of them. See there.
+    /// 
+    /// ## Examples
+    /// ```
+    /// use wamp_client::sync::context::Context;
+    /// use wamp_core::unsubscribe;
+    ///
+    /// // Construct a context with no socket
+    /// let mut context = Context::new(None);
+    ///
+    /// // Dont forget to send your unsubscribe message with the callback registration!
+    /// context.unsubscribe(unsubscribe!(1), Box::new(|ctx, unsubscribed| {
+    ///     // This never happens in this test, but if it did it would allow you to access the values returned.
+    ///     // You must always return the created context object
+    ///     ctx
+    /// })).unwrap();
+    /// ```
+    pub fn unsubscribe(
+        &mut self,
+        unsubscribe: Unsubscribe,
+        mut callback: CallBackResult<Unsubscribed>,
+    ) -> Result<(), Error> {
+        let shared = self
+            .active_subscriptions
+            .iter()
+            .find(|(_, subscribed)| subscribed.subscription == unsubscribe.subscription)
+            .map(|(subscribe, _)| subscribe.request_id);
+        if let Some(refs) = shared.and_then(|request_id| self.subscription_refs.get_mut(&request_id)) {
+            if *refs > 0 {
+                *refs -= 1;
+                let unsubscribed = Unsubscribed {
+                    request_id: unsubscribe.request_id,
+                };
+                let context = callback(self.child(), Ok(unsubscribed));
+                self.extend(context);
+                return Ok(());
+            }
+        }
+
+        // A second pending request with the same id would make its reply ambiguous.
+        if self
+            .unsubscriptions
+            .iter()
+            .any(|(pending, _)| pending.request_id == unsubscribe.request_id)
         {
-            unsubscribe,
-            unsubscriptions,
-            Unsubscribe,
-            CallBackResult<Unsubscribed>
+            return Err(Error::DuplicateRequestId(unsubscribe.request_id));
         }
-    );
+        self.send(unsubscribe.clone())?;
+        Ok(self.unsubscriptions.push((unsubscribe, callback)))
+    }
 
-    create_push_methods!(
-        /// # Context Subscribe
-        /// Method that allows for subscribing to a topic with a callback.
-        /// 
-        /// ## Examples
-        /// ```
-        /// use wamp::core::messages::Subscribe;
-        /// use wamp::client::context::Context;
-        /// use wamp::subscribe;
-        /// 
-        /// // Construct a context with no socket
-        /// let mut context = Context::new(None);
-        /// 
-        /// // Dont forget to send your unsubscribe message with the callback registration!
-        /// context.subscribe(subscribe!("topic"), Box::new(|mut ctx, subscribed| {
-        ///     // This never happens in this test, but if it did it would allow you to access the values returned.
-        ///     // You must always return the created context object
-        /// 
-        ///     ctx.event(subscribed.unwrap(), Box::new(|ctx, event| {
-        ///         ctx // Always return context :)
-        ///     })).unwrap();
-        /// 
-        ///     ctx
-        /// })).unwrap();
-        /// ```
+    /// # Context Subscribe
+    /// Method that allows for subscribing to a topic with a callback.
+    ///
+    /// With `Context::set_dedupe_subscriptions` on, subscribing to a topic this context
+    /// already subscribed to, with the same options, sends nothing and shares the existing
+    /// subscription. See there for how the callback is answered.
+    /// 
+    /// ## Examples
+    /// ```
+    /// use wamp_client::sync::context::Context;
+    /// use wamp_core::subscribe;
+    ///
+    /// // Construct a context with no socket
+    /// let mut context = Context::new(None);
+    ///
+    /// // Dont forget to send your unsubscribe message with the callback registration!
+    /// context.subscribe(subscribe!("topic"), Box::new(|mut ctx, subscribed| {
+    ///     // This never happens in this test, but if it did it would allow you to access the values returned.
+    ///     // You must always return the created context object
+    ///
+    ///     ctx.event(subscribed.unwrap(), Box::new(|ctx, event| {
+    ///         ctx // Always return context :)
+    ///     })).unwrap();
+    ///
+    ///     ctx
+    /// })).unwrap();
+    /// ```
+    pub fn subscribe(
+        &mut self,
+        subscribe: Subscribe,
+        callback: CallBackResult<Subscribed>,
+    ) -> Result<(), Error> {
+        if self.dedupe_subscriptions {
+            return self.subscribe_deduped(subscribe, callback);
+        }
+        self.subscribe_new(subscribe, callback)
+    }
+
+    fn subscribe_new(
+        &mut self,
+        subscribe: Subscribe,
+        callback: CallBackResult<Subscribed>,
+    ) -> Result<(), Error> {
+        // A second pending request with the same id would make its reply ambiguous.
+        if self
+            .subscriptions
+            .iter()
+            .any(|(pending, _)| pending.request_id == subscribe.request_id)
         {
-            subscribe,
-            subscriptions,
-            Subscribe,
-            CallBackResult<Subscribed>
+            return Err(Error::DuplicateRequestId(subscribe.request_id));
         }
-    );
+        self.send(subscribe.clone())?;
+        Ok(self.subscriptions.push((subscribe, callback)))
+    }
+
+    /// # Context Set Dedupe Subscriptions
+    /// Opt in to sharing one router subscription between subscribes to the same topic with the
+    /// same options, instead of sending a `Subscribe` for each and getting every event twice.
+    ///
+    /// A subscribe matching an active subscription has its callback called right away with the
+    /// existing `Subscribed`. One matching a subscribe still waiting for its reply has its
+    /// callback called after the first one, with the same reply. Event listeners attached to a
+    /// shared subscription are each called with every event.
+    ///
+    /// Subscribes sharing a subscription are counted, so `unsubscribe` answers right away
+    /// without sending anything until the last of them unsubscribes. The event listeners of a
+    /// shared subscription stay attached until then.
+    ///
+    /// Only subscriptions made through this context are compared. Set it on `Client::context`
+    /// to dedupe the client's own subscribes.
+    ///
+    /// ## Examples
+    /// ```
+    /// use wamp_client::sync::context::Context;
+    /// use wamp_core::subscribe;
+    ///
+    /// let mut context = Context::new(None);
+    /// context.set_dedupe_subscriptions(true);
+    ///
+    /// context.subscribe(subscribe!("com.example.topic"), Box::new(|ctx, _| ctx)).unwrap();
+    /// context.subscribe(subscribe!("com.example.topic"), Box::new(|ctx, _| ctx)).unwrap();
+    ///
+    /// assert_eq!(context.drain_messages().len(), 1);
+    /// assert_eq!(context.pending_subscriptions(), 1);
+    /// ```
+    ///
+    /// Against a mock router, both listeners get the event and only the second unsubscribe is
+    /// sent:
+    /// ```
+    /// use std::cell::Cell;
+    /// use std::rc::Rc;
+    /// use wamp_client::sync::client::Client;
+    /// use wamp_client::sync::WampRequest;
+    /// use wamp_client::test_util::{establish, MockRouter};
+    /// use wamp_core::serde_json::json;
+    /// use wamp_core::tungstenite::Message;
+    /// use wamp_core::{subscribe, unsubscribe};
+    ///
+    /// let (uri, router) = MockRouter::spawn(move |router| {
+    ///     router.welcome();
+    ///
+    ///     let subscribe = router.recv();
+    ///     router.send(json!([33, subscribe[1], 5]));
+    ///     router.send(r#"[36,5,1,{},[]]"#);
+    ///
+    ///     let unsubscribe = router.recv();
+    ///     assert_eq!(unsubscribe[0], 34);
+    ///     assert_eq!(unsubscribe[2], 5);
+    ///     router.send(json!([35, unsubscribe[1]]));
+    ///     while let Ok(message) = router.socket().read() {
+    ///         assert!(!matches!(message, Message::Text(_)));
+    ///     }
+    /// });
+    ///
+    /// let (mut client, _) = Client::connect(WampRequest { uri, protocol: "wamp.2.json" }).unwrap();
+    /// establish(&mut client);
+    ///
+    /// client.context.set_dedupe_subscriptions(true);
+    /// let events = Rc::new(Cell::new(0));
+    /// for _ in 0..2 {
+    ///     let events = events.clone();
+    ///     client
+    ///         .subscribe(subscribe!("com.example.topic"), Box::new(move |mut ctx, subscribed| {
+    ///             let events = events.clone();
+    ///             ctx.event(subscribed.unwrap(), Box::new(move |ctx, _| {
+    ///                 events.set(events.get() + 1);
+    ///                 ctx
+    ///             }))
+    ///             .unwrap();
+    ///             ctx
+    ///         }))
+    ///         .unwrap();
+    /// }
+    /// while events.get() < 2 {
+    ///     let message = client.read().unwrap();
+    ///     client.read_contexts(message).unwrap();
+    /// }
+    ///
+    /// client.unsubscribe(unsubscribe!(5), Box::new(|ctx, _| ctx)).unwrap();
+    /// assert!(client.context.is_subscribed(5));
+    /// client.unsubscribe(unsubscribe!(5), Box::new(|ctx, _| ctx)).unwrap();
+    /// while client.context.is_subscribed(5) {
+    ///     let message = client.read().unwrap();
+    ///     client.read_contexts(message).unwrap();
+    /// }
+    /// drop(client);
+    /// router.join().unwrap();
+    /// ```
+    pub fn set_dedupe_subscriptions(&mut self, dedupe: bool) {
+        self.dedupe_subscriptions = dedupe;
+    }
+
+    fn subscribe_deduped(
+        &mut self,
+        subscribe: Subscribe,
+        mut callback: CallBackResult<Subscribed>,
+    ) -> Result<(), Error> {
+        let same_subscription = |other: &Subscribe| {
+            other.topic == subscribe.topic && other.options == subscribe.options
+        };
+
+        if let Some((active, subscribed)) = self
+            .active_subscriptions
+            .iter()
+            .find(|(active, _)| same_subscription(active))
+        {
+            let subscribed = subscribed.clone();
+            *self.subscription_refs.entry(active.request_id).or_insert(0) += 1;
+            let context = callback(self.child(), Ok(subscribed));
+            self.extend(context);
+            return Ok(());
+        }
+
+        if let Some((sent, pending)) = self
+            .subscriptions
+            .iter_mut()
+            .find(|(pending, _)| same_subscription(pending))
+        {
+            *self.subscription_refs.entry(sent.request_id).or_insert(0) += 1;
+            let mut first = std::mem::replace(pending, Box::new(|ctx, _| ctx));
+            *pending = Box::new(move |ctx, result| {
+                let ctx = first(ctx, result.clone());
+                callback(ctx, result)
+            });
+            return Ok(());
+        }
+
+        self.subscribe_new(subscribe, callback)
+    }
+
+    /// A context sharing this one's socket, raw send hook and serializer.
+    fn child(&self) -> Context {
+        Context::new(self.socket.clone())
+            .with_raw_send(self.on_raw_send.clone())
+            .with_serializer(self.serializer)
+    }
     create_push_methods!(
         /// # Context Publish
         /// Method that allows for publishing easily with a callback to the wamp client.
         ///
         /// ## Examples
         /// ```
-        /// use wamp::core::messages::Publish;
-        /// use wamp::client::context::Context;
-        /// use wamp::publish;
+        /// use wamp_client::sync::context::Context;
+        /// use wamp_core::publish;
         ///
         /// // Construct a context with no socket
         /// let mut context = Context::new(None);
@@ -409,9 +722,8 @@ impl Context {
         ///
         /// ## Examples
         /// ```
-        /// use wamp::core::messages::Call;
-        /// use wamp::client::context::Context;
-        /// use wamp::call;
+        /// use wamp_client::sync::context::Context;
+        /// use wamp_core::call;
         ///
         /// // Construct a context with no socket
         /// let mut context = Context::new(None);
@@ -422,6 +734,15 @@ impl Context {
         ///     // You must always return the created context object
         ///     ctx
         /// })).unwrap();
+        ///
+        /// // Request ids must be unique among the calls still waiting for a result.
+        /// let mut first = call!("procedure");
+        /// first.request_id = 1;
+        /// let mut second = call!("procedure");
+        /// second.request_id = 1;
+        ///
+        /// context.call(first, Box::new(|ctx, _| ctx)).unwrap();
+        /// assert!(context.call(second, Box::new(|ctx, _| ctx)).is_err());
         /// ```
         {
             call, 
@@ -436,9 +757,8 @@ impl Context {
         ///
         /// ## Examples
         /// ```
-        /// use wamp::core::messages::{Register, Registered, Invocation};
-        /// use wamp::client::context::Context;
-        /// use wamp::register;
+        /// use wamp_client::sync::context::Context;
+        /// use wamp_core::register;
         ///
         /// // Construct a context with no socket
         /// let mut context = Context::new(None);
@@ -467,15 +787,16 @@ impl Context {
         ///
         /// ## Examples
         /// ```
-        /// use wamp::core::messages::Cancel;
-        /// use wamp::client::context::Context;
-        /// use wamp::cancel;
+        /// use wamp_client::sync::context::Context;
+        /// use wamp_core::messages::Cancel;
+        /// use wamp_core::serde_json::json;
         ///
         /// // Construct a context with no socket
         /// let mut context = Context::new(None);
         ///
         /// // Dont forget to send your cancel message with the callback registration!
-        /// context.cancel(cancel!(1), Box::new(|ctx, interrupt| {
+        /// let cancel = Cancel { request_id: 1, options: json!({}) };
+        /// context.cancel(cancel, Box::new(|ctx, interrupt| {
         ///     // This never happens in this test, but if it did it would allow you to access the values returned.
         ///     // You must always return the created context object
         ///     ctx
@@ -489,6 +810,45 @@ impl Context {
         }
     );
 
+    create_raw_options_methods!(
+        /// # Context Subscribe Raw Options
+        /// Subscribe to a topic with an options map sent exactly as given, bypassing the typed
+        /// options. Returns the request id of the `Subscribe`.
+        ///
+        /// ## Examples
+        /// ```
+        /// use wamp_client::sync::context::Context;
+        /// use wamp_core::serde_json::{from_str, json, Value};
+        /// use wamp_core::tungstenite::Message;
+        ///
+        /// let mut context = Context::new(None);
+        /// let options = json!({ "match": "wildcard", "x_experiment": [1, 2] });
+        /// context.subscribe_raw_options("a..c", options.clone(), Box::new(|ctx, _| ctx)).unwrap();
+        ///
+        /// let frame = match context.drain_messages().remove(0) {
+        ///     Message::Text(text) => from_str::<Value>(&text).unwrap(),
+        ///     _ => unreachable!(),
+        /// };
+        /// assert_eq!(frame[2], options);
+        /// ```
+        {subscribe_raw_options, subscribe, subscribe, CallBackResult<Subscribed>}
+    );
+    create_raw_options_methods!(
+        /// # Context Register Raw Options
+        /// Register a procedure with an options map sent exactly as given.
+        {register_raw_options, register, register, CallBackResult<Registered>}
+    );
+    create_raw_options_methods!(
+        /// # Context Publish Raw Options
+        /// Publish on a topic with an options map sent exactly as given.
+        {publish_raw_options, publish, publish, CallBackResult<Published>}
+    );
+    create_raw_options_methods!(
+        /// # Context Call Raw Options
+        /// Call a procedure with an options map sent exactly as given.
+        {call_raw_options, call, call, CallBackResult<WampResult>}
+    );
+
     create_find_methods!(
         find_register,
         registered,
@@ -520,8 +880,9 @@ impl Context {
     );
     create_find_methods!(find_publish, publish, publications, Publish, Published);
     create_find_methods!(find_call, call, calls, Call, WampResult);
+    // Invocations carry the router assigned registration id, not the id of the `Register` request.
     create_find_methods!(
-        find_invocation,
+        registration: find_invocation,
         invocation,
         invocations,
         Registered,
@@ -537,7 +898,734 @@ impl Context {
         self.subscriptions.extend(ctx.subscriptions);
         self.publications.extend(ctx.publications);
         self.calls.extend(ctx.calls);
+        self.call_deadlines.extend(ctx.call_deadlines);
         self.invocations.extend(ctx.invocations);
         self.messages.extend(ctx.messages);
+        self.cancelations.extend(ctx.cancelations);
+        self.active_subscriptions.extend(ctx.active_subscriptions);
+        self.active_registrations.extend(ctx.active_registrations);
+        for (request_id, refs) in ctx.subscription_refs {
+            *self.subscription_refs.entry(request_id).or_insert(0) += refs;
+        }
+    }
+
+    create_pending_methods!(
+        /// Number of `Register`s waiting for a `Registered`.
+        pending_registrations, registrations;
+        /// Number of `Unregister`s waiting for an `Unregistered`.
+        pending_unregistrations, unregistrations;
+        /// Number of `Subscribe`s waiting for a `Subscribed`.
+        pending_subscriptions, subscriptions;
+        /// Number of `Unsubscribe`s waiting for an `Unsubscribed`.
+        pending_unsubscriptions, unsubscriptions;
+        /// Number of acknowledged `Publish`es waiting for a `Published`.
+        pending_publications, publications;
+        /// Number of `Call`s waiting for their final `Result`.
+        pending_calls, calls;
+        /// Number of `Cancel`s waiting for the call to be interrupted.
+        pending_cancelations, cancelations;
+        /// Number of messages buffered by a context with no socket.
+        pending_messages, messages;
+    );
+
+    /// # Context Pending Total
+    /// Number of requests still waiting for a reply from the router, plus buffered messages.
+    ///
+    /// A count that keeps growing points at replies the router dropped. Event and invocation
+    /// listeners are not counted, since they stay until unsubscribing or unregistering.
+    ///
+    /// ## Examples
+    /// ```
+    /// use wamp_client::sync::context::Context;
+    /// use wamp_core::messages::Subscribed;
+    /// use wamp_core::{call, publish, subscribe};
+    ///
+    /// let mut context = Context::new(None);
+    /// for _ in 0..3 {
+    ///     context.call(call!("procedure"), Box::new(|ctx, _| ctx)).unwrap();
+    /// }
+    /// context.subscribe(subscribe!("topic"), Box::new(|ctx, _| ctx)).unwrap();
+    /// context.event(Subscribed { request_id: 1, subscription: 2 }, Box::new(|ctx, _| ctx)).unwrap();
+    /// context.send(publish!("topic")).unwrap();
+    ///
+    /// assert_eq!(context.pending_calls(), 3);
+    /// assert_eq!(context.pending_subscriptions(), 1);
+    /// // Every request above was buffered, since the context has no socket.
+    /// assert_eq!(context.pending_messages(), 5);
+    /// assert_eq!(context.pending_total(), 9);
+    /// ```
+    pub fn pending_total(&self) -> usize {
+        self.registrations.len()
+            + self.unregistrations.len()
+            + self.subscriptions.len()
+            + self.unsubscriptions.len()
+            + self.publications.len()
+            + self.calls.len()
+            + self.cancelations.len()
+            + self.messages.len()
+    }
+
+    /// # Context Cancel Pending
+    /// Drop the callback of a request still waiting for its reply, so the reply is ignored when
+    /// it arrives. Returns whether a pending request had that id.
+    ///
+    /// Nothing is sent to the router, use `PendingCall::cancel` to stop a call on the dealer too.
+    ///
+    /// ## Examples
+    /// ```
+    /// use wamp_client::sync::context::Context;
+    /// use wamp_core::call;
+    ///
+    /// let mut context = Context::new(None);
+    /// let call = call!("com.example.slow");
+    /// let request_id = call.request_id;
+    /// context.call(call, Box::new(|_, _| panic!("the result of a dropped call is ignored"))).unwrap();
+    ///
+    /// assert!(context.cancel_pending(request_id));
+    /// assert_eq!(context.pending_calls(), 0);
+    /// assert!(!context.cancel_pending(request_id));
+    /// ```
+    pub fn cancel_pending(&mut self, request_id: u64) -> bool {
+        let before = self.pending_total();
+        self.registrations
+            .retain(|(register, _)| register.request_id != request_id);
+        self.unregistrations
+            .retain(|(unregister, _)| unregister.request_id != request_id);
+        self.subscriptions
+            .retain(|(subscribe, _)| subscribe.request_id != request_id);
+        self.unsubscriptions
+            .retain(|(unsubscribe, _)| unsubscribe.request_id != request_id);
+        self.publications
+            .retain(|(publish, _)| publish.request_id != request_id);
+        self.calls.retain(|(call, _)| call.request_id != request_id);
+        self.call_deadlines
+            .retain(|(deadline_id, _)| *deadline_id != request_id);
+        self.cancelations
+            .retain(|(cancel, _)| cancel.request_id != request_id);
+        self.pending_total() != before
+    }
+
+    /// Find the call a progressive result belongs to, leaving it pending for the final result.
+    pub(crate) fn find_call_progress(
+        &mut self,
+        result: &WampResult,
+    ) -> Option<&mut (Call, CallBackResult<WampResult>)> {
+        self.calls
+            .iter_mut()
+            .find(|(call, _)| call.request_id == result.request_id)
+    }
+
+    /// # Context Call With Timeout
+    /// Call a procedure like `Context::call`, giving up on it after `timeout`.
+    ///
+    /// Once the deadline passes, the client event loop sends a `killnowait` `Cancel` for the call
+    /// and hands `callback` a `wamp.error.timeout` error, and a reply arriving later is ignored.
+    /// The event loop stops waiting for frames at the nearest deadline, so the timeout fires on
+    /// a silent connection too.
+    ///
+    /// ## Examples
+    /// A call the callee never answers, against a mock router that goes silent after the call:
+    /// ```
+    /// use std::cell::RefCell;
+    /// use std::rc::Rc;
+    /// use std::time::Duration;
+    /// use wamp_client::hello::HelloBuilder;
+    /// use wamp_client::sync::{client::Client, WampRequest};
+    /// use wamp_client::test_util::MockRouter;
+    /// use wamp_core::call;
+    /// use wamp_core::serde_json::json;
+    ///
+    /// let (uri, router) = MockRouter::spawn(move |router| {
+    ///     router.welcome();
+    ///
+    ///     let call = router.recv();
+    ///     let cancel = router.recv();
+    ///     assert_eq!(cancel, json!([49, call[1], { "mode": "killnowait" }]));
+    ///     router.send(r#"[6,{},"wamp.close.system_shutdown"]"#);
+    /// });
+    ///
+    /// let (mut client, _) = Client::connect(WampRequest { uri, protocol: "wamp.2.json" }).unwrap();
+    /// let failed = Rc::new(RefCell::new(None));
+    /// let failed2 = failed.clone();
+    /// client.on_welcome(Box::new(move |mut ctx, _| {
+    ///     let failed = failed2.clone();
+    ///     ctx.call_with_timeout(call!("com.example.dead"), Duration::from_millis(50), Box::new(move |ctx, result| {
+    ///         *failed.borrow_mut() = result.err().map(|error| error.error);
+    ///         ctx
+    ///     })).unwrap();
+    ///     ctx
+    /// }));
+    ///
+    /// client.send(HelloBuilder::new("realm1").build().unwrap()).unwrap();
+    /// client.event_loop().unwrap();
+    /// router.join().unwrap();
+    ///
+    /// assert_eq!(failed.borrow().as_deref(), Some("wamp.error.timeout"));
+    /// ```
+    pub fn call_with_timeout(
+        &mut self,
+        call: Call,
+        timeout: Duration,
+        callback: CallBackResult<WampResult>,
+    ) -> Result<(), Error> {
+        let request_id = call.request_id;
+        self.call(call, callback)?;
+        self.call_deadlines.push((request_id, Instant::now() + timeout));
+        Ok(())
+    }
+
+    /// The nearest deadline of the calls made with `Context::call_with_timeout`.
+    pub(crate) fn next_call_deadline(&self) -> Option<Instant> {
+        self.call_deadlines.iter().map(|(_, deadline)| *deadline).min()
+    }
+
+    /// Take the pending calls whose deadline passed by `now`. Deadlines of calls that were
+    /// already answered are dropped along the way.
+    pub(crate) fn take_expired_calls(
+        &mut self,
+        now: Instant,
+    ) -> Vec<(Call, CallBackResult<WampResult>)> {
+        let mut expired = vec![];
+        let calls = &mut self.calls;
+        self.call_deadlines.retain(|(request_id, deadline)| {
+            if *deadline > now {
+                return true;
+            }
+            if let Some(position) = calls.iter().position(|(call, _)| call.request_id == *request_id) {
+                expired.push(calls.remove(position));
+            }
+            false
+        });
+        expired
+    }
+
+    /// # Context Call Pending
+    /// Call a procedure like `Context::call`, returning a handle that cancels exactly this call
+    /// through the context it is given.
+    ///
+    /// ## Examples
+    /// ```
+    /// use wamp_client::options::CancelMode;
+    /// use wamp_client::sync::context::Context;
+    /// use wamp_core::call;
+    /// use wamp_core::serde_json::{from_str, json, Value};
+    /// use wamp_core::tungstenite::Message;
+    ///
+    /// let mut context = Context::new(None);
+    /// let call = call!("com.example.slow");
+    /// let request_id = call.request_id;
+    ///
+    /// let pending = context.call_pending(call, Box::new(|ctx, _result| ctx)).unwrap();
+    /// let cancel = pending.cancel(&mut context, CancelMode::Kill).unwrap();
+    /// assert_eq!(cancel.request_id, request_id);
+    ///
+    /// let frame = match context.drain_messages().pop().unwrap() {
+    ///     Message::Text(text) => from_str::<Value>(&text).unwrap(),
+    ///     _ => unreachable!(),
+    /// };
+    /// assert_eq!(frame, json!([49, request_id, { "mode": "kill" }]));
+    /// ```
+    pub fn call_pending(
+        &mut self,
+        call: Call,
+        callback: CallBackResult<WampResult>,
+    ) -> Result<PendingCall, Error> {
+        let request_id = call.request_id;
+        self.call(call, callback)?;
+        Ok(PendingCall { request_id })
+    }
+
+    /// # Context Call Cancellable
+    /// Call a procedure like `Context::call`, returning a `CallToken` to cancel exactly this call
+    /// later with `Context::cancel_call`.
+    ///
+    /// ## Examples
+    /// ```
+    /// use wamp_client::options::CancelMode;
+    /// use wamp_client::sync::context::Context;
+    /// use wamp_core::call;
+    /// use wamp_core::serde_json::{from_str, json, Value};
+    /// use wamp_core::tungstenite::Message;
+    ///
+    /// let mut context = Context::new(None);
+    /// let call = call!("com.example.slow");
+    /// let request_id = call.request_id;
+    ///
+    /// let token = context.call_cancellable(call, Box::new(|ctx, _result| ctx)).unwrap();
+    /// assert_eq!(token.request_id, request_id);
+    /// context.cancel_call(token, CancelMode::KillNoWait).unwrap();
+    ///
+    /// let frame = match context.drain_messages().pop().unwrap() {
+    ///     Message::Text(text) => from_str::<Value>(&text).unwrap(),
+    ///     _ => unreachable!(),
+    /// };
+    /// assert_eq!(frame, json!([49, request_id, { "mode": "killnowait" }]));
+    /// ```
+    pub fn call_cancellable(
+        &mut self,
+        call: Call,
+        callback: CallBackResult<WampResult>,
+    ) -> Result<CallToken, Error> {
+        let request_id = call.request_id;
+        self.call(call, callback)?;
+        Ok(CallToken { request_id })
+    }
+
+    /// # Context Cancel Call
+    /// Send a `Cancel` for the call `token` was returned for, with the given mode, returning the
+    /// `Cancel` that was sent.
+    ///
+    /// The dealer answers a canceled call with an `Error`, which is delivered to the call
+    /// callback. With `CancelMode::Skip` the callee keeps running, and with `CancelMode::Kill` the
+    /// dealer waits for it to stop before answering.
+    pub fn cancel_call(&mut self, token: CallToken, mode: CancelMode) -> Result<Cancel, Error> {
+        let cancel = Cancel {
+            request_id: token.request_id,
+            options: json!({ "mode": mode }),
+        };
+        self.send(cancel.clone())?;
+        Ok(cancel)
+    }
+
+    /// # Context Publish With Sent
+    /// Publish like `Context::publish`, with a local `on_sent` signal for best effort publishes.
+    ///
+    /// When `acknowledge` is not set the router never answers, so `callback` is dropped and
+    /// `on_sent` is called with the publish right after its frame is written to the socket. With
+    /// `acknowledge` set this is `Context::publish`, and `on_sent` is not called.
+    ///
+    /// ## Examples
+    /// ```
+    /// use std::cell::Cell;
+    /// use std::rc::Rc;
+    /// use wamp_client::sync::context::Context;
+    /// use wamp_core::publish;
+    ///
+    /// let mut context = Context::new(None);
+    /// let sent = Rc::new(Cell::new(false));
+    /// let sent2 = sent.clone();
+    ///
+    /// context
+    ///     .publish_with_sent(publish!("topic"), Box::new(|ctx, _| ctx), move |_| sent2.set(true))
+    ///     .unwrap();
+    /// assert!(sent.get());
+    /// ```
+    pub fn publish_with_sent(
+        &mut self,
+        publish: Publish,
+        callback: CallBackResult<Published>,
+        on_sent: impl FnOnce(&Publish),
+    ) -> Result<(), Error> {
+        if publish.options.get("acknowledge").and_then(Value::as_bool) == Some(true) {
+            return self.publish(publish, callback);
+        }
+        self.send(publish.clone())?;
+        on_sent(&publish);
+        Ok(())
+    }
+
+    /// # Context Publish With Options
+    /// Publish with typed options, which replace the options of `publish`. Use them to narrow
+    /// the receivers with `exclude`, `exclude_authid`, `eligible` and friends, or to ask for
+    /// `disclose_me`.
+    ///
+    /// With `acknowledge` set the router answers with a `Published`, or an `Error` such as
+    /// `wamp.error.not_authorized`, and `callback` is called with it once it arrives. Otherwise the
+    /// router never answers, so `callback` is called with `Ok(None)` as soon as the frame is
+    /// written to the socket.
+    ///
+    /// ## Examples
+    /// ```
+    /// use std::cell::RefCell;
+    /// use std::rc::Rc;
+    /// use wamp_client::options::PublishOptions;
+    /// use wamp_client::sync::context::Context;
+    /// use wamp_core::publish;
+    /// use wamp_core::serde_json::{from_str, json, Value};
+    /// use wamp_core::tungstenite::Message;
+    ///
+    /// let mut context = Context::new(None);
+    /// let options = PublishOptions {
+    ///     exclude: Some(vec![7]),
+    ///     exclude_authid: Some(vec!["bob".to_string()]),
+    ///     eligible: Some(vec![8, 9]),
+    ///     disclose_me: Some(true),
+    ///     ..Default::default()
+    /// };
+    ///
+    /// let replies = Rc::new(RefCell::new(vec![]));
+    /// let replies2 = replies.clone();
+    /// context
+    ///     .publish_with_options(publish!("topic"), options, Box::new(move |ctx, published| {
+    ///         replies2.borrow_mut().push(published.unwrap());
+    ///         ctx
+    ///     }))
+    ///     .unwrap();
+    ///
+    /// // Not acknowledged, so the callback already ran and nothing waits for a `Published`.
+    /// assert_eq!(*replies.borrow(), vec![None]);
+    /// assert_eq!(context.pending_publications(), 0);
+    ///
+    /// let frame = match context.drain_messages().remove(0) {
+    ///     Message::Text(text) => from_str::<Value>(&text).unwrap(),
+    ///     _ => unreachable!(),
+    /// };
+    /// assert_eq!(
+    ///     frame[2],
+    ///     json!({ "exclude": [7], "exclude_authid": ["bob"], "eligible": [8, 9], "disclose_me": true })
+    /// );
+    ///
+    /// let acknowledged = PublishOptions { acknowledge: Some(true), ..Default::default() };
+    /// context.publish_with_options(publish!("topic"), acknowledged, Box::new(|ctx, _| ctx)).unwrap();
+    /// assert_eq!(context.pending_publications(), 1);
+    /// ```
+    pub fn publish_with_options(
+        &mut self,
+        mut publish: Publish,
+        options: PublishOptions,
+        mut callback: CallBackResult<Option<Published>>,
+    ) -> Result<(), Error> {
+        publish.options = options.to_value();
+        if options.acknowledge == Some(true) {
+            return self.publish(
+                publish,
+                Box::new(move |ctx, published| callback(ctx, published.map(Some))),
+            );
+        }
+        self.send(publish)?;
+        let context = callback(self.child(), Ok(None));
+        self.extend(context);
+        Ok(())
+    }
+
+    /// # Context Register With Options
+    /// Register a procedure with typed options, which replace the options of `register`, such as
+    /// the `invoke` policy of a shared registration or a `match` pattern.
+    ///
+    /// Every callee of a shared registration has to ask for the same `invoke` policy. The router
+    /// refuses a registration whose policy differs from the existing one, or any shared
+    /// registration if it does not support them, and `callback` is then called with the `Error`,
+    /// usually `wamp.error.procedure_already_exists`.
+    ///
+    /// ## Examples
+    /// ```
+    /// use wamp_client::options::{InvokePolicy, RegisterOptions};
+    /// use wamp_client::sync::context::Context;
+    /// use wamp_core::register;
+    /// use wamp_core::serde_json::{from_str, json, Value};
+    /// use wamp_core::tungstenite::Message;
+    ///
+    /// let mut context = Context::new(None);
+    /// let options = RegisterOptions::shared(InvokePolicy::RoundRobin);
+    /// context
+    ///     .register_with_options(register!("com.example.add"), options, Box::new(|ctx, _| ctx))
+    ///     .unwrap();
+    ///
+    /// let frame = match context.drain_messages().remove(0) {
+    ///     Message::Text(text) => from_str::<Value>(&text).unwrap(),
+    ///     _ => unreachable!(),
+    /// };
+    /// assert_eq!(frame[2], json!({ "invoke": "roundrobin" }));
+    /// assert_eq!(frame[3], "com.example.add");
+    /// ```
+    pub fn register_with_options(
+        &mut self,
+        mut register: Register,
+        options: RegisterOptions,
+        callback: CallBackResult<Registered>,
+    ) -> Result<(), Error> {
+        register.options = options.to_value();
+        self.register(register, callback)
+    }
+
+    /// # Context Invocation Error
+    /// Answer `invocation` with an `Error` instead of a `Yield`, for a procedure that failed.
+    ///
+    /// The frame is keyed by the invocation's request id with the `INVOCATION` type, and carries
+    /// `args` and `kwargs` as the error payload. Pass `Value::Null` for either to leave it empty.
+    ///
+    /// ## Examples
+    /// ```
+    /// use wamp_client::sync::context::Context;
+    /// use wamp_core::messages::Messages;
+    /// use wamp_core::serde_json::{from_str, json, Value};
+    /// use wamp_core::tungstenite::Message;
+    ///
+    /// let invocation = match from_str(r#"[68,42,7,{},[1,"two"]]"#) {
+    ///     Ok(Messages::Invocation(invocation)) => invocation,
+    ///     _ => unreachable!(),
+    /// };
+    ///
+    /// let mut context = Context::new(None);
+    /// context
+    ///     .invocation_error(&invocation, "com.example.error.bad_input", json!(["two"]), json!({ "expected": "number" }))
+    ///     .unwrap();
+    /// context.invocation_error(&invocation, "wamp.error.canceled", Value::Null, Value::Null).unwrap();
+    ///
+    /// let frames: Vec<Value> = context
+    ///     .drain_messages()
+    ///     .into_iter()
+    ///     .map(|message| match message {
+    ///         Message::Text(text) => from_str(&text).unwrap(),
+    ///         _ => unreachable!(),
+    ///     })
+    ///     .collect();
+    ///
+    /// assert_eq!(frames[0], json!([8, 68, 42, {}, "com.example.error.bad_input", ["two"], { "expected": "number" }]));
+    ///
+    /// // An empty payload is either left out of the frame or sent empty.
+    /// assert_eq!(frames[1].as_array().unwrap()[..5], json!([8, 68, 42, {}, "wamp.error.canceled"]).as_array().unwrap()[..]);
+    /// assert!(frames[1].get(5).map_or(true, |args| args == &json!([])));
+    /// ```
+    pub fn invocation_error<E: ToString>(
+        &mut self,
+        invocation: &Invocation,
+        error: E,
+        args: Value,
+        kwargs: Value,
+    ) -> Result<(), Error> {
+        let mut error = wamp_error(WampErrorEvent::Invocation, invocation.request_id, error);
+        if !args.is_null() {
+            error.args = args;
+        }
+        if !kwargs.is_null() {
+            error.kwargs = kwargs;
+        }
+        self.send(error)
+    }
+
+    /// # Context Call Correlated
+    /// Call a procedure like `Context::call`, handing a failed call's callback the `Call` it
+    /// answers along with the error, so retry logic can inspect and resend it.
+    ///
+    /// ## Examples
+    /// ```
+    /// use std::cell::RefCell;
+    /// use std::rc::Rc;
+    /// use wamp_client::messages::wamp_error;
+    /// use wamp_client::sync::context::Context;
+    /// use wamp_core::call;
+    /// use wamp_core::messages::WampErrorEvent;
+    /// use wamp_core::serde_json::json;
+    ///
+    /// let mut context = Context::new(None);
+    /// let mut call = call!("com.example.flaky");
+    /// call.args = json!([1, 2]);
+    /// let request_id = call.request_id;
+    ///
+    /// let failed = Rc::new(RefCell::new(None));
+    /// let failed2 = failed.clone();
+    /// context.call_correlated(call, Box::new(move |ctx, result| {
+    ///     *failed2.borrow_mut() = result.err();
+    ///     ctx
+    /// })).unwrap();
+    ///
+    /// // Deliver the error the way the client routes it.
+    /// let error = wamp_error(WampErrorEvent::Call, request_id, "wamp.error.unavailable");
+    /// let (_, mut callback) = context.find_by_error_call(&error).unwrap();
+    /// callback(Context::new(None), Err(error));
+    ///
+    /// let failure = failed.borrow_mut().take().unwrap();
+    /// assert_eq!(failure.error.error, "wamp.error.unavailable");
+    /// assert_eq!(failure.call.procedure, "com.example.flaky");
+    /// assert_eq!(failure.call.args, json!([1, 2]));
+    /// ```
+    pub fn call_correlated(
+        &mut self,
+        call: Call,
+        mut callback: CallBack<Result<WampResult, CallFailure>>,
+    ) -> Result<(), Error> {
+        let sent = call.clone();
+        self.call(
+            call,
+            Box::new(move |ctx, result| {
+                callback(
+                    ctx,
+                    result.map_err(|error| CallFailure {
+                        call: sent.clone(),
+                        error,
+                    }),
+                )
+            }),
+        )
+    }
+
+    /// # Context Replace Event Callback
+    /// Swap the event listener of a subscription in place, keeping the subscription active on the
+    /// router. Returns `Error::NoSubscription` if no listener is attached to the subscription.
+    ///
+    /// ## Examples
+    /// ```
+    /// use std::cell::RefCell;
+    /// use std::rc::Rc;
+    /// use wamp_client::sync::{client::Client, WampRequest};
+    /// use wamp_client::test_util::MockRouter;
+    /// use wamp_core::messages::{Messages, Subscribed};
+    /// use wamp_core::serde_json::{from_str, json};
+    ///
+    /// let uri = MockRouter::silent();
+    /// let (mut client, _) = Client::connect(WampRequest { uri, protocol: "wamp.2.json" }).unwrap();
+    /// let seen = Rc::new(RefCell::new(vec![]));
+    /// let (original, reloaded) = (seen.clone(), seen.clone());
+    ///
+    /// let subscribed = Subscribed { request_id: 1, subscription: 2 };
+    /// client.context.event(subscribed, Box::new(move |ctx, event| {
+    ///     original.borrow_mut().push(("original", event.args));
+    ///     ctx
+    /// })).unwrap();
+    /// client.context.replace_event_callback(2, Box::new(move |ctx, event| {
+    ///     reloaded.borrow_mut().push(("reloaded", event.args));
+    ///     ctx
+    /// })).unwrap();
+    ///
+    /// let event: Messages = from_str(r#"[36,2,1,{},["a"]]"#).unwrap();
+    /// client.read_contexts(Some(event)).unwrap();
+    /// assert_eq!(*seen.borrow(), vec![("reloaded", json!(["a"]))]);
+    ///
+    /// assert!(client.context.replace_event_callback(3, Box::new(|ctx, _| ctx)).is_err());
+    /// ```
+    pub fn replace_event_callback(
+        &mut self,
+        subscription: u64,
+        callback: CallBack<Event>,
+    ) -> Result<(), Error> {
+        match self
+            .events
+            .iter_mut()
+            .find(|(subscribed, _)| subscribed.subscription == subscription)
+        {
+            Some((_, current)) => {
+                *current = callback;
+                Ok(())
+            }
+            None => Err(Error::NoSubscription),
+        }
+    }
+
+    /// # Context Match Policy
+    /// How an active subscription matches topics, read from the options of the `Subscribe` it
+    /// was confirmed for, since `Subscribed` itself carries no details.
+    ///
+    /// ## Examples
+    /// ```
+    /// use wamp_client::options::MatchPolicy;
+    /// use wamp_client::sync::context::Context;
+    ///
+    /// let context = Context::new(None);
+    /// assert_eq!(context.match_policy(1), None::<MatchPolicy>);
+    /// ```
+    pub fn match_policy(&self, subscription: u64) -> Option<MatchPolicy> {
+        self.active_subscriptions
+            .iter()
+            .find(|(_, subscribed)| subscribed.subscription == subscription)
+            .map(|(subscribe, _)| {
+                SubscribeOptions::from_value(subscribe.options.clone())
+                    .ok()
+                    .and_then(|options| options.match_policy)
+                    .unwrap_or(MatchPolicy::Exact)
+            })
+    }
+
+    /// Whether the router has confirmed the subscription id with a `Subscribed`.
+    pub fn is_subscribed(&self, subscription: u64) -> bool {
+        self.active_subscriptions
+            .iter()
+            .any(|(_, subscribed)| subscribed.subscription == subscription)
+            || self
+                .events
+                .iter()
+                .any(|(subscribed, _)| subscribed.subscription == subscription)
+    }
+
+    /// Remove every entry tied to a subscription id once it has been unsubscribed.
+    ///
+    /// Each vec is cleaned up independently, so entries are not leaked when the event listener
+    /// was never attached or the `Subscribed` was handled out of order.
+    pub(crate) fn remove_subscription(&mut self, subscription: u64) {
+        let request_ids: Vec<u64> = self
+            .active_subscriptions
+            .iter()
+            .map(|(_, subscribed)| subscribed)
+            .chain(self.events.iter().map(|(subscribed, _)| subscribed))
+            .filter(|subscribed| subscribed.subscription == subscription)
+            .map(|subscribed| subscribed.request_id)
+            .collect();
+
+        self.unsubscriptions
+            .retain(|(unsubscribe, _)| unsubscribe.subscription != subscription);
+        self.events
+            .retain(|(subscribed, _)| subscribed.subscription != subscription);
+        self.active_subscriptions
+            .retain(|(_, subscribed)| subscribed.subscription != subscription);
+        self.subscriptions
+            .retain(|(subscribe, _)| !request_ids.contains(&subscribe.request_id));
+        for request_id in &request_ids {
+            self.subscription_refs.remove(request_id);
+        }
+    }
+
+    /// Remove every entry tied to a registration id once it has been unregistered.
+    pub(crate) fn remove_registration(&mut self, registration: u64) {
+        let request_ids: Vec<u64> = self
+            .active_registrations
+            .iter()
+            .map(|(_, registered)| registered)
+            .chain(self.invocations.iter().map(|(registered, _)| registered))
+            .filter(|registered| registered.registration == registration)
+            .map(|registered| registered.request_id)
+            .collect();
+
+        self.unregistrations
+            .retain(|(unregister, _)| unregister.registration != registration);
+        self.invocations
+            .retain(|(registered, _)| registered.registration != registration);
+        self.active_registrations
+            .retain(|(_, registered)| registered.registration != registration);
+        self.registrations
+            .retain(|(register, _)| !request_ids.contains(&register.request_id));
+    }
+}
+
+/// A failed call, with the `Call` the error answers. See `Context::call_correlated`.
+#[derive(Debug, Clone)]
+pub struct CallFailure {
+    pub call: Call,
+    pub error: WampError,
+}
+
+/// # Call Token
+/// The request id of a call sent with `Context::call_cancellable`, to cancel it with
+/// `Context::cancel_call` without correlating ids by hand.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct CallToken {
+    pub request_id: u64,
+}
+
+/// # Pending Call
+/// Handle to a call sent with `Context::call_pending` or `Client::call_pending`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct PendingCall {
+    request_id: u64,
+}
+
+impl PendingCall {
+    /// Request id of the call.
+    pub fn request_id(&self) -> u64 {
+        self.request_id
+    }
+
+    /// Send a `Cancel` for the call on `context` with the given mode, returning the `Cancel`
+    /// that was sent. Pass the context the call was made on, `Client::context` for a call made
+    /// with `Client::call_pending`, so the frame reaches its socket or message buffer.
+    ///
+    /// The dealer answers a canceled call with an `Error`, which is delivered to the call
+    /// callback.
+    pub fn cancel(&self, context: &mut Context, mode: CancelMode) -> Result<Cancel, Error> {
+        context.cancel_call(
+            CallToken {
+                request_id: self.request_id,
+            },
+            mode,
+        )
     }
 }
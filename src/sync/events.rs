@@ -0,0 +1,142 @@
+use std::cell::{Cell, RefCell};
+use std::collections::VecDeque;
+use std::convert::TryInto;
+use std::rc::Rc;
+use wamp_core::messages::{Event, Subscribed};
+use wamp_core::tungstenite::Message;
+use wamp_core::unsubscribe;
+
+use crate::error::Error;
+
+use super::client::Client;
+
+/// # Event Iterator
+/// Blocking iterator over the events of one subscription, returned by `Client::events_iter`.
+///
+/// Each call to `next` drives `Client::read` until an event for the subscription arrives. Other
+/// frames read meanwhile are routed to their callbacks as usual. The iterator ends when the
+/// socket is closed.
+///
+/// Events read while waiting, before `next` takes them, count towards the client's byte budget.
+/// See `Client::set_byte_budget`.
+///
+/// Dropping the iterator removes its event listener and sends an `Unsubscribe` for the
+/// subscription.
+///
+/// ## Examples
+/// ```
+/// use wamp_client::sync::client::Client;
+/// use wamp_client::sync::WampRequest;
+/// use wamp_client::test_util::{establish, MockRouter};
+/// use wamp_core::serde_json::json;
+///
+/// let (uri, router) = MockRouter::spawn(move |router| {
+///     router.welcome();
+///
+///     let subscribe = router.recv();
+///     router.send(json!([33, subscribe[1], 5]));
+///     router.send(r#"[36,5,1,{},["a"]]"#);
+///
+///     let unsubscribe = router.recv();
+///     assert_eq!(unsubscribe[0], 34);
+///     assert_eq!(unsubscribe[2], 5);
+///     router.send(json!([35, unsubscribe[1]]));
+/// });
+///
+/// let (mut client, _) = Client::connect(WampRequest { uri, protocol: "wamp.2.json" }).unwrap();
+/// establish(&mut client);
+///
+/// let mut events = client.events_iter("topic").unwrap();
+/// assert_eq!(events.next().unwrap().unwrap().args, json!(["a"]));
+/// drop(events);
+///
+/// while client.context.is_subscribed(5) {
+///     let message = client.read().unwrap();
+///     client.read_contexts(message).unwrap();
+/// }
+/// drop(client);
+/// router.join().unwrap();
+/// ```
+pub struct EventIterator<'a> {
+    client: &'a mut Client,
+    subscribed: Subscribed,
+    events: Rc<RefCell<VecDeque<(usize, Event)>>>,
+    read_ahead_bytes: Rc<Cell<usize>>,
+}
+
+impl<'a> EventIterator<'a> {
+    pub(crate) fn new(client: &'a mut Client, subscribed: Subscribed) -> Result<Self, Error> {
+        let events = Rc::new(RefCell::new(VecDeque::new()));
+        let events2 = events.clone();
+        let read_ahead_bytes = client.read_ahead_bytes();
+        let read_ahead_bytes2 = read_ahead_bytes.clone();
+        client.event(
+            subscribed.clone(),
+            Box::new(move |ctx, event| {
+                let frame: Result<Message, _> = event.clone().try_into();
+                let size = frame.map(|frame| frame.len()).unwrap_or(0);
+                read_ahead_bytes2.set(read_ahead_bytes2.get() + size);
+                events2.borrow_mut().push_back((size, event));
+                ctx
+            }),
+        )?;
+        Ok(Self {
+            client,
+            subscribed,
+            events,
+            read_ahead_bytes,
+        })
+    }
+
+    /// The subscription the iterator yields events for.
+    pub fn subscribed(&self) -> &Subscribed {
+        &self.subscribed
+    }
+}
+
+impl<'a> Iterator for EventIterator<'a> {
+    type Item = Result<Event, Error>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        loop {
+            if let Some((size, event)) = self.events.borrow_mut().pop_front() {
+                self.read_ahead_bytes.set(self.read_ahead_bytes.get() - size);
+                return Some(Ok(event));
+            }
+            if self.client.is_closed() {
+                return None;
+            }
+
+            match self.client.read() {
+                Ok(Some(message)) => {
+                    if let Err(error) = self.client.read_contexts(Some(message)) {
+                        return Some(Err(error));
+                    }
+                    self.client.trim_read_ahead(&mut self.events.borrow_mut());
+                }
+                Ok(None) => {}
+                Err(Error::ConnectionClosed) => return None,
+                Err(error) => return Some(Err(error)),
+            }
+        }
+    }
+}
+
+impl<'a> Drop for EventIterator<'a> {
+    fn drop(&mut self) {
+        let subscription = self.subscribed.subscription;
+        if !self.client.is_closed() {
+            // Drop cannot fail, and a socket that is gone ended the subscription anyway.
+            let _ = self
+                .client
+                .unsubscribe(unsubscribe!(subscription), Box::new(|ctx, _| ctx));
+        }
+        // Events arriving before the `Unsubscribed` have nobody to read them.
+        self.client
+            .context
+            .events
+            .retain(|(subscribed, _)| subscribed.subscription != subscription);
+        let unread: usize = self.events.borrow().iter().map(|(size, _)| size).sum();
+        self.read_ahead_bytes.set(self.read_ahead_bytes.get() - unread);
+    }
+}
@@ -0,0 +1,73 @@
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{Arc, PoisonError};
+use std::time::{Duration, Instant};
+use wamp_core::tungstenite::Message;
+
+use crate::core::Socket;
+
+/// # Keep Alive
+/// Handle to the empty WebSocket pings a client sends at a fixed interval, keeping idle
+/// connections open behind load balancers.
+///
+/// Pings are sent while the client waits for frames, in `Client::read` and everything built on
+/// it such as `Client::event_loop`, which never wait longer than the time to the next ping.
+/// There is no thread of its own, so a client that stops reading stops pinging until it reads
+/// again. They stop when `stop` is called, when the handle is dropped, or when sending one fails
+/// because the socket closed.
+pub struct KeepAlive {
+    running: Arc<AtomicBool>,
+}
+
+impl KeepAlive {
+    /// A handle and the schedule the client keeps, sharing whether pings are still sent.
+    pub(crate) fn start(interval: Duration) -> (Self, PingSchedule) {
+        let running = Arc::new(AtomicBool::new(true));
+        let schedule = PingSchedule {
+            interval,
+            next: Instant::now() + interval,
+            running: running.clone(),
+        };
+        (Self { running }, schedule)
+    }
+
+    pub fn is_running(&self) -> bool {
+        self.running.load(Ordering::SeqCst)
+    }
+
+    /// Stop sending pings.
+    pub fn stop(self) {
+        self.running.store(false, Ordering::SeqCst);
+    }
+}
+
+impl Drop for KeepAlive {
+    fn drop(&mut self) {
+        self.running.store(false, Ordering::SeqCst);
+    }
+}
+
+/// When the client sends its next keep alive ping.
+pub(crate) struct PingSchedule {
+    interval: Duration,
+    next: Instant,
+    running: Arc<AtomicBool>,
+}
+
+impl PingSchedule {
+    /// Send the ping if it is due, returning how long until the next one, or `None` once the
+    /// keep alive stopped.
+    pub(crate) fn poll(&mut self, socket: &Socket, now: Instant) -> Option<Duration> {
+        if !self.running.load(Ordering::SeqCst) {
+            return None;
+        }
+        if now >= self.next {
+            let socket = &mut *socket.lock().unwrap_or_else(PoisonError::into_inner);
+            if socket.send(Message::Ping(vec![])).is_err() {
+                self.running.store(false, Ordering::SeqCst);
+                return None;
+            }
+            self.next = now + self.interval;
+        }
+        Some(self.next - now)
+    }
+}
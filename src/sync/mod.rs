@@ -1,3 +1,12 @@
-//pub mod context;
+pub mod auth;
+pub mod builder;
+pub mod cancellation;
+pub mod client;
+pub mod context;
+pub mod events;
+pub mod keepalive;
+pub mod session;
+pub mod snapshot;
+pub mod subscription;
 mod request;
-pub use self::request::WampRequest;
\ No newline at end of file
+pub use self::request::{negotiated_protocol, Protocols, WampRequest};
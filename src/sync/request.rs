@@ -1,8 +1,79 @@
+use std::fmt;
 use std::str::FromStr;
 use wamp_core::tungstenite;
-use wamp_core::http::{Uri, Version, self};
+use wamp_core::http::{self, header::{HeaderName, HeaderValue}, Uri, Version};
 use wamp_core::tungstenite::{client::IntoClientRequest, handshake::client::generate_key};
 
+/// # Protocols
+/// Several subprotocols offered in one `WampRequest`, in order of preference, for the router to
+/// pick from. They are sent comma separated in `Sec-WebSocket-Protocol`.
+///
+/// ## Examples
+/// ```
+/// use wamp_client::sync::{Protocols, WampRequest};
+/// use wamp_core::tungstenite::client::IntoClientRequest;
+///
+/// let request = WampRequest {
+///     uri: "ws://localhost:8080/ws",
+///     protocol: Protocols::new(vec!["wamp.2.msgpack", "wamp.2.json"]),
+/// }
+/// .into_client_request()
+/// .unwrap();
+///
+/// assert_eq!(request.headers()["Sec-WebSocket-Protocol"], "wamp.2.msgpack, wamp.2.json");
+/// ```
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Protocols(Vec<String>);
+
+impl Protocols {
+    pub fn new<P: ToString>(protocols: Vec<P>) -> Self {
+        Protocols(protocols.iter().map(ToString::to_string).collect())
+    }
+}
+
+impl fmt::Display for Protocols {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", self.0.join(", "))
+    }
+}
+
+/// The subprotocol the router picked in a handshake response, if it picked one.
+pub fn negotiated_protocol<T>(response: &http::Response<T>) -> Option<&str> {
+    response
+        .headers()
+        .get("Sec-WebSocket-Protocol")
+        .and_then(|protocol| protocol.to_str().ok())
+        .map(str::trim)
+}
+
+/// The handshake headers `into_client_request` sets itself, which `ClientBuilder::header` cannot
+/// override.
+const RESERVED_HEADERS: [&str; 6] = [
+    "sec-websocket-protocol",
+    "sec-websocket-key",
+    "sec-websocket-version",
+    "connection",
+    "upgrade",
+    "host",
+];
+
+/// `request` with `headers` added after the WebSocket headers. A header the request already has
+/// is replaced, except for the reserved WebSocket headers, which are always the generated ones.
+pub(crate) fn with_headers<R: IntoClientRequest>(
+    request: R,
+    headers: &[(String, String)],
+) -> tungstenite::Result<tungstenite::handshake::client::Request> {
+    let mut request = request.into_client_request()?;
+    for (name, value) in headers {
+        let name = HeaderName::from_bytes(name.as_bytes())?;
+        if !RESERVED_HEADERS.contains(&name.as_str()) {
+            request.headers_mut().insert(name, HeaderValue::from_str(value)?);
+        }
+    }
+    Ok(request)
+}
+
+#[derive(Clone)]
 pub struct WampRequest<U: ToString, P: ToString> {
     pub uri: U,
     pub protocol: P
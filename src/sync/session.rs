@@ -0,0 +1,170 @@
+use std::cell::RefCell;
+use std::rc::Rc;
+use wamp_core::call;
+use wamp_core::messages::{Event, Messages, WampError, WampResult};
+use wamp_core::publish;
+use wamp_core::serde_json::Value;
+
+use crate::error::Error;
+use crate::options::SubscribeOptions;
+
+use super::{
+    auth::AuthConfig,
+    builder::ClientBuilder,
+    client::{Client, ConnectionState, LoopExit},
+    subscription::SubscriptionHandle,
+    WampRequest,
+};
+
+/// # Session
+/// A sync client that has joined a realm, the high level entry point of the crate.
+///
+/// `Session::connect` opens the WebSocket, sends the `Hello`, answers the router's `Challenge`
+/// with the configured auth and returns once the router sends `Welcome`. The lower level
+/// `Client` stays available through `Session::client`.
+///
+/// ## Examples
+/// A full join and call against a mock router:
+/// ```
+/// use wamp_client::sync::{auth::AuthConfig, builder::ClientBuilder, session::Session};
+/// use wamp_client::test_util::{MockRouter, WELCOME};
+/// use wamp_core::serde_json::json;
+///
+/// let (url, router) = MockRouter::spawn(move |router| {
+///     let hello = router.recv();
+///     assert_eq!(hello[1], "realm1");
+///     assert_eq!(hello[2]["authmethods"], json!(["ticket"]));
+///     router.send(r#"[4,"ticket",{}]"#);
+///
+///     let authenticate = router.recv();
+///     assert_eq!(authenticate[1], "t0ps3cr3t");
+///     router.send(WELCOME);
+///
+///     let call = router.recv();
+///     assert_eq!(call[3], "com.example.add");
+///     router.send(json!([50, call[1], {}, [3]]));
+/// });
+///
+/// let auth = AuthConfig::new("alice").ticket("t0ps3cr3t");
+/// let mut session = Session::connect(url, "realm1", auth, ClientBuilder::new()).unwrap();
+///
+/// let result = session.call("com.example.add", json!([1, 2]), json!({})).unwrap().unwrap();
+/// assert_eq!(result.args, json!([3]));
+/// drop(session);
+/// router.join().unwrap();
+/// ```
+pub struct Session {
+    client: Client,
+}
+
+impl Session {
+    /// Connect to `url` and join `realm`, authenticating with `auth`. `options` configures the
+    /// underlying `Client`, and its timeout bounds the blocking calls of the session.
+    pub fn connect<U: ToString, R: ToString>(
+        url: U,
+        realm: R,
+        auth: AuthConfig,
+        options: ClientBuilder,
+    ) -> Result<Session, Error> {
+        let (mut client, _) = options.connect(WampRequest {
+            uri: url,
+            protocol: "wamp.2.json",
+        })?;
+        client.set_auth(auth.clone());
+        let hello = client.hello_builder(realm).build()?;
+        client.send(hello)?;
+
+        while client.state() != ConnectionState::Established {
+            let message = match client.read()? {
+                Some(message) => message,
+                None if client.state() == ConnectionState::Closed => {
+                    return Err(Error::ConnectionClosed)
+                }
+                None => continue,
+            };
+            if let Messages::Challenge(challenge) = &message {
+                client.send(auth.authenticate(challenge)?)?;
+            }
+            client.read_contexts(Some(message))?;
+        }
+        Ok(Session { client })
+    }
+
+    /// The client the session runs on, for everything the session does not wrap.
+    pub fn client(&mut self) -> &mut Client {
+        &mut self.client
+    }
+
+    pub fn into_client(self) -> Client {
+        self.client
+    }
+
+    /// Call `procedure` and block until its result, or the router's error, arrives.
+    pub fn call<P: ToString>(
+        &mut self,
+        procedure: P,
+        args: Value,
+        kwargs: Value,
+    ) -> Result<Result<WampResult, WampError>, Error> {
+        let mut call = call!(procedure.to_string());
+        call.args = args;
+        call.kwargs = kwargs;
+
+        let reply: Rc<RefCell<Option<Result<WampResult, WampError>>>> =
+            Rc::new(RefCell::new(None));
+        let reply2 = reply.clone();
+        self.client.call(
+            call,
+            Box::new(move |ctx, result| {
+                *reply2.borrow_mut() = Some(result);
+                ctx
+            }),
+        )?;
+
+        let timeout = self.client.timeout();
+        self.client.wait_for(
+            &reply,
+            timeout,
+            "The client did not receive a `Result` message before the timeout...",
+        )
+    }
+
+    /// Subscribe to `topic`, calling `on_event` with each of its events while the session's
+    /// event loop runs.
+    pub fn subscribe<T: ToString>(
+        &mut self,
+        topic: T,
+        mut on_event: impl FnMut(Event) + 'static,
+    ) -> Result<SubscriptionHandle, Error> {
+        self.client.subscribe_with_options(
+            topic,
+            SubscribeOptions::default(),
+            Box::new(move |ctx, event| {
+                on_event(event);
+                ctx
+            }),
+        )
+    }
+
+    /// Publish `args` on `topic`, without waiting for the router to acknowledge it.
+    pub fn publish<T: ToString>(&mut self, topic: T, args: Value) -> Result<(), Error> {
+        let mut publish = publish!(topic.to_string());
+        publish.args = args;
+        self.client.send(publish)
+    }
+
+    /// Register `procedure`, answering its invocations with `procedure_fn`.
+    /// See `Client::register_fn`.
+    pub fn register<P: ToString>(
+        &mut self,
+        procedure: P,
+        procedure_fn: impl FnMut(Value, Value) -> Result<Value, WampError> + 'static,
+    ) -> Result<(), Error> {
+        self.client.register_fn(procedure, procedure_fn)
+    }
+
+    /// Route frames to the session's callbacks until it ends. See `Client::event_loop`.
+    pub fn event_loop(&mut self) -> Result<LoopExit, Error> {
+        self.client.event_loop()
+    }
+}
@@ -0,0 +1,70 @@
+use serde::{Deserialize, Serialize};
+use wamp_core::serde_json::Value;
+
+use super::context::Context;
+
+/// A `Subscribe` without its request id, as persisted in a `SessionSnapshot`.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct SubscriptionSpec {
+    pub topic: String,
+    pub options: Value,
+}
+
+/// A `Register` without its request id, as persisted in a `SessionSnapshot`.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct RegistrationSpec {
+    pub procedure: String,
+    pub options: Value,
+}
+
+/// # Session Snapshot
+/// The subscriptions and registrations active on a session, without their callbacks, and the
+/// idempotency keys the router acknowledged.
+///
+/// Serialize this to disk before shutting down, then hand it to `Client::restore` on a fresh
+/// session to re-issue everything.
+///
+/// ## Examples
+/// ```
+/// use wamp_client::sync::context::Context;
+/// use wamp_client::sync::snapshot::SessionSnapshot;
+///
+/// let context = Context::new(None);
+/// let snapshot = SessionSnapshot::from(&context);
+///
+/// let persisted = wamp_core::serde_json::to_string(&snapshot).unwrap();
+/// let restored: SessionSnapshot = wamp_core::serde_json::from_str(&persisted).unwrap();
+/// assert_eq!(snapshot, restored);
+/// ```
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct SessionSnapshot {
+    pub subscriptions: Vec<SubscriptionSpec>,
+    pub registrations: Vec<RegistrationSpec>,
+    /// Keys of the publishes sent with `Client::publish_idempotent` the router acknowledged.
+    #[serde(default)]
+    pub acknowledged_keys: Vec<String>,
+}
+
+impl From<&Context> for SessionSnapshot {
+    fn from(context: &Context) -> Self {
+        Self {
+            subscriptions: context
+                .active_subscriptions
+                .iter()
+                .map(|(subscribe, _)| SubscriptionSpec {
+                    topic: subscribe.topic.clone(),
+                    options: subscribe.options.clone(),
+                })
+                .collect(),
+            registrations: context
+                .active_registrations
+                .iter()
+                .map(|(register, _)| RegistrationSpec {
+                    procedure: register.procedure.clone(),
+                    options: register.options.clone(),
+                })
+                .collect(),
+            acknowledged_keys: vec![],
+        }
+    }
+}
@@ -0,0 +1,127 @@
+use std::cell::RefCell;
+use std::rc::Rc;
+use std::time::{Duration, Instant};
+use wamp_core::messages::Subscribed;
+
+use crate::options::MatchPolicy;
+
+/// # Rate Limit
+/// Fixed window limit on events per second, counting the events it turns away.
+///
+/// ## Examples
+/// ```
+/// use wamp_client::sync::subscription::RateLimit;
+///
+/// let mut limit = RateLimit::new(2);
+/// let allowed = (0..5).filter(|_| limit.allow()).count();
+///
+/// assert_eq!(allowed, 2);
+/// assert_eq!(limit.dropped(), 3);
+/// ```
+#[derive(Debug)]
+pub struct RateLimit {
+    per_second: u32,
+    window_start: Instant,
+    window_count: u32,
+    dropped: u64,
+}
+
+impl RateLimit {
+    pub fn new(per_second: u32) -> Self {
+        Self {
+            per_second,
+            window_start: Instant::now(),
+            window_count: 0,
+            dropped: 0,
+        }
+    }
+
+    /// Whether one more event fits in the current one second window.
+    pub fn allow(&mut self) -> bool {
+        if self.window_start.elapsed() >= Duration::from_secs(1) {
+            self.window_start = Instant::now();
+            self.window_count = 0;
+        }
+        if self.window_count < self.per_second {
+            self.window_count += 1;
+            true
+        } else {
+            self.dropped += 1;
+            false
+        }
+    }
+
+    /// Number of events turned away since the limit was created.
+    pub fn dropped(&self) -> u64 {
+        self.dropped
+    }
+}
+
+/// # Subscription Handle
+/// Returned by `Client::subscribe_with_options`, to observe a subscription after the fact.
+#[derive(Clone)]
+pub struct SubscriptionHandle {
+    pub(crate) subscribed: Rc<RefCell<Option<Subscribed>>>,
+    pub(crate) rate_limit: Option<Rc<RefCell<RateLimit>>>,
+    pub(crate) match_policy: MatchPolicy,
+}
+
+impl SubscriptionHandle {
+    /// The router's confirmation of the subscription, once it has arrived.
+    pub fn subscribed(&self) -> Option<Subscribed> {
+        self.subscribed.borrow().clone()
+    }
+
+    /// How the topic is matched. `Subscribed` carries no details, so this is the policy the
+    /// subscription was requested with, `MatchPolicy::Exact` when none was given.
+    ///
+    /// ## Examples
+    /// ```
+    /// use wamp_client::options::{MatchPolicy, SubscribeOptions};
+    /// use wamp_client::sync::client::Client;
+    /// use wamp_client::sync::WampRequest;
+    /// use wamp_client::test_util::{establish, MockRouter};
+    /// use wamp_core::serde_json::json;
+    ///
+    /// let (uri, router) = MockRouter::spawn(move |router| {
+    ///     router.welcome();
+    ///     let subscribe = router.recv();
+    ///     assert_eq!(subscribe[2], json!({ "match": "wildcard" }));
+    ///     router.send(json!([33, subscribe[1], 7]));
+    /// });
+    ///
+    /// let (mut client, _) = Client::connect(WampRequest { uri, protocol: "wamp.2.json" }).unwrap();
+    /// establish(&mut client);
+    ///
+    /// let handle = client
+    ///     .subscribe_with_options("com.myapp..door", SubscribeOptions::wildcard(), Box::new(|ctx, _| ctx))
+    ///     .unwrap();
+    /// while handle.subscribed().is_none() {
+    ///     let message = client.read().unwrap();
+    ///     client.read_contexts(message).unwrap();
+    /// }
+    ///
+    /// assert_eq!(handle.match_policy(), MatchPolicy::Wildcard);
+    /// assert!(handle.is_pattern());
+    /// assert_eq!(client.context.match_policy(7), Some(MatchPolicy::Wildcard));
+    /// drop(client);
+    /// router.join().unwrap();
+    /// ```
+    pub fn match_policy(&self) -> MatchPolicy {
+        self.match_policy
+    }
+
+    /// Whether the subscription matches a prefix or wildcard pattern rather than one topic, in
+    /// which case events carry the concrete topic in their `topic` detail.
+    pub fn is_pattern(&self) -> bool {
+        self.match_policy != MatchPolicy::Exact
+    }
+
+    /// Number of events dropped by the subscription's rate limit, always `0` without one.
+    pub fn dropped(&self) -> u64 {
+        self.rate_limit
+            .as_ref()
+            .map(|rate_limit| rate_limit.borrow().dropped())
+            .unwrap_or(0)
+    }
+}
@@ -0,0 +1,230 @@
+//! # Test Util
+//! Replay captured WAMP traffic into a client, to check callback behaviour against what a real
+//! router sent, or script a `MockRouter` to answer a live client.
+//!
+//! A capture is a file of WAMP frames as JSON, one per line. Blank lines are skipped.
+//!
+//! Needs the `test-util` feature, so it is only built for the tests that enable it. The crate's
+//! own tests enable it through its dev-dependencies.
+//!
+//! ## Examples
+//! ```
+//! use wamp_client::test_util::Replay;
+//! use wamp_core::messages::Messages;
+//!
+//! let replay = Replay::from_file(concat!(env!("CARGO_MANIFEST_DIR"), "/fixtures/session.ndjson")).unwrap();
+//!
+//! assert_eq!(replay.frames().len(), 6);
+//! assert!(matches!(replay.frames()[0], Messages::Welcome(_)));
+//! let events = replay.frames().iter().filter(|frame| matches!(frame, Messages::Event(_))).count();
+//! assert_eq!(events, 2);
+//! ```
+
+use std::fs;
+use std::net::{TcpListener, TcpStream};
+use std::path::Path;
+use std::thread::{self, JoinHandle};
+use wamp_core::messages::Messages;
+use wamp_core::serde_json::{from_str, Value};
+use wamp_core::tungstenite::{accept, Message, WebSocket};
+
+use crate::error::Error;
+use crate::hello::HelloBuilder;
+use crate::sync::client::{Client, ConnectionState};
+use crate::threads::client::Client as ThreadsClient;
+
+/// The `Welcome` a `MockRouter` answers a `Hello` with, for session `1` on a router playing
+/// both the broker and the dealer role.
+pub const WELCOME: &str = r#"[2,1,{"roles":{"broker":{},"dealer":{}}}]"#;
+
+/// # Replay
+/// Frames parsed from a capture, fed to a client in the order they were recorded.
+#[derive(Debug, Clone)]
+pub struct Replay {
+    frames: Vec<Messages>,
+}
+
+impl Replay {
+    /// Parse a capture held in memory.
+    pub fn parse(capture: &str) -> Result<Self, Error> {
+        let mut frames = vec![];
+        for line in capture.lines() {
+            if line.trim().is_empty() {
+                continue;
+            }
+            frames.push(from_str(line)?);
+        }
+        Ok(Self { frames })
+    }
+
+    /// Read and parse a capture file.
+    pub fn from_file<P: AsRef<Path>>(path: P) -> Result<Self, Error> {
+        Self::parse(&fs::read_to_string(path)?)
+    }
+
+    /// The recorded frames, in order.
+    pub fn frames(&self) -> &[Messages] {
+        &self.frames
+    }
+
+    /// # Replay Sync
+    /// Route every frame through `Client::read_contexts`, as if it had been read from the socket.
+    /// Stops at the first frame the client fails to handle.
+    ///
+    /// ## Examples
+    /// ```
+    /// use std::cell::RefCell;
+    /// use std::rc::Rc;
+    /// use wamp_client::sync::client::{Client, ConnectionState};
+    /// use wamp_client::sync::WampRequest;
+    /// use wamp_client::test_util::{MockRouter, Replay};
+    /// use wamp_core::messages::Subscribed;
+    ///
+    /// let uri = MockRouter::silent();
+    ///
+    /// let (mut client, _) = Client::connect(WampRequest { uri, protocol: "wamp.2.json" }).unwrap();
+    /// let replayed = Rc::new(RefCell::new(vec![]));
+    /// let replayed2 = replayed.clone();
+    /// client.context.event(Subscribed { request_id: 1, subscription: 10 }, Box::new(move |ctx, event| {
+    ///     replayed2.borrow_mut().push(event.args[0].clone());
+    ///     ctx
+    /// })).unwrap();
+    ///
+    /// let replay = Replay::from_file(concat!(env!("CARGO_MANIFEST_DIR"), "/fixtures/session.ndjson")).unwrap();
+    /// replay.replay_sync(&mut client).unwrap();
+    ///
+    /// assert_eq!(*replayed.borrow(), vec!["hello", "world"]);
+    /// // The capture ends with the router's `Goodbye`.
+    /// assert_ne!(client.state(), ConnectionState::Established);
+    /// ```
+    pub fn replay_sync(&self, client: &mut Client) -> Result<(), Error> {
+        for frame in &self.frames {
+            client.read_contexts(Some(frame.clone()))?;
+        }
+        Ok(())
+    }
+
+    /// # Replay Threads
+    /// Route every frame through `threads::client::Client::run_events`, joining the listener
+    /// thread of each frame before the next one so the replay is deterministic.
+    pub fn replay_threads(&self, client: &mut ThreadsClient) -> Result<(), wamp_core::Error> {
+        for frame in &self.frames {
+            let (_, handle) = client.run_events(frame.clone())?;
+            // A panicking listener is the listener's failure, the replay carries on like the
+            // event loop does.
+            let _ = handle.join();
+        }
+        Ok(())
+    }
+}
+
+/// # Mock Router
+/// A WebSocket server on a free local port standing in for a WAMP router, driven frame by frame
+/// by the test.
+///
+/// ## Examples
+/// ```
+/// use wamp_client::sync::{client::Client, WampRequest};
+/// use wamp_client::test_util::{establish, MockRouter};
+/// use wamp_core::publish;
+///
+/// let (uri, router) = MockRouter::spawn(move |router| {
+///     router.welcome();
+///     router.recv()
+/// });
+///
+/// let (mut client, _) = Client::connect(WampRequest { uri, protocol: "wamp.2.json" }).unwrap();
+/// establish(&mut client);
+/// client.send(publish!("com.example.topic")).unwrap();
+/// drop(client);
+///
+/// assert_eq!(router.join().unwrap()[3], "com.example.topic");
+/// ```
+pub struct MockRouter {
+    socket: WebSocket<TcpStream>,
+}
+
+impl MockRouter {
+    /// Bind a listener on a free local port, with the `ws://` uri that connects to it.
+    pub fn listen() -> (String, TcpListener) {
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let uri = format!("ws://{}/ws", listener.local_addr().unwrap());
+        (uri, listener)
+    }
+
+    /// Accept the next connection on `listener` and complete its WebSocket handshake.
+    pub fn accept(listener: &TcpListener) -> Self {
+        let (stream, _) = listener.accept().unwrap();
+        Self {
+            socket: accept(stream).unwrap(),
+        }
+    }
+
+    /// # Mock Router Spawn
+    /// Accept a single connection on a new thread and run `script` on it, then keep reading
+    /// until the client disconnects. Returns the uri to connect to and the router's thread,
+    /// which hands back what `script` returned.
+    ///
+    /// A panicking script fails the test on `JoinHandle::join`.
+    pub fn spawn<T, F>(script: F) -> (String, JoinHandle<T>)
+    where
+        T: Send + 'static,
+        F: FnOnce(&mut MockRouter) -> T + Send + 'static,
+    {
+        let (uri, listener) = Self::listen();
+        let router = thread::spawn(move || {
+            let mut router = Self::accept(&listener);
+            let result = script(&mut router);
+            router.wait_closed();
+            result
+        });
+        (uri, router)
+    }
+
+    /// A router that accepts a single connection and never sends a frame on it.
+    pub fn silent() -> String {
+        Self::spawn(|_| ()).0
+    }
+
+    /// The next text frame, parsed as JSON. Control and binary frames are skipped.
+    pub fn recv(&mut self) -> Value {
+        loop {
+            if let Message::Text(text) = self.socket.read().unwrap() {
+                return from_str(&text).unwrap();
+            }
+        }
+    }
+
+    /// Send `frame`, such as a `json!` value or a literal, as a text frame.
+    pub fn send<F: ToString>(&mut self, frame: F) {
+        self.socket.send(Message::Text(frame.to_string())).unwrap();
+    }
+
+    /// Read the client's `Hello` and answer it with `WELCOME`, returning the `Hello`.
+    pub fn welcome(&mut self) -> Value {
+        let hello = self.recv();
+        assert_eq!(hello[0], 1, "expected a Hello, got {}", hello);
+        self.send(WELCOME);
+        hello
+    }
+
+    /// The WebSocket under the router, for control frames and closing the connection.
+    pub fn socket(&mut self) -> &mut WebSocket<TcpStream> {
+        &mut self.socket
+    }
+
+    /// Read until the client disconnects or the connection fails, answering pings and close
+    /// frames on the way.
+    pub fn wait_closed(&mut self) {
+        while self.socket.read().is_ok() {}
+    }
+}
+
+/// Send a `Hello` for `realm1` and read until the router welcomes the client.
+pub fn establish(client: &mut Client) {
+    client.send(HelloBuilder::new("realm1").build().unwrap()).unwrap();
+    while client.state() != ConnectionState::Established {
+        let message = client.read().unwrap();
+        client.read_contexts(message).unwrap();
+    }
+}
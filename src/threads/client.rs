@@ -1,11 +1,15 @@
 
 
-use std::{sync::{Arc, Mutex}, convert::TryInto, thread::JoinHandle};
-use wamp_core::{messages::*, serde_json::from_str, tungstenite::client, subscribe, unsubscribe};
+use std::{sync::{Arc, Mutex, PoisonError}, convert::TryInto, thread::{self, JoinHandle}, time::Duration};
+use wamp_core::{messages::*, serde_json::{from_str, Value}, tungstenite::client, subscribe, unsubscribe};
+use std::panic::resume_unwind;
 use std::thread::spawn;
-use wamp_core::{Error, http::Response, tungstenite::{connect, Message}, WampMessage};
-use crate::{core::Socket, sync::WampRequest};
-use super::events::Events;
+use std::net::TcpStream;
+use wamp_core::{Error, http::Response, tungstenite::{connect, stream::MaybeTlsStream, Message, WebSocket}, WampMessage};
+#[cfg(feature = "native-tls")]
+use wamp_core::tungstenite::Connector;
+use crate::{core::{read_timeout, Socket, IDLE_WAIT}, sync::{cancellation::CancellationToken, WampRequest}};
+use super::{events::Events, resume::Resume};
 
 #[derive(Clone)]
 pub struct Client {
@@ -13,30 +17,107 @@ pub struct Client {
     pub request_id: Arc<Mutex<u64>>,
     pub routing_id: Arc<Mutex<u64>>,
     pub events: Arc<Mutex<Vec<Arc<Mutex<(u64, Events)>>>>>,
+    pub(crate) resume: Arc<Mutex<Resume>>,
+    /// The request the client last connected with, which `reconnect` connects with again.
+    pub(crate) request: Arc<Mutex<WampRequest<String, String>>>,
+    /// Cancelled by `shutdown`, which stops the heartbeat threads.
+    shutdown: CancellationToken,
 }
 
 impl Client {
     pub fn connect<U: ToString, P: ToString>(
         request: WampRequest<U, P>,
     ) -> Result<(Client, Response<Option<Vec<u8>>>), Error> {
-        let (socket, response) = connect(request)?;
+        let request = stored_request(request);
+        let (socket, response) = connect(request.clone())?;
+        Ok((Client::from_socket(socket, request), response))
+    }
+
+    /// # Connect With Tls Config
+    /// Connect like `Client::connect`, wrapping the stream with `connector` instead of the
+    /// default TLS settings, for routers with self-signed certificates or a private CA.
+    ///
+    /// Needs the `native-tls` feature. `reconnect` connects with the default TLS settings.
+    ///
+    /// ## Examples
+    /// ```
+    /// use std::fs;
+    /// use std::net::TcpListener;
+    /// use std::thread;
+    /// use native_tls::{Certificate, Identity, TlsAcceptor, TlsConnector};
+    /// use wamp_client::hello::HelloBuilder;
+    /// use wamp_client::sync::WampRequest;
+    /// use wamp_client::threads::client::Client;
+    /// use wamp_core::messages::Messages;
+    /// use wamp_core::tungstenite::{accept, Connector, Message};
+    ///
+    /// fn fixture(name: &str) -> Vec<u8> {
+    ///     fs::read(format!("{}/fixtures/tls/{}", env!("CARGO_MANIFEST_DIR"), name)).unwrap()
+    /// }
+    ///
+    /// // A wss router whose certificate is signed by a private CA.
+    /// let identity = Identity::from_pkcs8(&fixture("router.pem"), &fixture("router-key.pem")).unwrap();
+    /// let acceptor = TlsAcceptor::new(identity).unwrap();
+    /// let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+    /// let uri = format!("wss://localhost:{}/ws", listener.local_addr().unwrap().port());
+    /// thread::spawn(move || {
+    ///     for stream in listener.incoming() {
+    ///         // The default TLS settings do not trust the CA, so that handshake fails.
+    ///         if let Ok(stream) = acceptor.accept(stream.unwrap()) {
+    ///             let mut socket = accept(stream).unwrap();
+    ///             while !socket.read().unwrap().is_text() {}
+    ///             socket.send(Message::Text(r#"[2,1,{"roles":{"broker":{}}}]"#.to_string())).unwrap();
+    ///             while socket.read().is_ok() {}
+    ///         }
+    ///     }
+    /// });
+    ///
+    /// assert!(Client::connect(WampRequest { uri: uri.clone(), protocol: "wamp.2.json" }).is_err());
+    ///
+    /// let ca = Certificate::from_pem(&fixture("router-ca.pem")).unwrap();
+    /// let connector = TlsConnector::builder().add_root_certificate(ca).build().unwrap();
+    ///
+    /// let (mut client, _) = Client::connect_with_tls_config(
+    ///     WampRequest { uri, protocol: "wamp.2.json" },
+    ///     Connector::NativeTls(connector),
+    /// )
+    /// .unwrap();
+    /// client.send(HelloBuilder::new("realm1").build().unwrap()).unwrap();
+    /// assert!(matches!(client.read().unwrap(), Some(Messages::Welcome(_))));
+    /// ```
+    #[cfg(feature = "native-tls")]
+    pub fn connect_with_tls_config<U: ToString, P: ToString>(
+        request: WampRequest<U, P>,
+        connector: Connector,
+    ) -> Result<(Client, Response<Option<Vec<u8>>>), Error> {
+        let request = stored_request(request);
+        let (socket, response) =
+            crate::core::connect_with_connector(request.clone(), None, connector)?;
+        Ok((Client::from_socket(socket, request), response))
+    }
+
+    fn from_socket(
+        socket: WebSocket<MaybeTlsStream<TcpStream>>,
+        request: WampRequest<String, String>,
+    ) -> Client {
         let socket = Arc::new(Mutex::new(socket));
         let request_id = Arc::new(Mutex::new(0));
         let routing_id = Arc::new(Mutex::new(0));
         let events = Arc::new(Mutex::new(vec![]));
-        Ok((
-            Client {
-                socket,
-                request_id,
-                routing_id,
-                events
-            },
-            response,
-        ))
+        let resume = Arc::new(Mutex::new(Resume::default()));
+        Client {
+            socket,
+            request_id,
+            routing_id,
+            events,
+            resume,
+            request: Arc::new(Mutex::new(request)),
+            shutdown: CancellationToken::new(),
+        }
     }
 
     pub fn on(&self, routing_id: u64, event: Events) {
-        let mut events = self.events.lock().expect("Events mutex guard poisoned");
+        let mut events = self.events.lock().unwrap_or_else(PoisonError::into_inner);
         events.push(Arc::new(Mutex::new((routing_id, event))));
     }
 
@@ -44,23 +125,238 @@ impl Client {
     where
         Error: From<<T as TryInto<Message>>::Error>,
     {
+        let message = {
+            let mut resume = self.resume.lock().unwrap_or_else(PoisonError::into_inner);
+            let message = resume.outbound(message.try_into()?)?;
+            resume.remember_hello(&message);
+            message
+        };
         let socket = &mut *self
             .socket
             .lock()
             .expect("WebSocket mutex Poisoned during message sending.");
-        Ok(socket.send(message.try_into()?)?)
+        Ok(socket.send(message)?)
+    }
+
+    /// # Reconnect
+    /// Re-run the WebSocket handshake with the request the client last connected with, and join
+    /// a new session with the last `Hello` it sent. See `Client::reconnect_to`.
+    ///
+    /// Returns `Error::HelloNotSent` if the client never sent a `Hello`. When the handshake
+    /// fails the error is returned and the client is left as it was, listeners included.
+    ///
+    /// ## Examples
+    /// ```
+    /// use std::thread;
+    /// use wamp_client::hello::HelloBuilder;
+    /// use wamp_client::sync::WampRequest;
+    /// use wamp_client::test_util::MockRouter;
+    /// use wamp_client::threads::{client::Client, events::Events};
+    ///
+    /// // A router that accepts a single connection, reads its `Hello` and goes away.
+    /// let (uri, listener) = MockRouter::listen();
+    /// let router = thread::spawn(move || {
+    ///     MockRouter::accept(&listener).recv();
+    /// });
+    ///
+    /// let (mut client, _) = Client::connect(WampRequest { uri, protocol: "wamp.2.json" }).unwrap();
+    /// client.on(client.new_routing_id(), Events::Welcome(Box::new(|_, welcome| {
+    ///     println!("joined as session {}", welcome.session);
+    /// })));
+    /// client.send(HelloBuilder::new("realm1").build().unwrap()).unwrap();
+    /// router.join().unwrap();
+    ///
+    /// // The router is gone, so the handshake fails and the listener stays registered.
+    /// assert!(client.reconnect().is_err());
+    /// assert_eq!(client.events.lock().unwrap().len(), 1);
+    /// ```
+    pub fn reconnect(&mut self) -> Result<Response<Option<Vec<u8>>>, crate::error::Error> {
+        let hello = self
+            .resume
+            .lock()
+            .unwrap_or_else(PoisonError::into_inner)
+            .hello
+            .clone()
+            .ok_or(crate::error::Error::HelloNotSent)?;
+        let request = self
+            .request
+            .lock()
+            .unwrap_or_else(PoisonError::into_inner)
+            .clone();
+        Ok(self.reconnect_to(request, hello)?)
+    }
+
+    /// # Endpoint
+    /// The URI the client is connected to, the one of the last `reconnect_to` if it was called.
+    /// Returned owned, since clones of the client on other threads may reconnect it.
+    pub fn endpoint(&self) -> String {
+        self.request
+            .lock()
+            .unwrap_or_else(PoisonError::into_inner)
+            .uri
+            .clone()
+    }
+
+    /// # Reconnect To
+    /// Re-run the WebSocket handshake with `request`, swap the new socket in behind the shared
+    /// `socket` mutex and send `hello` to join a new session.
+    ///
+    /// Listeners registered with `on` are kept, and once the router sends `Welcome` every
+    /// subscription and registration made through `Subscription` is replayed. Events on replayed
+    /// subscriptions are delivered with the subscription id the listeners were set up with.
+    pub fn reconnect_to<U: ToString, P: ToString>(
+        &self,
+        request: WampRequest<U, P>,
+        hello: Hello,
+    ) -> Result<Response<Option<Vec<u8>>>, Error> {
+        let request = stored_request(request);
+        let (socket, response) = connect(request.clone())?;
+        *self.socket.lock().unwrap_or_else(PoisonError::into_inner) = socket;
+        *self.request.lock().unwrap_or_else(PoisonError::into_inner) = request;
+        self.resume
+            .lock()
+            .unwrap_or_else(PoisonError::into_inner)
+            .replay_on_welcome = true;
+        self.send(hello)?;
+        Ok(response)
+    }
+
+    /// # Start Heartbeat
+    /// Spawn a thread sending an empty WebSocket ping on the shared socket every `interval`, so
+    /// idle sessions are not dropped by NAT or load balancers.
+    ///
+    /// The thread stops once a ping fails to send because the socket closed, or within one
+    /// `interval` of `Client::shutdown`. A panic elsewhere poisoning the socket mutex does not
+    /// stop it, since the socket itself is still usable.
+    ///
+    /// `Client::read`, and so `event_loop` running on another thread, leaves the socket unlocked
+    /// while the connection is idle, so pings go out even when the router stays silent.
+    ///
+    /// ## Examples
+    /// ```
+    /// use std::thread;
+    /// use std::time::Duration;
+    /// use wamp_client::hello::HelloBuilder;
+    /// use wamp_client::sync::WampRequest;
+    /// use wamp_client::test_util::MockRouter;
+    /// use wamp_client::threads::client::Client;
+    /// use wamp_core::tungstenite::Message;
+    ///
+    /// // A router that never answers, and hangs up on the first ping.
+    /// let (uri, router) = MockRouter::spawn(move |router| {
+    ///     while !matches!(router.socket().read().unwrap(), Message::Ping(_)) {}
+    ///     router.socket().close(None).unwrap();
+    /// });
+    ///
+    /// let (client, _) = Client::connect(WampRequest { uri, protocol: "wamp.2.json" }).unwrap();
+    /// let mut event_loop = client.clone();
+    /// let reader = thread::spawn(move || event_loop.event_loop());
+    ///
+    /// let heartbeat = client.start_heartbeat(Duration::from_millis(50));
+    /// client.send(HelloBuilder::new("realm1").build().unwrap()).unwrap();
+    ///
+    /// // The ping got through while the event loop was reading.
+    /// router.join().unwrap();
+    /// client.shutdown();
+    /// heartbeat.join().unwrap();
+    /// assert!(reader.join().unwrap().is_err());
+    /// ```
+    pub fn start_heartbeat(&self, interval: Duration) -> JoinHandle<()> {
+        let socket = self.socket.clone();
+        let shutdown = self.shutdown.clone();
+        thread::spawn(move || loop {
+            thread::park_timeout(interval);
+            if shutdown.is_cancelled() {
+                break;
+            }
+            let socket = &mut *socket.lock().unwrap_or_else(PoisonError::into_inner);
+            if socket.send(Message::Ping(vec![])).is_err() {
+                break;
+            }
+        })
     }
 
+    /// Stop the heartbeat threads started with `start_heartbeat`, shared by every clone of the
+    /// client.
+    pub fn shutdown(&self) {
+        self.shutdown.cancel();
+    }
+
+    pub(crate) fn replay(&self) -> Result<(), Error> {
+        let replay = self
+            .resume
+            .lock()
+            .unwrap_or_else(PoisonError::into_inner)
+            .replay(|| self.new_request_id());
+        for message in replay {
+            self.send(message)?;
+        }
+        Ok(())
+    }
+
+    /// # New Routing Id
+    /// The next id to register a listener under with `Client::on`. Routing ids are counted apart
+    /// from request ids, so taking one never skips a request id.
+    ///
+    /// ## Examples
+    /// ```
+    /// use wamp_client::sync::WampRequest;
+    /// use wamp_client::test_util::MockRouter;
+    /// use wamp_client::threads::client::Client;
+    ///
+    /// let uri = MockRouter::silent();
+    ///
+    /// let (client, _) = Client::connect(WampRequest { uri, protocol: "wamp.2.json" }).unwrap();
+    ///
+    /// let ids: Vec<_> = (0..3)
+    ///     .map(|_| (client.new_request_id(), client.new_routing_id()))
+    ///     .collect();
+    /// assert_eq!(ids, vec![(1, 1), (2, 2), (3, 3)]);
+    ///
+    /// // Request ids taken in between do not move the routing ids on.
+    /// assert_eq!((client.new_request_id(), client.new_request_id()), (4, 5));
+    /// assert_eq!(client.new_routing_id(), 4);
+    /// ```
     pub fn new_routing_id(&self) -> u64 {
-        let mut request_id = *self.request_id.lock().unwrap();
-        request_id = request_id + 1;
-        request_id
+        let mut routing_id = self.routing_id.lock().unwrap_or_else(PoisonError::into_inner);
+        *routing_id += 1;
+        *routing_id
     }
 
+    /// # New Request Id
+    /// The next request id of the session, unique across every clone of the client.
+    ///
+    /// ## Examples
+    /// ```
+    /// use std::collections::HashSet;
+    /// use std::thread;
+    /// use wamp_client::sync::WampRequest;
+    /// use wamp_client::test_util::MockRouter;
+    /// use wamp_client::threads::client::Client;
+    ///
+    /// let uri = MockRouter::silent();
+    ///
+    /// let (client, _) = Client::connect(WampRequest { uri, protocol: "wamp.2.json" }).unwrap();
+    ///
+    /// let threads: Vec<_> = (0..8)
+    ///     .map(|_| {
+    ///         let client = client.clone();
+    ///         thread::spawn(move || (0..100).map(|_| client.new_request_id()).collect::<Vec<_>>())
+    ///     })
+    ///     .collect();
+    ///
+    /// let mut ids = HashSet::new();
+    /// for thread in threads {
+    ///     for id in thread.join().unwrap() {
+    ///         assert!(ids.insert(id));
+    ///     }
+    /// }
+    /// assert_eq!(ids.len(), 800);
+    /// ```
     pub fn new_request_id(&self) -> u64 {
-        let mut request_id = *self.request_id.lock().unwrap();
-        request_id = request_id + 1;
-        request_id
+        let mut request_id = self.request_id.lock().unwrap_or_else(PoisonError::into_inner);
+        *request_id += 1;
+        *request_id
     }
 
     //pub fn create_callback(&self, routing_ids: Vec<u64>, on_callback: Box<dyn FnOnce(Client)>) -> Box<dyn FnOnce()> {
@@ -71,10 +367,67 @@ impl Client {
     //})
     //}
 
+    /// # Replace
+    /// Swap the listener registered under `routing_id` in place, keeping its position and any
+    /// subscription it serves. Returns `false` if no listener has that routing id.
+    ///
+    /// ## Examples
+    /// ```
+    /// use std::sync::mpsc::channel;
+    /// use std::thread;
+    /// use std::time::Duration;
+    /// use wamp_client::sync::WampRequest;
+    /// use wamp_client::test_util::MockRouter;
+    /// use wamp_client::threads::{client::Client, events::Events};
+    /// use wamp_core::serde_json::{json, Value};
+    ///
+    /// // A router sending an event on subscription 5 each time it is released.
+    /// let (release, released) = channel::<&str>();
+    /// let (uri, _) = MockRouter::spawn(move |router| {
+    ///     for (publication, arg) in released.iter().enumerate() {
+    ///         router.send(json!([36, 5, publication, {}, [arg]]));
+    ///     }
+    /// });
+    ///
+    /// let (client, _) = Client::connect(WampRequest { uri, protocol: "wamp.2.json" }).unwrap();
+    /// let (sender, receiver) = channel::<(&str, Value)>();
+    /// let routing_id = client.new_routing_id();
+    /// let first = sender.clone();
+    /// client.on(routing_id, Events::Event(Box::new(move |_, event| {
+    ///     let _ = first.send(("first", event.args));
+    /// })));
+    /// let mut event_loop = client.clone();
+    /// thread::spawn(move || event_loop.event_loop());
+    ///
+    /// release.send("a").unwrap();
+    /// let delivered = receiver.recv_timeout(Duration::from_secs(5)).unwrap();
+    /// assert_eq!(delivered, ("first", json!(["a"])));
+    ///
+    /// assert!(client.replace(routing_id, Events::Event(Box::new(move |_, event| {
+    ///     let _ = sender.send(("second", event.args));
+    /// }))));
+    /// assert!(!client.replace(client.new_routing_id(), Events::Event(Box::new(|_, _| {}))));
+    ///
+    /// release.send("b").unwrap();
+    /// let delivered = receiver.recv_timeout(Duration::from_secs(5)).unwrap();
+    /// assert_eq!(delivered, ("second", json!(["b"])));
+    /// ```
+    pub fn replace(&self, routing_id: u64, event: Events) -> bool {
+        let events = self.events.lock().unwrap_or_else(PoisonError::into_inner);
+        for callback in events.iter() {
+            let callback = &mut *callback.lock().unwrap_or_else(PoisonError::into_inner);
+            if callback.0 == routing_id {
+                callback.1 = event;
+                return true;
+            }
+        }
+        false
+    }
+
     pub fn remove_callbacks(&self, routing_ids: Vec<u64>) {
-        let events = &mut *self.events.lock().unwrap();
+        let events = &mut *self.events.lock().unwrap_or_else(PoisonError::into_inner);
         events.retain(|callback| { 
-            let (routing, _) = *callback.lock().unwrap();
+            let (routing, _) = *callback.lock().unwrap_or_else(PoisonError::into_inner);
 
                 if routing_ids.contains(&routing) {
                     return false
@@ -122,17 +475,92 @@ impl Client {
     }
     */
 
+    /// # Event Loop
+    /// Read frames and run the listeners for each on their own thread, until reading fails,
+    /// such as when the connection closes.
+    ///
+    /// The read error is returned once every listener thread still running has finished, so
+    /// the effects of the last events are visible when `event_loop` returns. Use
+    /// `read_then_run_event` to get the handle of each listener thread instead.
+    ///
+    /// A listener that panics ends the loop as well, which stops reading and resumes the panic
+    /// once the other listener threads finished, instead of losing it.
+    ///
+    /// ## Examples
+    /// ```
+    /// use std::sync::atomic::{AtomicBool, Ordering};
+    /// use std::sync::Arc;
+    /// use wamp_client::sync::WampRequest;
+    /// use wamp_client::test_util::MockRouter;
+    /// use wamp_client::threads::{client::Client, events::Events};
+    ///
+    /// // A router that welcomes the client, then closes the connection.
+    /// let (uri, _) = MockRouter::spawn(move |router| {
+    ///     router.send(r#"[2,1,{"roles":{"broker":{}}}]"#);
+    ///     router.socket().close(None).unwrap();
+    /// });
+    ///
+    /// let (mut client, _) = Client::connect(WampRequest { uri, protocol: "wamp.2.json" }).unwrap();
+    /// let welcomed = Arc::new(AtomicBool::new(false));
+    /// let welcomed2 = welcomed.clone();
+    /// client.on(client.new_routing_id(), Events::Welcome(Box::new(move |_, _| {
+    ///     welcomed2.store(true, Ordering::SeqCst);
+    /// })));
+    ///
+    /// assert!(client.event_loop().is_err());
+    /// assert!(welcomed.load(Ordering::SeqCst));
+    /// ```
+    ///
+    /// A panicking listener, against a router that keeps the connection open:
+    /// ```
+    /// use std::panic::{catch_unwind, AssertUnwindSafe};
+    /// use std::thread;
+    /// use std::time::Duration;
+    /// use wamp_client::sync::WampRequest;
+    /// use wamp_client::test_util::MockRouter;
+    /// use wamp_client::threads::{client::Client, events::Events};
+    ///
+    /// let (uri, _) = MockRouter::spawn(move |router| {
+    ///     router.send(r#"[2,1,{"roles":{"broker":{}}}]"#);
+    ///     // Another frame for the loop to read once the listener panicked.
+    ///     thread::sleep(Duration::from_millis(100));
+    ///     router.send(r#"[36,5,1,{},[]]"#);
+    /// });
+    ///
+    /// let (mut client, _) = Client::connect(WampRequest { uri, protocol: "wamp.2.json" }).unwrap();
+    /// client.on(client.new_routing_id(), Events::Welcome(Box::new(|_, _| {
+    ///     panic!("listener failed");
+    /// })));
+    ///
+    /// let panic = catch_unwind(AssertUnwindSafe(|| client.event_loop())).unwrap_err();
+    /// assert_eq!(panic.downcast_ref::<&str>(), Some(&"listener failed"));
+    /// ```
     pub fn event_loop(&mut self) -> Result<(), Error> {
-        loop {
-            self.read_then_run_event()?;
-            //let event = self.read_then_run_event()?;
-            //match event {
-            //    Some((message, joiner)) => {
-            //
-            //    },
-            //    _ => {}
-            //}
+        let mut handles: Vec<JoinHandle<()>> = vec![];
+        let mut panic = None;
+        let result = loop {
+            match self.read_then_run_event() {
+                Ok(Some((_, handle))) => handles.push(handle),
+                Ok(None) => {}
+                Err(error) => break Err(error),
+            }
+            let (finished, running): (Vec<_>, Vec<_>) =
+                handles.into_iter().partition(|handle| handle.is_finished());
+            handles = running;
+            panic = finished.into_iter().filter_map(|handle| handle.join().err()).next();
+            if panic.is_some() {
+                break Ok(());
+            }
+        };
+        for handle in handles {
+            if let Err(payload) = handle.join() {
+                panic.get_or_insert(payload);
+            }
+        }
+        if let Some(payload) = panic {
+            resume_unwind(payload);
         }
+        result
     }
 
     pub fn read_then_run_event(&mut self) -> Result<Option<(Messages, JoinHandle<()>)>, Error> {
@@ -142,7 +570,53 @@ impl Client {
         }
     }
 
+    /// # Run Events
+    /// Hand `message` to every listener of its kind on a new thread, returned with the message.
+    ///
+    /// A panicking listener poisons the listener mutexes, which the client recovers from, so
+    /// listeners can still be added, removed and called afterwards.
+    ///
+    /// ## Examples
+    /// ```
+    /// use std::sync::mpsc::channel;
+    /// use wamp_client::sync::WampRequest;
+    /// use wamp_client::test_util::MockRouter;
+    /// use wamp_client::threads::{client::Client, events::Events};
+    /// use wamp_core::messages::Messages;
+    /// use wamp_core::serde_json::{from_str, json};
+    ///
+    /// let uri = MockRouter::silent();
+    ///
+    /// let (mut client, _) = Client::connect(WampRequest { uri, protocol: "wamp.2.json" }).unwrap();
+    /// let event: Messages = from_str(r#"[36,5,1,{},["a"]]"#).unwrap();
+    ///
+    /// let panicking = client.new_routing_id();
+    /// client.on(panicking, Events::Event(Box::new(|_, _| panic!("listener failed"))));
+    /// let (_, handle) = client.run_events(event.clone()).unwrap();
+    /// assert!(handle.join().is_err());
+    ///
+    /// client.remove_callbacks(vec![panicking]);
+    /// let (sender, receiver) = channel();
+    /// client.on(client.new_routing_id(), Events::Event(Box::new(move |_, event| {
+    ///     sender.send(event.args).unwrap();
+    /// })));
+    /// let (_, handle) = client.run_events(event).unwrap();
+    /// handle.join().unwrap();
+    /// assert_eq!(receiver.recv().unwrap(), json!(["a"]));
+    /// ```
     pub fn run_events(&mut self, message: Messages) -> Result<(Messages, JoinHandle<()>), Error> {
+        let message = {
+            let mut resume = self.resume.lock().unwrap_or_else(PoisonError::into_inner);
+            let message = resume.inbound(message);
+            if let Messages::Welcome(_) = message {
+                if resume.replay_on_welcome {
+                    resume.replay_on_welcome = false;
+                    drop(resume);
+                    self.replay()?;
+                }
+            }
+            message
+        };
         let events = (&self.events).clone();
         let arc_client = Client::from(self);
 
@@ -153,9 +627,11 @@ impl Client {
                 Ok((
                     message,
                     spawn(move || {
-                        let mut events = events.lock().unwrap();
+                        // A panicking handler poisons the listener mutexes, but the listeners are
+                        // still intact, so keep dispatching to them rather than bricking the client.
+                        let mut events = events.lock().unwrap_or_else(PoisonError::into_inner);
                         for event in events.iter_mut() {
-                            let (_, event) = &mut *event.lock().unwrap();
+                            let (_, event) = &mut *event.lock().unwrap_or_else(PoisonError::into_inner);
                             if let Events::$events(callback) = event {
                                 callback(arc_client.clone(), $value.clone());
                             }
@@ -180,39 +656,57 @@ impl Client {
             Messages::Unregistered(unregistered) => run_events!(Unregistered, unregistered),
             Messages::Unsubscribed(unsubscribed) => run_events!(Unsubscribed, unsubscribed),
             Messages::Welcome(welcome) => run_events!(Welcome, welcome),
-            Messages::Extension(extension) => run_events!(Extension, extension),
+            Messages::Extension(extension) => run_events!(Extension, Value::from(extension)),
+            // Frames only a router should receive go to the `InvalidFrame` listeners, and are
+            // only an error when nobody listens for them.
+            invalid if self.listens_for_invalid_frames() => run_events!(InvalidFrame, invalid),
             _ => Err(Error::InvalidFrameReceived(message)),
         }
     }
 
+    /// Whether an `Events::InvalidFrame` listener is registered.
+    fn listens_for_invalid_frames(&self) -> bool {
+        let events = self.events.lock().unwrap_or_else(PoisonError::into_inner);
+        events.iter().any(|event| {
+            let (_, event) = &*event.lock().unwrap_or_else(PoisonError::into_inner);
+            matches!(event, Events::InvalidFrame(_))
+        })
+    }
+
     /// # Read
     /// Read a frame from tungstenite and convert to WAMP messages.
+    ///
+    /// The socket is only locked for short stretches while waiting, so other clones of the
+    /// client can send, and `start_heartbeat` can ping, while a read waits on an idle connection.
     pub fn read(&mut self) -> Result<Option<Messages>, Error> {
-        match self.socket.lock().unwrap().read().unwrap() {
+        let message = loop {
+            if let Some(message) = read_timeout(&self.socket, IDLE_WAIT)? {
+                break message;
+            }
+        };
+        match message {
             Message::Text(message) => Ok(Some(from_str(&message)?)),
             _ => Ok(None),
         }
     }
 }
 
+/// `request` with its uri and protocol as strings, so the client can keep it around.
+fn stored_request<U: ToString, P: ToString>(request: WampRequest<U, P>) -> WampRequest<String, String> {
+    WampRequest {
+        uri: request.uri.to_string(),
+        protocol: request.protocol.to_string(),
+    }
+}
+
 impl From<&Client> for Client {
     fn from(value: &Client) -> Self {
-        Self {
-            socket: value.socket.clone(),
-            request_id: value.request_id.clone(),
-            events: value.events.clone(),
-            routing_id: value.routing_id.clone()
-        }
+        value.clone()
     }
 }
 
 impl From<&mut Client> for Client {
     fn from(value: &mut Client) -> Self {
-        Self {
-            socket: value.socket.clone(),
-            request_id: value.request_id.clone(),
-            events: value.events.clone(),
-            routing_id: value.routing_id.clone()
-        }
+        value.clone()
     }
-}
\ No newline at end of file
+}
@@ -28,8 +28,75 @@ pub enum Events {
     Unsubscribed(Callback<Unsubscribed>),
     Welcome(Callback<Welcome>),
     Challenge(Callback<Challenge>),
-    Extension(Callback<Vec<Value>>),
+    /// An extension frame, a message type outside the WAMP spec, as the raw JSON array it was
+    /// received as, like the sync client's `on_extension`.
+    ///
+    /// ```
+    /// use std::sync::mpsc::channel;
+    /// use std::thread;
+    /// use std::time::Duration;
+    /// use wamp_client::sync::WampRequest;
+    /// use wamp_client::test_util::MockRouter;
+    /// use wamp_client::threads::{client::Client, events::Events};
+    /// use wamp_core::serde_json::json;
+    ///
+    /// let (release, released) = channel::<()>();
+    /// let (uri, _) = MockRouter::spawn(move |router| {
+    ///     released.recv().unwrap();
+    ///     router.send(r#"[300, "custom", { "a": 1 }]"#);
+    /// });
+    ///
+    /// let (client, _) = Client::connect(WampRequest { uri, protocol: "wamp.2.json" }).unwrap();
+    /// let (sender, receiver) = channel();
+    /// client.on(client.new_routing_id(), Events::Extension(Box::new(move |_, frame| {
+    ///     let _ = sender.send(frame);
+    /// })));
+    /// let mut event_loop = client.clone();
+    /// thread::spawn(move || event_loop.event_loop());
+    /// release.send(()).unwrap();
+    ///
+    /// let frame = receiver.recv_timeout(Duration::from_secs(5)).unwrap();
+    /// assert_eq!(frame, json!([300, "custom", { "a": 1 }]));
+    /// ```
+    Extension(Callback<Value>),
     Unregistered(Callback<Unregistered>),
+    /// A frame only a router should receive, such as a `Call`. Without an `InvalidFrame`
+    /// listener such frames fail `Client::run_events` with `Error::InvalidFrameReceived`.
+    ///
+    /// ```
+    /// use std::sync::mpsc::channel;
+    /// use std::thread;
+    /// use std::time::Duration;
+    /// use wamp_client::error::Error;
+    /// use wamp_client::sync::WampRequest;
+    /// use wamp_client::test_util::MockRouter;
+    /// use wamp_client::threads::{client::Client, events::Events};
+    /// use wamp_core::messages::Messages;
+    /// use wamp_core::serde_json::from_str;
+    ///
+    /// const CALL: &str = r#"[48,1,{},"com.example.add"]"#;
+    ///
+    /// let (release, released) = channel::<()>();
+    /// let (uri, _) = MockRouter::spawn(move |router| {
+    ///     released.recv().unwrap();
+    ///     router.send(CALL);
+    /// });
+    ///
+    /// let (mut client, _) = Client::connect(WampRequest { uri, protocol: "wamp.2.json" }).unwrap();
+    /// let call: Messages = from_str(CALL).unwrap();
+    /// assert!(matches!(client.run_events(call), Err(Error::InvalidFrameReceived(_))));
+    ///
+    /// let (sender, receiver) = channel();
+    /// client.on(client.new_routing_id(), Events::InvalidFrame(Box::new(move |_, frame| {
+    ///     let _ = sender.send(frame);
+    /// })));
+    /// let mut event_loop = client.clone();
+    /// thread::spawn(move || event_loop.event_loop());
+    /// release.send(()).unwrap();
+    ///
+    /// let frame = receiver.recv_timeout(Duration::from_secs(5)).unwrap();
+    /// assert!(matches!(frame, Messages::Call(call) if call.procedure == "com.example.add"));
+    /// ```
     InvalidFrame(Callback<Messages>)
 }
 
@@ -1,3 +1,6 @@
 pub mod client;
 pub mod events;
-pub mod pubsub;
\ No newline at end of file
+#[macro_use]
+pub mod pubsub;
+pub mod rpc;
+mod resume;
@@ -1,8 +1,10 @@
-use std::{sync::{Arc, Mutex}, time::{SystemTime, Duration}};
+use std::{sync::{Arc, Condvar, Mutex, PoisonError}, time::{Instant, Duration}};
 
 use wamp_core::{Subscribe, WampError, Subscribed, Unsubscribed, Unsubscribe, Event, call};
+use wamp_core::messages::{Publish, Published, WampErrorEvent};
 
 use crate::error::Error;
+use crate::options::PublishOptions;
 
 use super::{client::Client, events::Events};
 
@@ -10,11 +12,14 @@ pub struct Subscription {
     pub client: Client,
     pub subscribe: Option<Subscribe>,
     pub subscribed: Option<Subscribed>,
-    pub routing_ids: Vec<u64>
+    pub routing_ids: Vec<u64>,
+    /// How long `subscribe` and `unsubscribe` wait for the router's reply.
+    pub timeout: Duration,
 }
 
 macro_rules! create_callback_handler {
-    ($sig:ident, $arg_type:ty, $return_value:ty, $variant:ident, $lock_error:expr, $timeout_error:expr) => {
+    ($(#[$attr:meta])* $sig:ident, $arg_type:ty, $return_value:ty, $variant:ident, $lock_error:expr, $timeout_error:expr, $on_reply:expr) => {
+        $(#[$attr])*
         pub fn $sig(&mut self, $sig: $arg_type ) -> Result<Result<$return_value, wamp_core::WampError>, $crate::error::Error> {
             let routing_id1 = self.client.new_routing_id();
             let error_routing_id = self.client.new_routing_id();
@@ -22,45 +27,55 @@ macro_rules! create_callback_handler {
             self.routing_ids.push(routing_id1);
             self.routing_ids.push(error_routing_id);
     
-            let routed_reference: Arc<Mutex<Option<$return_value>>> = Arc::new(Mutex::new(None));
-            let wamperror: Arc<Mutex<Option<WampError>>> = Arc::new(Mutex::new(None));
-    
-            let routed_reference2 = routed_reference.clone();
+            // The reply, or the error answering the request, whichever the listeners see first.
+            let reply: Arc<(Mutex<Option<Result<$return_value, WampError>>>, Condvar)> =
+                Arc::new((Mutex::new(None), Condvar::new()));
 
             let request_id = $sig.request_id;
+            let request = $sig.clone();
 
+            let reply2 = reply.clone();
             self.client.on(routing_id1, Events::$variant(Box::new(move |_, result| {
                 if request_id == result.request_id {
-                    let mut routed_reference = routed_reference2.lock().expect($lock_error);
-                    *routed_reference = Some(result);
+                    let (slot, condvar) = &*reply2;
+                    slot.lock().expect($lock_error).get_or_insert(Ok(result));
+                    condvar.notify_all();
                 };
             })));
-    
-            let wamperror2 = wamperror.clone();
+
+            let reply2 = reply.clone();
             self.client.on(error_routing_id, Events::Error(Box::new(move |_, error| {
                 if request_id == error.request_id {
-                    let mut wamperror = wamperror2.lock().expect($lock_error);
-                    *wamperror = Some(error);
+                    let (slot, condvar) = &*reply2;
+                    slot.lock().expect($lock_error).get_or_insert(Err(error));
+                    condvar.notify_all();
                 }
             })));
-    
-            let time_start = SystemTime::now();
-            loop {
-                if SystemTime::now().duration_since(time_start)? > Duration::from_secs(10) {
-                    break Err(Error::TimeOutError($timeout_error))
-                };
-    
-                let result = routed_reference.lock().expect($lock_error).clone();
-                let wamperror = wamperror.lock().expect($lock_error).clone();
-    
-                if let Some(result) = result {
-                    break Ok(Ok(result));
-                }
-    
-                if let Some(error) = wamperror {
-                    break Ok(Err(error))
+
+            self.client.send(request.clone())?;
+
+            // Block until a listener stores the reply, instead of spinning on the mutex.
+            let deadline = Instant::now() + self.timeout;
+            let reply = {
+                let (slot, condvar) = &*reply;
+                let mut slot = slot.lock().expect($lock_error);
+                loop {
+                    if let Some(reply) = slot.take() {
+                        break Ok(reply);
+                    }
+                    let now = Instant::now();
+                    if now >= deadline {
+                        break Err(Error::TimeOutError($timeout_error));
+                    }
+                    slot = condvar.wait_timeout(slot, deadline - now).expect($lock_error).0;
                 }
-            }
+            };
+
+            reply.map(|reply| reply.map(|reply| {
+                let on_reply: fn(&mut Self, $arg_type, &$return_value) = $on_reply;
+                on_reply(self, request, &reply);
+                reply
+            }))
         }
     };
 }
@@ -71,22 +86,149 @@ impl Subscription {
             client,
             subscribe: None,
             subscribed: None,
-            routing_ids: vec![]
+            routing_ids: vec![],
+            timeout: Duration::from_secs(10),
         }
     }
-    create_callback_handler!(subscribe, Subscribe, Subscribed, Subscribed, "One of the values involved in the subscription callback was poisoned, oh no.", "The client did not receive a `Subscribed` message from the WAMP implementation in less than 10 seconds...");
-    create_callback_handler!(unsubscribe, Unsubscribe, Unsubscribed, Unsubscribed, "One of the values involved in the unsubscription callback was poisoned, oh no.", "The client did not receive a `Unsubscribed` message from the WAMP implementation in less than 10 seconds...");
+
+    /// # Subscription With Timeout
+    /// Wait `timeout` for the router's reply, instead of the default 10 seconds.
+    ///
+    /// ## Examples
+    /// ```
+    /// use std::thread;
+    /// use std::time::{Duration, Instant};
+    /// use wamp_client::error::Error;
+    /// use wamp_client::sync::WampRequest;
+    /// use wamp_client::test_util::MockRouter;
+    /// use wamp_client::threads::{client::Client, pubsub::Subscription};
+    /// use wamp_core::subscribe;
+    ///
+    /// // A router that reads the `Subscribe` and never answers it.
+    /// let uri = MockRouter::silent();
+    ///
+    /// let (client, _) = Client::connect(WampRequest { uri, protocol: "wamp.2.json" }).unwrap();
+    /// let mut event_loop = client.clone();
+    /// thread::spawn(move || event_loop.event_loop());
+    ///
+    /// let started = Instant::now();
+    /// let mut subscription = Subscription::new(client).with_timeout(Duration::from_millis(100));
+    /// assert!(matches!(subscription.subscribe(subscribe!("topic")), Err(Error::TimeOutError(_))));
+    /// assert!(started.elapsed() < Duration::from_secs(5));
+    /// ```
+    pub fn with_timeout(mut self, timeout: Duration) -> Self {
+        self.timeout = timeout;
+        self
+    }
+    create_callback_handler!(
+        /// # Subscription Subscribe
+        /// Send `subscribe` and block until the router answers it, with the `Subscribed` or the
+        /// `Error`. Another thread has to run `Client::event_loop` to read the reply.
+        ///
+        /// The request is sent here, so it must not be sent with `Client::send` beforehand.
+        ///
+        /// An error the listeners stored by the time the wait ends is returned over the timeout,
+        /// even when it lands right at the deadline.
+        ///
+        /// ## Examples
+        /// ```
+        /// use std::thread;
+        /// use std::time::Duration;
+        /// use wamp_client::sync::WampRequest;
+        /// use wamp_client::test_util::MockRouter;
+        /// use wamp_client::threads::{client::Client, pubsub::Subscription};
+        /// use wamp_core::serde_json::json;
+        /// use wamp_core::subscribe;
+        ///
+        /// let (uri, _) = MockRouter::spawn(move |router| {
+        ///     let subscribe = router.recv();
+        ///     let error = json!([8, 32, subscribe[1], {}, "wamp.error.not_authorized"]);
+        ///     router.send(error);
+        /// });
+        ///
+        /// let (client, _) = Client::connect(WampRequest { uri, protocol: "wamp.2.json" }).unwrap();
+        /// let mut event_loop = client.clone();
+        /// thread::spawn(move || event_loop.event_loop());
+        ///
+        /// let mut subscription = Subscription::new(client).with_timeout(Duration::from_millis(500));
+        /// match subscription.subscribe(subscribe!("topic")) {
+        ///     Ok(Err(error)) => assert_eq!(error.error, "wamp.error.not_authorized"),
+        ///     _ => panic!("expected the router's error, not a timeout"),
+        /// }
+        /// assert!(subscription.subscribed.is_none());
+        /// ```
+        subscribe, Subscribe, Subscribed, Subscribed, "One of the values involved in the subscription callback was poisoned, oh no.", "The client did not receive a `Subscribed` message from the WAMP implementation before the timeout...", |this, subscribe, subscribed| {
+        this.client.resume.lock().unwrap_or_else(PoisonError::into_inner).remember_subscription(subscribe.clone(), subscribed.subscription);
+        this.subscribe = Some(subscribe);
+        this.subscribed = Some(subscribed.clone());
+    });
+    create_callback_handler!(
+        /// # Subscription Unsubscribe
+        /// Send `unsubscribe` and block until the router answers it, like `subscribe`.
+        unsubscribe, Unsubscribe, Unsubscribed, Unsubscribed, "One of the values involved in the unsubscription callback was poisoned, oh no.", "The client did not receive a `Unsubscribed` message from the WAMP implementation before the timeout...", |this, unsubscribe, _| {
+        this.client.resume.lock().unwrap_or_else(PoisonError::into_inner).forget_subscription(unsubscribe.subscription);
+        this.subscribe = None;
+        this.subscribed = None;
+    });
+    /// # Subscription Events
+    /// Call `callback` with every event of the subscription, matched on the subscription id the
+    /// router assigned in `Subscribed`.
+    ///
+    /// Returns `Error::NoSubscription` until `subscribe` succeeded.
+    ///
+    /// ## Examples
+    /// ```
+    /// use std::sync::mpsc::channel;
+    /// use std::thread;
+    /// use std::time::Duration;
+    /// use wamp_client::sync::WampRequest;
+    /// use wamp_client::test_util::MockRouter;
+    /// use wamp_client::threads::{client::Client, pubsub::Subscription};
+    /// use wamp_core::serde_json::json;
+    /// use wamp_core::{publish, subscribe};
+    ///
+    /// let (uri, router) = MockRouter::spawn(move |router| {
+    ///     let subscribe = router.recv();
+    ///     assert_eq!(subscribe[0], 32);
+    ///     router.send(json!([33, subscribe[1], 5]));
+    ///
+    ///     // The `Subscribe` was sent once, the next frame is the publish marking the listener set.
+    ///     assert_eq!(router.recv()[0], 16);
+    ///     router.send(r#"[36,5,1,{},["a"]]"#);
+    ///     router.socket().close(None).unwrap();
+    /// });
+    ///
+    /// let (client, _) = Client::connect(WampRequest { uri, protocol: "wamp.2.json" }).unwrap();
+    /// let mut event_loop = client.clone();
+    /// let reader = thread::spawn(move || event_loop.event_loop());
+    ///
+    /// let mut subscription = Subscription::new(client.clone());
+    /// let subscribed = subscription.subscribe(subscribe!("topic")).unwrap().unwrap();
+    /// assert_eq!(subscribed.subscription, 5);
+    ///
+    /// let (sender, receiver) = channel();
+    /// subscription
+    ///     .events(Box::new(move |_, event| {
+    ///         let _ = sender.send(event.args);
+    ///     }))
+    ///     .unwrap();
+    /// client.send(publish!("ready")).unwrap();
+    ///
+    /// assert_eq!(receiver.recv_timeout(Duration::from_secs(5)).unwrap(), json!(["a"]));
+    /// assert!(reader.join().unwrap().is_err());
+    /// router.join().unwrap();
+    /// ```
     pub fn events(&mut self, callback: Box<dyn FnMut(Client, Event) + Send> ) -> Result<(), Error> {
         if let Some(subscribed) = &self.subscribed {
             let routing_id = self.client.new_routing_id();
             self.routing_ids.push(routing_id);
             let callback = Arc::new(Mutex::new(callback)); 
             
-            let subscribed = subscribed.request_id;
+            let subscribed = subscribed.subscription;
 
             self.client.on(routing_id, Events::Event(Box::new(move |client, event| {
                 if event.subscription == subscribed {
-                    let callback = &mut *callback.lock().unwrap();
+                    let callback = &mut *callback.lock().unwrap_or_else(PoisonError::into_inner);
                     callback(client, event)
                 }
             })));
@@ -96,4 +238,120 @@ impl Subscription {
         }
     }
     
-}
\ No newline at end of file
+}
+
+/// # Publication
+/// Publish on the threads client with typed options, blocking for the router's `Published` when
+/// the options ask for `acknowledge`, like `Rpc` does for calls. Another thread has to run
+/// `Client::event_loop` to read the reply.
+///
+/// Without `acknowledge` the router never answers, so `publish` returns `Ok(Ok(None))` once the
+/// frame is sent. An acknowledged publish the router refuses, for example with
+/// `wamp.error.not_authorized`, returns `Ok(Err(error))`, and no reply within `timeout` returns
+/// `Error::TimeOutError`.
+///
+/// ## Examples
+/// ```no_run
+/// use std::thread;
+/// use wamp_client::options::PublishOptions;
+/// use wamp_client::sync::WampRequest;
+/// use wamp_client::threads::{client::Client, pubsub::Publication};
+/// use wamp_core::publish;
+///
+/// let (client, _) = Client::connect(WampRequest { uri: "ws://localhost:8080/ws", protocol: "wamp.2.json" }).unwrap();
+/// let mut event_loop = client.clone();
+/// thread::spawn(move || event_loop.event_loop());
+///
+/// let options = PublishOptions {
+///     acknowledge: Some(true),
+///     exclude_authid: Some(vec!["bob".to_string()]),
+///     ..Default::default()
+/// };
+/// match Publication::new(client).publish(publish!("com.example.news"), options).unwrap() {
+///     Ok(published) => println!("published as {:?}", published.map(|p| p.publication)),
+///     Err(error) => println!("publish refused with {}", error.error),
+/// }
+/// ```
+pub struct Publication {
+    pub client: Client,
+    /// How long an acknowledged `publish` waits for the `Published`.
+    pub timeout: Duration,
+}
+
+impl Publication {
+    pub fn new(client: Client) -> Self {
+        Publication {
+            client,
+            timeout: Duration::from_secs(10),
+        }
+    }
+
+    /// Wait `timeout` for the router's reply, instead of the default 10 seconds.
+    pub fn with_timeout(mut self, timeout: Duration) -> Self {
+        self.timeout = timeout;
+        self
+    }
+
+    /// Publish with `options`, which replace the options of `publish`.
+    pub fn publish(
+        &mut self,
+        mut publish: Publish,
+        options: PublishOptions,
+    ) -> Result<Result<Option<Published>, WampError>, Error> {
+        publish.options = options.to_value();
+        if options.acknowledge != Some(true) {
+            self.client.send(publish)?;
+            return Ok(Ok(None));
+        }
+
+        let published_routing_id = self.client.new_routing_id();
+        let error_routing_id = self.client.new_routing_id();
+
+        // The `Published`, or the error refusing the publish, whichever the listeners see first.
+        let reply: Arc<(Mutex<Option<Result<Published, WampError>>>, Condvar)> =
+            Arc::new((Mutex::new(None), Condvar::new()));
+        let request_id = publish.request_id;
+
+        let reply2 = reply.clone();
+        self.client.on(published_routing_id, Events::Published(Box::new(move |_, published| {
+            if request_id == published.request_id {
+                let (slot, condvar) = &*reply2;
+                slot.lock().unwrap_or_else(PoisonError::into_inner).get_or_insert(Ok(published));
+                condvar.notify_all();
+            }
+        })));
+
+        let reply2 = reply.clone();
+        self.client.on(error_routing_id, Events::Error(Box::new(move |_, error| {
+            if request_id == error.request_id && matches!(error.event, WampErrorEvent::Publish) {
+                let (slot, condvar) = &*reply2;
+                slot.lock().unwrap_or_else(PoisonError::into_inner).get_or_insert(Err(error));
+                condvar.notify_all();
+            }
+        })));
+
+        let reply = self.client.send(publish).map_err(Error::from).and_then(|_| {
+            let deadline = Instant::now() + self.timeout;
+            let (slot, condvar) = &*reply;
+            let mut slot = slot.lock().unwrap_or_else(PoisonError::into_inner);
+            loop {
+                if let Some(reply) = slot.take() {
+                    break Ok(reply.map(Some));
+                }
+                let now = Instant::now();
+                if now >= deadline {
+                    break Err(Error::TimeOutError(
+                        "The client did not receive a `Published` message from the WAMP implementation before the timeout...",
+                    ));
+                }
+                slot = condvar
+                    .wait_timeout(slot, deadline - now)
+                    .unwrap_or_else(PoisonError::into_inner)
+                    .0;
+            }
+        });
+
+        self.client.remove_callbacks(vec![published_routing_id, error_routing_id]);
+        reply
+    }
+}
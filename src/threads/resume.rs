@@ -0,0 +1,163 @@
+use std::convert::TryInto;
+use wamp_core::{messages::*, serde_json::from_str, tungstenite::Message, Error};
+
+/// Subscriptions and registrations a threads `Client` replays after a reconnect.
+///
+/// The router assigns new subscription and registration ids to the replayed requests, so the
+/// new ids are aliased back to the ones listeners were set up with. Inbound `Event`s and
+/// `Invocation`s are rewritten to the original ids, and outbound `Unsubscribe`s and
+/// `Unregister`s to the current ones.
+#[derive(Default)]
+pub(crate) struct Resume {
+    subscriptions: Vec<(Subscribe, u64)>,
+    registrations: Vec<(Register, u64)>,
+    pending_subscribes: Vec<(u64, u64)>,
+    pending_registers: Vec<(u64, u64)>,
+    subscription_aliases: Vec<(u64, u64)>,
+    registration_aliases: Vec<(u64, u64)>,
+    pub(crate) replay_on_welcome: bool,
+    /// The last `Hello` sent, which `Client::reconnect` joins the new session with.
+    pub(crate) hello: Option<Hello>,
+}
+
+impl Resume {
+    pub(crate) fn remember_subscription(&mut self, subscribe: Subscribe, subscription: u64) {
+        self.subscriptions.push((subscribe, subscription));
+    }
+
+    pub(crate) fn forget_subscription(&mut self, subscription: u64) {
+        self.subscriptions.retain(|(_, original)| *original != subscription);
+        self.subscription_aliases.retain(|(_, original)| *original != subscription);
+    }
+
+    pub(crate) fn remember_registration(&mut self, register: Register, registration: u64) {
+        self.registrations.push((register, registration));
+    }
+
+    pub(crate) fn forget_registration(&mut self, registration: u64) {
+        self.registrations.retain(|(_, original)| *original != registration);
+        self.registration_aliases.retain(|(_, original)| *original != registration);
+    }
+
+    /// Fresh `Subscribe` and `Register` frames for everything remembered, with new request ids.
+    pub(crate) fn replay(&mut self, mut new_request_id: impl FnMut() -> u64) -> Vec<Messages> {
+        self.pending_subscribes.clear();
+        self.pending_registers.clear();
+        let mut replay = vec![];
+        for (subscribe, original) in &self.subscriptions {
+            let mut subscribe = subscribe.clone();
+            subscribe.request_id = new_request_id();
+            self.pending_subscribes.push((subscribe.request_id, *original));
+            replay.push(Messages::from(subscribe));
+        }
+        for (register, original) in &self.registrations {
+            let mut register = register.clone();
+            register.request_id = new_request_id();
+            self.pending_registers.push((register.request_id, *original));
+            replay.push(Messages::from(register));
+        }
+        replay
+    }
+
+    /// Record aliases from replies to replayed requests and rewrite ids back to the originals.
+    pub(crate) fn inbound(&mut self, message: Messages) -> Messages {
+        match message {
+            Messages::Subscribed(subscribed) => {
+                if let Some(i) = self
+                    .pending_subscribes
+                    .iter()
+                    .position(|(request_id, _)| *request_id == subscribed.request_id)
+                {
+                    let (_, original) = self.pending_subscribes.remove(i);
+                    self.subscription_aliases
+                        .retain(|(_, aliased)| *aliased != original);
+                    self.subscription_aliases
+                        .push((subscribed.subscription, original));
+                }
+                Messages::from(subscribed)
+            }
+            Messages::Registered(registered) => {
+                if let Some(i) = self
+                    .pending_registers
+                    .iter()
+                    .position(|(request_id, _)| *request_id == registered.request_id)
+                {
+                    let (_, original) = self.pending_registers.remove(i);
+                    self.registration_aliases
+                        .retain(|(_, aliased)| *aliased != original);
+                    self.registration_aliases
+                        .push((registered.registration, original));
+                }
+                Messages::from(registered)
+            }
+            Messages::Event(mut event) => {
+                if let Some((_, original)) = self
+                    .subscription_aliases
+                    .iter()
+                    .find(|(current, _)| *current == event.subscription)
+                {
+                    event.subscription = *original;
+                }
+                Messages::from(event)
+            }
+            Messages::Invocation(mut invocation) => {
+                if let Some((_, original)) = self
+                    .registration_aliases
+                    .iter()
+                    .find(|(current, _)| *current == invocation.registration)
+                {
+                    invocation.registration = *original;
+                }
+                Messages::from(invocation)
+            }
+            message => message,
+        }
+    }
+
+    /// Remember `message` if it is a `Hello`. Frames are serialized compactly, so a `Hello`
+    /// always starts with `[1,`, which spares parsing every other frame.
+    pub(crate) fn remember_hello(&mut self, message: &Message) {
+        if let Message::Text(text) = message {
+            if text.starts_with("[1,") {
+                if let Ok(Messages::Hello(hello)) = from_str(text) {
+                    self.hello = Some(hello);
+                }
+            }
+        }
+    }
+
+    /// Rewrite original ids in an outgoing frame to the ids the current session knows them by.
+    pub(crate) fn outbound(&self, message: Message) -> Result<Message, Error> {
+        if self.subscription_aliases.is_empty() && self.registration_aliases.is_empty() {
+            return Ok(message);
+        }
+
+        let text = match &message {
+            Message::Text(text) => text,
+            _ => return Ok(message),
+        };
+        match from_str(text)? {
+            Messages::Unsubscribe(mut unsubscribe) => {
+                if let Some((current, _)) = self
+                    .subscription_aliases
+                    .iter()
+                    .find(|(_, original)| *original == unsubscribe.subscription)
+                {
+                    unsubscribe.subscription = *current;
+                }
+                Ok(Messages::from(unsubscribe).try_into()?)
+            }
+            Messages::Unregister(mut unregister) => {
+                if let Some((current, _)) = self
+                    .registration_aliases
+                    .iter()
+                    .find(|(_, original)| *original == unregister.registration)
+                {
+                    unregister.registration = *current;
+                }
+                Ok(Messages::from(unregister).try_into()?)
+            }
+            _ => Ok(message),
+        }
+    }
+}
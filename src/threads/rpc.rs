@@ -0,0 +1,315 @@
+use std::{sync::{Arc, Condvar, Mutex, PoisonError}, time::{Duration, Instant}};
+
+use wamp_core::messages::{
+    Call, Invocation, Register, Registered, Unregister, Unregistered, WampErrorEvent, WampResult,
+    Yield,
+};
+use wamp_core::WampError;
+use wamp_core::serde_json::Value;
+
+use crate::error::Error;
+use crate::options::RegisterOptions;
+
+use super::{client::Client, events::Events};
+
+/// # Rpc
+/// Issue a `Call` on the threads client and block until its `Result`, like `Subscription` does
+/// for subscribing. Another thread has to run `Client::event_loop` to read the reply.
+///
+/// Progressive results, sent when the call asked for `receive_progress`, are skipped, and the
+/// final `Result` is returned. A call the router or callee answers with an `Error` returns
+/// `Ok(Err(error))`, while no reply within `timeout` returns `Error::TimeOutError`. The
+/// listeners of a call are removed once it returns, whatever the outcome.
+///
+/// ## Examples
+/// ```no_run
+/// use std::thread;
+/// use wamp_client::sync::WampRequest;
+/// use wamp_client::threads::{client::Client, rpc::Rpc};
+/// use wamp_core::call;
+/// use wamp_core::serde_json::json;
+///
+/// let (client, _) = Client::connect(WampRequest { uri: "ws://localhost:8080/ws", protocol: "wamp.2.json" }).unwrap();
+/// let mut event_loop = client.clone();
+/// thread::spawn(move || event_loop.event_loop());
+///
+/// let mut call = call!("com.example.add");
+/// call.args = json!([1, 2]);
+///
+/// match Rpc::new(client).call(call).unwrap() {
+///     Ok(result) => println!("sum: {}", result.args[0]),
+///     Err(error) => println!("call failed with {}", error.error),
+/// }
+/// ```
+pub struct Rpc {
+    pub client: Client,
+    /// How long `call` waits for the final `Result`.
+    pub timeout: Duration,
+}
+
+impl Rpc {
+    pub fn new(client: Client) -> Self {
+        Rpc {
+            client,
+            timeout: Duration::from_secs(10),
+        }
+    }
+
+    /// Wait `timeout` for the final result, instead of the default 10 seconds.
+    pub fn with_timeout(mut self, timeout: Duration) -> Self {
+        self.timeout = timeout;
+        self
+    }
+
+    pub fn call(&mut self, call: Call) -> Result<Result<WampResult, WampError>, Error> {
+        let result_routing_id = self.client.new_routing_id();
+        let error_routing_id = self.client.new_routing_id();
+
+        // The final result, or the error answering the call, whichever the listeners see first.
+        let reply: Arc<(Mutex<Option<Result<WampResult, WampError>>>, Condvar)> =
+            Arc::new((Mutex::new(None), Condvar::new()));
+        let request_id = call.request_id;
+
+        let reply2 = reply.clone();
+        self.client.on(result_routing_id, Events::Result(Box::new(move |_, result| {
+            let progress = result.details.get("progress").and_then(Value::as_bool) == Some(true);
+            if request_id == result.request_id && !progress {
+                let (slot, condvar) = &*reply2;
+                slot.lock().unwrap_or_else(PoisonError::into_inner).get_or_insert(Ok(result));
+                condvar.notify_all();
+            }
+        })));
+
+        let reply2 = reply.clone();
+        self.client.on(error_routing_id, Events::Error(Box::new(move |_, error| {
+            if request_id == error.request_id && matches!(error.event, WampErrorEvent::Call) {
+                let (slot, condvar) = &*reply2;
+                slot.lock().unwrap_or_else(PoisonError::into_inner).get_or_insert(Err(error));
+                condvar.notify_all();
+            }
+        })));
+
+        let reply = self.client.send(call).map_err(Error::from).and_then(|_| {
+            let deadline = Instant::now() + self.timeout;
+            let (slot, condvar) = &*reply;
+            let mut slot = slot.lock().unwrap_or_else(PoisonError::into_inner);
+            loop {
+                if let Some(reply) = slot.take() {
+                    break Ok(reply);
+                }
+                let now = Instant::now();
+                if now >= deadline {
+                    break Err(Error::TimeOutError(
+                        "The client did not receive a `Result` message from the WAMP implementation before the timeout...",
+                    ));
+                }
+                slot = condvar
+                    .wait_timeout(slot, deadline - now)
+                    .unwrap_or_else(PoisonError::into_inner)
+                    .0;
+            }
+        });
+
+        self.client.remove_callbacks(vec![result_routing_id, error_routing_id]);
+        reply
+    }
+}
+
+/// # Procedure
+/// Register a procedure on the threads client, waiting for `Registered` and `Unregistered` like
+/// `Subscription` does, and answer its invocations with `invocations`.
+///
+/// ## Examples
+/// ```no_run
+/// use std::thread;
+/// use wamp_client::messages::wamp_error;
+/// use wamp_client::sync::WampRequest;
+/// use wamp_client::threads::{client::Client, rpc::Procedure};
+/// use wamp_core::messages::{WampErrorEvent, Yield};
+/// use wamp_core::register;
+/// use wamp_core::serde_json::json;
+///
+/// let (client, _) = Client::connect(WampRequest { uri: "ws://localhost:8080/ws", protocol: "wamp.2.json" }).unwrap();
+/// let mut event_loop = client.clone();
+/// thread::spawn(move || event_loop.event_loop());
+///
+/// let mut procedure = Procedure::new(client);
+/// procedure.register(register!("com.example.add")).unwrap().unwrap();
+/// procedure.invocations(Box::new(|_, invocation| {
+///     match (invocation.args[0].as_i64(), invocation.args[1].as_i64()) {
+///         (Some(a), Some(b)) => Ok(Yield {
+///             request_id: invocation.request_id,
+///             options: json!({}),
+///             args: json!([a + b]),
+///             kwargs: json!({}),
+///         }),
+///         // Sent back as an `Error` answering the invocation.
+///         _ => Err(wamp_error(WampErrorEvent::Invocation, 0, "wamp.error.invalid_argument")),
+///     }
+/// })).unwrap();
+/// ```
+pub struct Procedure {
+    pub client: Client,
+    pub register: Option<Register>,
+    pub registered: Option<Registered>,
+    pub routing_ids: Vec<u64>,
+    /// How long `register` and `unregister` wait for the router's reply.
+    pub timeout: Duration,
+}
+
+impl Procedure {
+    pub fn new(client: Client) -> Self {
+        Procedure {
+            client,
+            register: None,
+            registered: None,
+            routing_ids: vec![],
+            timeout: Duration::from_secs(10),
+        }
+    }
+
+    /// Wait `timeout` for the router's reply, instead of the default 10 seconds.
+    pub fn with_timeout(mut self, timeout: Duration) -> Self {
+        self.timeout = timeout;
+        self
+    }
+
+    create_callback_handler!(register, Register, Registered, Registered, "One of the values involved in the registration callback was poisoned, oh no.", "The client did not receive a `Registered` message from the WAMP implementation before the timeout...", |this, register, registered| {
+        this.client.resume.lock().unwrap_or_else(PoisonError::into_inner).remember_registration(register.clone(), registered.registration);
+        this.register = Some(register);
+        this.registered = Some(registered.clone());
+    });
+    create_callback_handler!(unregister, Unregister, Unregistered, Unregistered, "One of the values involved in the unregistration callback was poisoned, oh no.", "The client did not receive a `Unregistered` message from the WAMP implementation before the timeout...", |this, unregister, _| {
+        this.client.resume.lock().unwrap_or_else(PoisonError::into_inner).forget_registration(unregister.registration);
+        this.client.remove_callbacks(this.routing_ids.drain(..).collect());
+        this.register = None;
+        this.registered = None;
+    });
+
+    /// # Procedure Register With Options
+    /// Register like `Procedure::register`, with typed options replacing the options of
+    /// `register`, such as the `invoke` policy of a shared registration. A router refusing the
+    /// policy answers with an `Error`, returned as `Ok(Err(error))`.
+    ///
+    /// ## Examples
+    /// ```no_run
+    /// use std::thread;
+    /// use wamp_client::options::{InvokePolicy, RegisterOptions};
+    /// use wamp_client::sync::WampRequest;
+    /// use wamp_client::threads::{client::Client, rpc::Procedure};
+    /// use wamp_core::register;
+    ///
+    /// let (client, _) = Client::connect(WampRequest { uri: "ws://localhost:8080/ws", protocol: "wamp.2.json" }).unwrap();
+    /// let mut event_loop = client.clone();
+    /// thread::spawn(move || event_loop.event_loop());
+    ///
+    /// let options = RegisterOptions::shared(InvokePolicy::RoundRobin);
+    /// let mut procedure = Procedure::new(client);
+    /// if let Err(error) = procedure.register_with_options(register!("com.example.add"), options).unwrap() {
+    ///     println!("registration refused with {}", error.error);
+    /// }
+    /// ```
+    pub fn register_with_options(
+        &mut self,
+        mut register: Register,
+        options: RegisterOptions,
+    ) -> Result<Result<Registered, WampError>, Error> {
+        register.options = options.to_value();
+        self.register(register)
+    }
+
+    /// # Procedure Invocations
+    /// Answer every invocation of the registered procedure with `handler`. `Ok(yield)` is sent
+    /// back as a `Yield`, and `Err(error)` as an `Error` answering the invocation, both with the
+    /// request id of the invocation filled in.
+    ///
+    /// Returns `Error::NoSubscription` if the procedure is not registered.
+    ///
+    /// ## Examples
+    /// ```
+    /// use std::thread;
+    /// use wamp_client::messages::wamp_error;
+    /// use wamp_client::sync::WampRequest;
+    /// use wamp_client::test_util::MockRouter;
+    /// use wamp_client::threads::{client::Client, rpc::Procedure};
+    /// use wamp_core::messages::{WampErrorEvent, Yield};
+    /// use wamp_core::serde_json::json;
+    /// use wamp_core::{publish, register};
+    ///
+    /// let (uri, router) = MockRouter::spawn(move |router| {
+    ///     let register = router.recv();
+    ///     assert_eq!(register[0], 64);
+    ///     router.send(json!([65, register[1], 9]));
+    ///
+    ///     // The publish marks the handler as set.
+    ///     assert_eq!(router.recv()[0], 16);
+    ///     router.send(r#"[68,100,9,{},[1,2]]"#);
+    ///     let r#yield = router.recv();
+    ///     assert_eq!((r#yield[0].clone(), r#yield[1].clone()), (json!(70), json!(100)));
+    ///     assert_eq!(r#yield[3], json!([3]));
+    ///
+    ///     router.send(r#"[68,101,9,{},["one",2]]"#);
+    ///     let error = router.recv();
+    ///     assert_eq!((error[0].clone(), error[1].clone(), error[2].clone()), (json!(8), json!(68), json!(101)));
+    ///     assert_eq!(error[4], "wamp.error.invalid_argument");
+    ///     router.socket().close(None).unwrap();
+    /// });
+    ///
+    /// let (client, _) = Client::connect(WampRequest { uri, protocol: "wamp.2.json" }).unwrap();
+    /// let mut event_loop = client.clone();
+    /// let reader = thread::spawn(move || event_loop.event_loop());
+    ///
+    /// let mut procedure = Procedure::new(client.clone());
+    /// procedure.register(register!("com.example.add")).unwrap().unwrap();
+    /// procedure.invocations(Box::new(|_, invocation| {
+    ///     match (invocation.args[0].as_i64(), invocation.args[1].as_i64()) {
+    ///         // The request id is left to `invocations`.
+    ///         (Some(a), Some(b)) => Ok(Yield {
+    ///             request_id: 0,
+    ///             options: json!({}),
+    ///             args: json!([a + b]),
+    ///             kwargs: json!({}),
+    ///         }),
+    ///         _ => Err(wamp_error(WampErrorEvent::Invocation, 0, "wamp.error.invalid_argument")),
+    ///     }
+    /// })).unwrap();
+    /// client.send(publish!("ready")).unwrap();
+    ///
+    /// assert!(reader.join().unwrap().is_err());
+    /// router.join().unwrap();
+    /// ```
+    pub fn invocations(
+        &mut self,
+        handler: Box<dyn FnMut(Client, Invocation) -> Result<Yield, WampError> + Send>,
+    ) -> Result<(), Error> {
+        if let Some(registered) = &self.registered {
+            let routing_id = self.client.new_routing_id();
+            self.routing_ids.push(routing_id);
+            let handler = Arc::new(Mutex::new(handler));
+
+            let registration = registered.registration;
+
+            self.client.on(routing_id, Events::Invocation(Box::new(move |client, invocation| {
+                if invocation.registration == registration {
+                    let request_id = invocation.request_id;
+                    let handler = &mut *handler.lock().unwrap_or_else(PoisonError::into_inner);
+                    // A failed send means the socket is gone, which the event loop reports.
+                    let _ = match handler(client.clone(), invocation) {
+                        Ok(mut r#yield) => {
+                            r#yield.request_id = request_id;
+                            client.send(r#yield)
+                        }
+                        Err(mut error) => {
+                            error.event = WampErrorEvent::Invocation;
+                            error.request_id = request_id;
+                            client.send(error)
+                        }
+                    };
+                }
+            })));
+            Ok(())
+        } else {
+            Err(Error::NoSubscription)
+        }
+    }
+}
@@ -0,0 +1,419 @@
+use std::collections::HashMap;
+use std::convert::TryInto;
+use std::sync::{Arc, Mutex as StdMutex, PoisonError};
+
+use ::tokio::net::TcpStream;
+use ::tokio::sync::{mpsc, oneshot, Mutex};
+use futures_util::stream::{SplitSink, SplitStream};
+use futures_util::{SinkExt, StreamExt};
+use tokio_tungstenite::{connect_async, MaybeTlsStream, WebSocketStream};
+use wamp_core::http::Response;
+use wamp_core::messages::*;
+use wamp_core::serde_json::{from_str, json, Value};
+use wamp_core::tungstenite::client::IntoClientRequest;
+use wamp_core::tungstenite::Message;
+use wamp_core::{call, publish, register, subscribe};
+
+use crate::error::Error;
+use crate::messages::{reply_request_id, Serializer};
+use crate::sync::WampRequest;
+
+type Socket = WebSocketStream<MaybeTlsStream<TcpStream>>;
+type Handler = Box<dyn FnMut(Invocation) -> Result<Yield, WampError> + Send>;
+
+/// A request waiting for its reply, with what to set up when the reply confirms it.
+enum Waiter {
+    Reply(oneshot::Sender<Messages>),
+    Subscribe(oneshot::Sender<Messages>, mpsc::UnboundedSender<Event>),
+    Register(oneshot::Sender<Messages>, Handler),
+}
+
+#[derive(Default)]
+struct Routes {
+    waiters: HashMap<u64, Waiter>,
+    subscriptions: HashMap<u64, mpsc::UnboundedSender<Event>>,
+    registrations: HashMap<u64, Handler>,
+}
+
+/// # Tokio Client
+/// An async client on `tokio-tungstenite`, mirroring the sync client. Needs the `async`
+/// feature.
+///
+/// `connect` spawns a task reading the socket, which resolves each request's future with the
+/// router's reply, forwards events to their `Subscription` and answers invocations of
+/// registered procedures. Every other frame, such as `Welcome` or `Goodbye`, is returned by
+/// `read`. Once the connection drops, pending requests and `read` return
+/// `Error::ConnectionClosed`.
+///
+/// The client is cheap to clone, and clones share the connection.
+///
+/// ## Examples
+/// Joining a realm, then subscribing and awaiting an event:
+/// ```no_run
+/// use wamp_client::sync::WampRequest;
+/// use wamp_client::tokio::client::Client;
+/// use wamp_core::hello;
+/// use wamp_core::messages::Messages;
+///
+/// #[tokio::main(flavor = "current_thread")]
+/// async fn main() {
+///     let (client, _) = Client::connect(WampRequest { uri: "ws://localhost:8080/ws", protocol: "wamp.2.json" })
+///         .await
+///         .unwrap();
+///
+///     client.send(hello!("realm1")).await.unwrap();
+///     match client.read().await.unwrap() {
+///         Messages::Welcome(welcome) => println!("joined as session {}", welcome.session),
+///         message => panic!("the router did not welcome us: {:?}", message),
+///     }
+///
+///     let mut subscription = client.subscribe("com.example.topic").await.unwrap().unwrap();
+///     let event = subscription.next().await.unwrap();
+///     println!("first event: {}", event.args);
+/// }
+/// ```
+#[derive(Clone)]
+pub struct Client {
+    sink: Arc<Mutex<SplitSink<Socket, Message>>>,
+    routes: Arc<StdMutex<Routes>>,
+    inbox: Arc<Mutex<mpsc::UnboundedReceiver<Messages>>>,
+    serializer: Serializer,
+}
+
+impl Client {
+    /// Connect to the router and start the task reading the socket. Must be called from
+    /// within a tokio runtime.
+    pub async fn connect<U: ToString, P: ToString>(
+        request: WampRequest<U, P>,
+    ) -> Result<(Client, Response<Option<Vec<u8>>>), Error> {
+        let (socket, response) = connect_async(request.into_client_request()?).await?;
+        let (sink, stream) = socket.split();
+        let (inbox_sender, inbox) = mpsc::unbounded_channel();
+        let client = Client {
+            sink: Arc::new(Mutex::new(sink)),
+            routes: Arc::default(),
+            inbox: Arc::new(Mutex::new(inbox)),
+            serializer: Serializer::from_response(&response),
+        };
+        ::tokio::spawn(client.clone().route(stream, inbox_sender));
+        Ok((client, response))
+    }
+
+    pub async fn send<T: TryInto<Message>>(&self, message: T) -> Result<(), Error>
+    where
+        Error: From<<T as TryInto<Message>>::Error>,
+    {
+        let message = self.serializer.encode(message.try_into()?)?;
+        Ok(self.sink.lock().await.send(message).await?)
+    }
+
+    /// # Tokio Client Read
+    /// The next frame that is not the reply to a request, an event of a `Subscription` or an
+    /// invocation of a registered procedure.
+    pub async fn read(&self) -> Result<Messages, Error> {
+        self.inbox
+            .lock()
+            .await
+            .recv()
+            .await
+            .ok_or(Error::ConnectionClosed)
+    }
+
+    /// Subscribe to `topic`, resolving once the router confirms the subscription, or answers
+    /// with an `Error`.
+    pub async fn subscribe<T: ToString>(
+        &self,
+        topic: T,
+    ) -> Result<Result<Subscription, WampError>, Error> {
+        let subscribe = subscribe!(topic.to_string());
+        let (events, receiver) = mpsc::unbounded_channel();
+        match self
+            .request(subscribe.request_id, subscribe, |reply| {
+                Waiter::Subscribe(reply, events)
+            })
+            .await?
+        {
+            Messages::Subscribed(subscribed) => Ok(Ok(Subscription {
+                subscribed,
+                events: receiver,
+                routes: self.routes.clone(),
+            })),
+            Messages::Error(error) => Ok(Err(error)),
+            message => Err(Error::InvalidFrameReceived(message)),
+        }
+    }
+
+    /// Call a procedure, resolving with its final result, or the error it failed with.
+    /// Progressive results are returned by `read`.
+    pub async fn call(&self, call: Call) -> Result<Result<WampResult, WampError>, Error> {
+        match self.request(call.request_id, call, Waiter::Reply).await? {
+            Messages::Result(result) => Ok(Ok(result)),
+            Messages::Error(error) => Ok(Err(error)),
+            message => Err(Error::InvalidFrameReceived(message)),
+        }
+    }
+
+    /// Call `procedure` with `args` and `kwargs`. See `Client::call`.
+    pub async fn call_procedure<P: ToString>(
+        &self,
+        procedure: P,
+        args: Value,
+        kwargs: Value,
+    ) -> Result<Result<WampResult, WampError>, Error> {
+        let mut call = call!(procedure.to_string());
+        call.args = args;
+        call.kwargs = kwargs;
+        self.call(call).await
+    }
+
+    /// Publish with `acknowledge` set, resolving once the router confirms the publication.
+    pub async fn publish(&self, mut publish: Publish) -> Result<Result<Published, WampError>, Error> {
+        if !publish.options.is_object() {
+            publish.options = json!({});
+        }
+        publish.options["acknowledge"] = json!(true);
+        match self.request(publish.request_id, publish, Waiter::Reply).await? {
+            Messages::Published(published) => Ok(Ok(published)),
+            Messages::Error(error) => Ok(Err(error)),
+            message => Err(Error::InvalidFrameReceived(message)),
+        }
+    }
+
+    /// Publish `args` on `topic`. See `Client::publish`.
+    pub async fn publish_args<T: ToString>(
+        &self,
+        topic: T,
+        args: Value,
+    ) -> Result<Result<Published, WampError>, Error> {
+        let mut publish = publish!(topic.to_string());
+        publish.args = args;
+        self.publish(publish).await
+    }
+
+    /// # Tokio Client Register
+    /// Register `procedure`, resolving once the router confirms the registration, and answer
+    /// its invocations with `handler`.
+    ///
+    /// `Ok(yield)` is sent back as is, and `Err(error)` as an `Error` answering the
+    /// invocation, with its event and request id filled in. The handler runs on the task
+    /// reading the socket, so it should return quickly.
+    pub async fn register<P: ToString>(
+        &self,
+        procedure: P,
+        handler: impl FnMut(Invocation) -> Result<Yield, WampError> + Send + 'static,
+    ) -> Result<Result<Registered, WampError>, Error> {
+        let register = register!(procedure.to_string());
+        let handler: Handler = Box::new(handler);
+        match self
+            .request(register.request_id, register, |reply| {
+                Waiter::Register(reply, handler)
+            })
+            .await?
+        {
+            Messages::Registered(registered) => Ok(Ok(registered)),
+            Messages::Error(error) => Ok(Err(error)),
+            message => Err(Error::InvalidFrameReceived(message)),
+        }
+    }
+
+    /// Send a request and wait for the reply carrying its request id.
+    async fn request<T: TryInto<Message>>(
+        &self,
+        request_id: u64,
+        message: T,
+        waiter: impl FnOnce(oneshot::Sender<Messages>) -> Waiter,
+    ) -> Result<Messages, Error>
+    where
+        Error: From<<T as TryInto<Message>>::Error>,
+    {
+        let (reply, replied) = oneshot::channel();
+        self.routes()
+            .waiters
+            .insert(request_id, waiter(reply));
+        if let Err(error) = self.send(message).await {
+            self.routes().waiters.remove(&request_id);
+            return Err(error);
+        }
+        // The reading task drops every waiter once the connection is gone.
+        replied.await.map_err(|_| Error::ConnectionClosed)
+    }
+
+    fn routes(&self) -> std::sync::MutexGuard<'_, Routes> {
+        self.routes.lock().unwrap_or_else(PoisonError::into_inner)
+    }
+
+    /// Read the socket until it closes, routing each frame.
+    async fn route(self, mut stream: SplitStream<Socket>, inbox: mpsc::UnboundedSender<Messages>) {
+        while let Some(Ok(frame)) = stream.next().await {
+            let message = match self.serializer.decode(frame) {
+                Ok(Some(text)) => match from_str::<Messages>(&text) {
+                    Ok(message) => message,
+                    Err(_) => continue,
+                },
+                _ => continue,
+            };
+            if let Some(message) = self.dispatch(message).await {
+                // Nobody reading the inbox is not a reason to stop routing replies.
+                let _ = inbox.send(message);
+            }
+        }
+        let mut routes = self.routes();
+        routes.waiters.clear();
+        routes.subscriptions.clear();
+    }
+
+    /// Hand a frame to whoever waits for it, returning it if nobody does.
+    async fn dispatch(&self, message: Messages) -> Option<Messages> {
+        match message {
+            Messages::Event(event) => {
+                let routes = self.routes();
+                match routes.subscriptions.get(&event.subscription) {
+                    // A `Subscription` dropped since this event was read no longer receives it.
+                    Some(events) => {
+                        let _ = events.send(event);
+                        None
+                    }
+                    None => Some(Messages::from(event)),
+                }
+            }
+            Messages::Invocation(invocation) => {
+                let reply = {
+                    let mut routes = self.routes();
+                    let request_id = invocation.request_id;
+                    match routes.registrations.get_mut(&invocation.registration) {
+                        Some(handler) => match handler(invocation) {
+                            Ok(r#yield) => Messages::from(r#yield),
+                            Err(mut error) => {
+                                error.event = WampErrorEvent::Invocation;
+                                error.request_id = request_id;
+                                Messages::from(error)
+                            }
+                        },
+                        None => return Some(Messages::from(invocation)),
+                    }
+                };
+                // A failed send means the socket is gone, which ends the reading task.
+                let _ = self.send(reply).await;
+                None
+            }
+            // Interrupts carry the id of an invocation, not of a request of ours.
+            Messages::Interrupt(interrupt) => Some(Messages::from(interrupt)),
+            Messages::Result(result) if is_progress(&result) => Some(Messages::from(result)),
+            message => {
+                let mut routes = self.routes();
+                let waiter = match reply_request_id(&message) {
+                    Some(request_id) => routes.waiters.remove(&request_id),
+                    None => None,
+                };
+                let reply = match waiter {
+                    Some(Waiter::Reply(reply)) => reply,
+                    Some(Waiter::Subscribe(reply, events)) => {
+                        if let Messages::Subscribed(subscribed) = &message {
+                            routes.subscriptions.insert(subscribed.subscription, events);
+                        }
+                        reply
+                    }
+                    Some(Waiter::Register(reply, handler)) => {
+                        if let Messages::Registered(registered) = &message {
+                            routes.registrations.insert(registered.registration, handler);
+                        }
+                        reply
+                    }
+                    None => return Some(message),
+                };
+                // The request future was dropped, so nobody wants the reply.
+                let _ = reply.send(message);
+                None
+            }
+        }
+    }
+}
+
+/// Whether a `Result` is a progressive result, with more results to follow for the same call.
+fn is_progress(result: &WampResult) -> bool {
+    result
+        .details
+        .get("progress")
+        .and_then(Value::as_bool)
+        .unwrap_or(false)
+}
+
+/// # Tokio Subscription
+/// The events of a topic subscribed to with `Client::subscribe`.
+///
+/// Dropping it stops routing the events of the subscription, so any still arriving are returned
+/// by `Client::read`. It does not unsubscribe.
+///
+/// ## Examples
+/// ```
+/// use std::thread;
+/// use wamp_client::sync::WampRequest;
+/// use wamp_client::test_util::MockRouter;
+/// use wamp_client::tokio::client::Client;
+/// use wamp_core::messages::Messages;
+/// use wamp_core::serde_json::json;
+/// use wamp_core::{hello, publish};
+///
+/// #[tokio::main(flavor = "current_thread")]
+/// async fn main() {
+///     // Not `MockRouter::spawn`, which waits on the client: the router hangs up once done.
+///     let (uri, listener) = MockRouter::listen();
+///     let router = thread::spawn(move || {
+///         let mut router = MockRouter::accept(&listener);
+///         router.welcome();
+///
+///         let subscribe = router.recv();
+///         router.send(json!([33, subscribe[1], 5]));
+///         router.send(r#"[36,5,1,{},["a"]]"#);
+///
+///         // Sent once the subscription was dropped.
+///         let publish = router.recv();
+///         assert_eq!(publish[2]["acknowledge"], true);
+///         router.send(r#"[36,5,2,{},["b"]]"#);
+///         router.send(json!([17, publish[1], 99]));
+///     });
+///
+///     let (client, _) = Client::connect(WampRequest { uri, protocol: "wamp.2.json" })
+///         .await
+///         .unwrap();
+///     client.send(hello!("realm1")).await.unwrap();
+///     assert!(matches!(client.read().await.unwrap(), Messages::Welcome(_)));
+///
+///     let mut subscription = client.subscribe("com.example.topic").await.unwrap().unwrap();
+///     assert_eq!(subscription.next().await.unwrap().args, json!(["a"]));
+///     drop(subscription);
+///
+///     // Options that are not a map are replaced rather than indexed into.
+///     let mut notice = publish!("com.example.topic");
+///     notice.options = json!([]);
+///     assert_eq!(client.publish(notice).await.unwrap().unwrap().publication, 99);
+///
+///     match client.read().await.unwrap() {
+///         Messages::Event(event) => assert_eq!(event.args, json!(["b"])),
+///         message => panic!("expected the unrouted event, got {:?}", message),
+///     }
+///     router.join().unwrap();
+/// }
+/// ```
+pub struct Subscription {
+    pub subscribed: Subscribed,
+    events: mpsc::UnboundedReceiver<Event>,
+    routes: Arc<StdMutex<Routes>>,
+}
+
+impl Subscription {
+    /// The next event, `None` once the connection is gone.
+    pub async fn next(&mut self) -> Option<Event> {
+        self.events.recv().await
+    }
+}
+
+impl Drop for Subscription {
+    fn drop(&mut self) {
+        self.routes
+            .lock()
+            .unwrap_or_else(PoisonError::into_inner)
+            .subscriptions
+            .remove(&self.subscribed.subscription);
+    }
+}